@@ -1,6 +1,5 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn;
 
 /// add derive(Debug, Clone) to struct
 /// impl as_any for struct
@@ -26,6 +25,9 @@ pub fn ast_node(
                     fn token_literal(&self) -> String {
                         self.token.literal.clone()
                     }
+                    fn span(&self) -> crate::token::Span {
+                        self.token.span
+                    }
                     fn as_any(&self) -> &dyn Any {
                         self
                     }
@@ -39,6 +41,9 @@ pub fn ast_node(
                     fn token_literal(&self) -> String {
                         self.token.literal.clone()
                     }
+                    fn span(&self) -> crate::token::Span {
+                        self.token.span
+                    }
                     fn as_any(&self) -> &dyn Any {
                         self
                     }
@@ -60,6 +65,9 @@ pub fn ast_node(
                     fn token_literal(&self) -> String {
                         self.token.literal.clone()
                     }
+                    fn span(&self) -> crate::token::Span {
+                        self.token.span
+                    }
                     fn as_any(&self) -> &dyn Any {
                         self
                     }
@@ -128,6 +136,93 @@ pub fn object(args: TokenStream, input: TokenStream) -> TokenStream {
     TokenStream::from(s)
 }
 
+fn named_fields(ipt: &syn::DeriveInput) -> syn::Result<Vec<syn::Ident>> {
+    match &ipt.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => Ok(fields
+            .named
+            .iter()
+            .map(|f| f.ident.clone().unwrap())
+            .collect()),
+        _ => Err(syn::Error::new_spanned(
+            &ipt.ident,
+            "FromObject/IntoObject can only be derived for structs with named fields",
+        )),
+    }
+}
+
+/// 每个类型参数都要实现 bound，比如 derive(FromObject) 的 struct Pair<T> 要求 T: FromObject
+fn bounded_generics(generics: &syn::Generics, bound: syn::TypeParamBound) -> syn::Generics {
+    let mut generics = generics.clone();
+    generics
+        .type_params_mut()
+        .for_each(|p| p.bounds.push(bound.clone()));
+    generics
+}
+
+// 生成的代码都用完整的路径，不依赖使用的地方 import 了什么
+// 解释器自己的 crate 里通过 `extern crate self as my_rust_interpreter` 用同样的路径
+
+/// impl FromObject for struct, 从以字段名为 key 的 HASH 里取值
+#[proc_macro_derive(FromObject)]
+pub fn derive_from_object(input: TokenStream) -> TokenStream {
+    let ipt = syn::parse_macro_input!(input as syn::DeriveInput);
+    let name = &ipt.ident;
+    let fields = match named_fields(&ipt) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let keys = fields.iter().map(|f| f.to_string());
+    let generics = bounded_generics(
+        &ipt.generics,
+        syn::parse_quote!(::my_rust_interpreter::object::FromObject),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let s = quote! {
+        impl #impl_generics ::my_rust_interpreter::object::FromObject for #name #ty_generics #where_clause {
+            fn from_object(
+                obj: &::std::rc::Rc<dyn ::my_rust_interpreter::object::Object>,
+            ) -> ::std::result::Result<Self, ::my_rust_interpreter::object::ConversionError> {
+                ::std::result::Result::Ok(#name {
+                    #( #fields: ::my_rust_interpreter::object::object_field(obj, #keys)?, )*
+                })
+            }
+        }
+    };
+    TokenStream::from(s)
+}
+
+/// impl IntoObject for struct, 转成以字段名为 key 的 HASH
+#[proc_macro_derive(IntoObject)]
+pub fn derive_into_object(input: TokenStream) -> TokenStream {
+    let ipt = syn::parse_macro_input!(input as syn::DeriveInput);
+    let name = &ipt.ident;
+    let fields = match named_fields(&ipt) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let keys = fields.iter().map(|f| f.to_string());
+    let generics = bounded_generics(
+        &ipt.generics,
+        syn::parse_quote!(::my_rust_interpreter::object::IntoObject),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let s = quote! {
+        impl #impl_generics ::my_rust_interpreter::object::IntoObject for #name #ty_generics #where_clause {
+            fn into_object(self) -> ::std::rc::Rc<dyn ::my_rust_interpreter::object::Object> {
+                ::my_rust_interpreter::object::fields_into_object(::std::vec![
+                    #( (#keys, ::my_rust_interpreter::object::IntoObject::into_object(self.#fields)), )*
+                ])
+            }
+        }
+    };
+    TokenStream::from(s)
+}
+
 // #[proc_macro]
 // macro_rules! hashmap {
 //     ($( $key: expr => $val: expr ),*) => {{
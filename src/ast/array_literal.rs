@@ -24,7 +24,7 @@ impl std::fmt::Display for ArrayLiteral {
 
 #[cfg(test)]
 mod test {
-    use crate::{ArrayLiteral, IntegerLiteral, Token, LBRACKET};
+    use crate::{ArrayLiteral, IntegerLiteral, Span, Token, LBRACKET};
     use std::rc::Rc;
 
     #[test]
@@ -34,6 +34,8 @@ mod test {
             token: Token {
                 token_type: LBRACKET,
                 literal: "[".into(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             elements: vec![
                 Rc::new(IntegerLiteral::try_from("1".to_string()).unwrap()),
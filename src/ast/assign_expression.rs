@@ -0,0 +1,19 @@
+use crate::ast::*;
+use crate::token::Token;
+use std::rc::Rc;
+
+/// x = 1, x += 1 或者 a[0] = 1
+/// target 只会是 Identifier 或者 IndexExpression，parser 负责检查
+#[ast_node(Expression)]
+pub struct AssignExpression {
+    pub token: Token,
+    pub operator: String,
+    pub target: Rc<dyn Expression>,
+    pub value: Rc<dyn Expression>,
+}
+
+impl std::fmt::Display for AssignExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {} {})", self.target, self.operator, self.value)
+    }
+}
@@ -0,0 +1,58 @@
+use crate::ast::*;
+use crate::token::*;
+use num_bigint::BigInt;
+
+/// 带 n 后缀的整数字面量，比如 123n、0xffffffffffffffffn，大小不受 64 位的限制
+#[ast_node(Expression)]
+pub struct BigIntegerLiteral {
+    pub token: Token,
+    pub value: BigInt,
+}
+
+impl TryFrom<String> for BigIntegerLiteral {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (radix, digits) = split_radix(value.strip_suffix('n').unwrap_or(&value));
+        match BigInt::parse_bytes(digits.as_bytes(), radix) {
+            // parse_bytes 允许开头的正负号，字面量里不应该有
+            Some(v) if !digits.starts_with(['+', '-']) => Ok(BigIntegerLiteral {
+                token: Token {
+                    token_type: BIGINT,
+                    literal: value,
+                    span: Span::default(),
+                    leading_trivia: vec![],
+                },
+                value: v,
+            }),
+            _ => Err(format!("can not parse {} into BigIntegerLiteral", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for BigIntegerLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}n", self.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::BigIntegerLiteral;
+
+    #[test]
+    fn test_big_integer_literal_try_from() {
+        let cases = [
+            ("123n", "123n"),
+            ("0xffffffffffffffffn", "18446744073709551615n"),
+            ("0b101n", "5n"),
+            ("0o17n", "15n"),
+            ("99999999999999999999n", "99999999999999999999n"),
+        ];
+        cases.iter().for_each(|&(input, display)| {
+            let r = BigIntegerLiteral::try_from(input.to_string());
+            assert_eq!(format!("{}", r.unwrap()), display);
+        });
+        assert!(BigIntegerLiteral::try_from("0xn".to_string()).is_err());
+    }
+}
@@ -32,14 +32,17 @@ impl std::fmt::Display for BooleanLiteral {
 
 #[cfg(test)]
 mod test {
-    use {crate::ast::BooleanLiteral, crate::token::Token, crate::token::TRUE};
+    use {crate::ast::BooleanLiteral, crate::token::Span, crate::token::Token, crate::token::TRUE};
 
+    #[allow(clippy::bool_assert_comparison)]
     #[test]
     fn test_bool_literal() {
         let s = BooleanLiteral {
             token: Token {
                 token_type: TRUE,
                 literal: "true".into(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             value: true,
         };
@@ -0,0 +1,13 @@
+use crate::ast::*;
+use crate::token::*;
+
+#[ast_node(Statement)]
+pub struct BreakStatement {
+    pub token: Token,
+}
+
+impl std::fmt::Display for BreakStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{};", self.token_literal())
+    }
+}
@@ -12,7 +12,7 @@ impl std::fmt::Display for CallExpression {
         write!(
             f,
             "{}",
-            format!(
+            format_args!(
                 "{}({})",
                 self.function
                     .as_ref()
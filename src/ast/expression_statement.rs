@@ -1,5 +1,5 @@
 use crate::ast::*;
-use crate::token::{Token, EOF};
+use crate::token::{Span, Token, EOF};
 
 use std::rc::Rc;
 
@@ -12,7 +12,6 @@ pub struct ExpressionStatement {
 impl TryFrom<Box<&dyn Statement>> for ExpressionStatement {
     type Error = String;
     fn try_from(value: Box<&dyn Statement>) -> Result<Self, Self::Error> {
-        println!("cast Statement to Expression: {:?}", value.as_any());
         if let Some(v) = value.as_any().downcast_ref::<Self>() {
             let mut ex = None;
             if v.expression.is_some() {
@@ -33,21 +32,13 @@ impl TryFrom<Box<&dyn Statement>> for ExpressionStatement {
 impl TryFrom<Box<&dyn Expression>> for ExpressionStatement {
     type Error = String;
     fn try_from(value: Box<&dyn Expression>) -> Result<Self, Self::Error> {
-        println!(
-            "casting dyn Expression to ExpressionStatement, {:?}",
-            *value
-        );
-        let x = (value).as_any(); // .downcast_ref::<Self>();
-        println!(
-            "casting dyn Expression to ExpressionStatement result: {:?}",
-            x.downcast_ref::<Self>()
-        );
-
         let mut ex = None;
         let mut did_match = false;
         let mut token: Token = Token {
             token_type: EOF,
             literal: "".into(),
+            span: Span::default(),
+            leading_trivia: vec![],
         };
 
         let v_any = value.as_any();
@@ -106,7 +97,6 @@ impl TryFrom<Box<&dyn Expression>> for ExpressionStatement {
         }
         if v_any.is::<IntegerLiteral>() {
             did_match = true;
-            println!("\n\n v_any is IntLiteral: {:?}\n\n", v_any);
             if let Some(v) = value.as_any().downcast_ref::<IntegerLiteral>() {
                 ex = Some(Rc::new(IntegerLiteral {
                     token: v.token.clone(),
@@ -118,7 +108,6 @@ impl TryFrom<Box<&dyn Expression>> for ExpressionStatement {
         }
         if v_any.is::<PrefixExpression>() {
             did_match = true;
-            println!("\n\nv_any is PrefixExpression\n\n");
             // FIXME: do here first
             if let Some(v) = value.as_any().downcast_ref::<PrefixExpression>() {
                 ex = Some(Rc::new(PrefixExpression {
@@ -133,7 +122,6 @@ impl TryFrom<Box<&dyn Expression>> for ExpressionStatement {
         }
         if v_any.is::<InfixExpression>() {
             did_match = true;
-            println!("\n\nv_any is InfixExpression\n\n");
             if let Some(val) = value.as_any().downcast_ref::<InfixExpression>() {
                 token = val.token.clone();
                 ex = Some(Rc::new(val.clone()))
@@ -164,7 +152,7 @@ impl TryFrom<Box<&dyn Expression>> for ExpressionStatement {
         if did_match {
             assert_ne!(token.token_type, EOF);
             Ok(ExpressionStatement {
-                token: token,
+                token,
                 expression: ex,
             })
         } else {
@@ -184,7 +172,7 @@ impl std::fmt::Display for ExpressionStatement {
 
 #[cfg(test)]
 mod test {
-    use crate::{ast::ExpressionStatement, token::Token, token::EOF};
+    use crate::{ast::ExpressionStatement, token::Span, token::Token, token::EOF};
 
     #[test]
     fn test_to_string() {
@@ -192,6 +180,8 @@ mod test {
             token: Token {
                 token_type: EOF,
                 literal: ";".into(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             expression: None,
         };
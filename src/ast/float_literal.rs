@@ -0,0 +1,56 @@
+use crate::ast::{Expression, Node, *};
+/// 浮点数字面量，比如 1.5、2e10、1.5e-3
+///
+use crate::token::*;
+
+#[ast_node(Expression)]
+pub struct FloatLiteral {
+    pub token: Token,
+    pub value: f64,
+}
+
+impl TryFrom<String> for FloatLiteral {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Ok(v) = value.parse::<f64>() {
+            return Ok(FloatLiteral {
+                token: Token {
+                    token_type: FLOAT,
+                    literal: value,
+                    span: Span::default(),
+                    leading_trivia: vec![],
+                },
+                value: v,
+            });
+        }
+        Err(format!("can not parse {} into FloatLiteral", value))
+    }
+}
+
+impl std::fmt::Display for FloatLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::FloatLiteral;
+
+    #[test]
+    fn test_float_literal_try_from() {
+        let cases = [
+            ("1.5", 1.5, "1.5"),
+            ("2e3", 2000.0, "2000.0"),
+            ("1.5e-3", 0.0015, "0.0015"),
+        ];
+        cases.iter().for_each(|&(input, out, display)| {
+            let r = FloatLiteral::try_from(input.to_string());
+            assert!(r.is_ok());
+            let r = r.unwrap();
+            assert_eq!(r.value, out);
+            assert_eq!(format!("{r}"), display);
+        });
+    }
+}
@@ -0,0 +1,23 @@
+use crate::ast::*;
+use crate::token::*;
+use std::rc::Rc;
+
+/// for (variable in iterable) { body }
+#[ast_node(Statement)]
+pub struct ForStatement {
+    pub token: Token,
+    pub variable: Rc<Identifier>,
+    pub iterable: Rc<dyn Expression>,
+    // BlockStatement
+    pub body: Rc<dyn Statement>,
+}
+
+impl std::fmt::Display for ForStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "for ({} in {}) {}",
+            self.variable, self.iterable, self.body
+        )
+    }
+}
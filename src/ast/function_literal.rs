@@ -13,12 +13,19 @@ pub struct FunctionLiteral {
     // pub body: Option<BlockStatement>,
 }
 
+impl FunctionLiteral {
+    /// 写在前面的 /// 文档注释
+    pub fn doc(&self) -> Option<String> {
+        self.token.doc_comment()
+    }
+}
+
 impl std::fmt::Display for FunctionLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "{}",
-            format!(
+            format_args!(
                 "{} {}({}) {}",
                 self.token_literal(),
                 format!(
@@ -30,7 +37,7 @@ impl std::fmt::Display for FunctionLiteral {
                 (*self
                     .parameters
                     .as_ref()
-                    .map_or_else(|| vec![], |v| v.to_vec()))
+                    .map_or_else(std::vec::Vec::new, |v| v.to_vec()))
                 .iter()
                 .map(|v| v.to_string())
                 .collect::<Vec<String>>()
@@ -47,6 +54,7 @@ impl std::fmt::Display for FunctionLiteral {
 mod test {
     use crate::*;
 
+    #[allow(clippy::to_string_in_format_args)]
     #[test]
     fn test_function_literal_to_string() {
         let input = r#"fn z (x, y, z) {  }"#;
@@ -59,22 +67,26 @@ mod test {
         //     token: Rc::new(RefCell::new(Token {
         //         token_type: FUNCTION,
         //         literal: "fn".into(),
+        //         leading_trivia: vec![],
         //     })),
         //     parameters: Rc::new(RefCell::new(vec![])),
         //     body: Rc::new(RefCell::new(BlockStatement {
         //         token: Rc::new(RefCell::new(Token {
         //             token_type: EOF,
         //             literal: '\0'.into(),
+        //             leading_trivia: vec![],
         //         })),
         //         statement: Rc::new(RefCell::new(vec![Rc::new(LetStatement {
         //             token: Rc::new(RefCell::new(Token {
         //                 token_type: EOF,
         //                 literal: '\0'.into(),
+        //                 leading_trivia: vec![],
         //             })),
         //             name: Box::new(Identifier {
         //                 token: Rc::new(RefCell::new(Token {
         //                     token_type: IDENT,
         //                     literal: 'a'.into(),
+        //                     leading_trivia: vec![],
         //                 })),
         //                 value: 'a'.into(),
         //             }),
@@ -82,6 +94,7 @@ mod test {
         //                 token: Rc::new(RefCell::new(Token {
         //                     token_type: EOF,
         //                     literal: '\0'.into(),
+        //                     leading_trivia: vec![],
         //                 })),
         //                 value: 5,
         //             }),
@@ -1,16 +1,16 @@
 use crate::ast::*;
-use crate::{Object, ObjectInspect, Token};
-use ast_macro::*;
+use crate::Token;
 use std::any::Any;
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::rc::Rc;
 
+pub type HashLiteralPair = (Rc<dyn Expression>, Rc<dyn Expression>);
+
 #[ast_node(Expression)]
 pub struct HashLiteral {
     pub token: Token,
-    // due to HashMap cannot be Eq PartialEq Hash, so we are unable to put Rc<dyn Expression> to key
-    pub pairs: RefCell<HashMap<Rc<String>, Rc<dyn Expression>>>,
+    // key 是任意表达式，要到 eval 的时候才知道能不能做 key，所以这里按源码顺序存成列表
+    pub pairs: RefCell<Vec<HashLiteralPair>>,
 }
 
 impl std::fmt::Display for HashLiteral {
@@ -18,7 +18,7 @@ impl std::fmt::Display for HashLiteral {
         write!(
             f,
             "{}",
-            format!(
+            format_args!(
                 "{{ {} }}",
                 self.pairs
                     .borrow()
@@ -2,13 +2,40 @@ use crate::ast::{Node, *};
 use crate::token::*;
 
 #[ast_node(Expression)]
-#[derive(Hash, Eq, PartialEq)]
 pub struct Identifier {
     pub token: Token,
 
     pub value: String,
 }
 
+// Identifier 是 Context 里的 key，只按名字比较，不同位置出现的同名标识符是同一个变量
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+impl Eq for Identifier {}
+impl std::hash::Hash for Identifier {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+// 宿主代码按名字读写变量时用，没有源码位置
+impl From<&str> for Identifier {
+    fn from(name: &str) -> Self {
+        Identifier {
+            token: Token {
+                token_type: IDENT,
+                literal: name.to_string(),
+                span: Span::default(),
+                leading_trivia: vec![],
+            },
+            value: name.to_string(),
+        }
+    }
+}
+
 impl TryFrom<Box<&dyn Expression>> for Identifier {
     type Error = String;
 
@@ -44,7 +71,7 @@ impl std::fmt::Display for Identifier {
     }
 }
 
-#[allow(unused)]
+#[allow(unused, clippy::redundant_allocation)]
 pub(crate) fn test_identifier_expression(exp: Box<&dyn Statement>, value: String) -> bool {
     let stm = ExpressionStatement::try_from(exp);
 
@@ -9,6 +9,8 @@ pub struct IfExpression {
 
     // FIXME: BlockStatement
     pub consequence: Option<Rc<dyn Statement /* BlockStatement */>>,
+    // else if 的分支按顺序平铺在这里，不嵌套新的 IfExpression
+    pub else_ifs: Vec<(Rc<dyn Expression>, Rc<dyn Statement /* BlockStatement */>)>,
     // FIXME: BlockStatement
     pub alternative: Option<Rc<dyn Statement /* BlockStatement */>>,
 }
@@ -17,17 +19,17 @@ impl std::fmt::Display for IfExpression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}",
-            format!(
-                "if {} {} {}",
-                self.condition,
-                self.consequence.as_ref().unwrap(),
-                self.alternative.as_ref().map_or_else(
-                    || "".into(),
-                    |val| format!("else {}", val.as_ref().to_string())
-                )
-            )
-        )
+            "if ({}) {}",
+            self.condition,
+            self.consequence.as_ref().unwrap()
+        )?;
+        for (condition, consequence) in &self.else_ifs {
+            write!(f, " else if ({}) {}", condition, consequence)?;
+        }
+        if let Some(alternative) = &self.alternative {
+            write!(f, " else {}", alternative)?;
+        }
+        Ok(())
     }
 }
 impl TryFrom<Box<&dyn Expression>> for IfExpression {
@@ -7,17 +7,20 @@ pub struct IndexExpression {
     pub token: Token,
     pub left: Rc<dyn Expression>,
     pub index: Rc<dyn Expression>,
+    // a?[b] 或者 a?.b，左边是 null 时整个表达式就是 null
+    pub optional: bool,
 }
 
 impl std::fmt::Display for IndexExpression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}[{}])", self.left, self.index)
+        let open = if self.optional { "?[" } else { "[" };
+        write!(f, "({}{}{}])", self.left, open, self.index)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Identifier, IndexExpression, Token, LPAREN};
+    use crate::{Identifier, IndexExpression, Span, Token, LPAREN};
     use std::rc::Rc;
 
     #[test]
@@ -26,11 +29,15 @@ mod test {
             token: Token {
                 token_type: LPAREN,
                 literal: "".into(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             left: Rc::new(Identifier {
                 token: Token {
                     token_type: LPAREN,
                     literal: "a".into(),
+                    span: Span::default(),
+                    leading_trivia: vec![],
                 },
                 value: "a".to_string(),
             }),
@@ -38,9 +45,12 @@ mod test {
                 token: Token {
                     token_type: LPAREN,
                     literal: "a".into(),
+                    span: Span::default(),
+                    leading_trivia: vec![],
                 },
                 value: "a".to_string(),
             }),
+            optional: false,
         };
         dbg!(&x);
         assert_eq!(format!("{}", x), "(a[a])");
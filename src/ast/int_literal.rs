@@ -16,26 +16,14 @@ impl TryFrom<String> for IntegerLiteral {
     type Error = String;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let mut radix = 10;
-        let mut v = value.clone();
-        if value.starts_with("0x") || value.starts_with("0X") {
-            radix = 16;
-            v = value.replace("0x", "").replace("0X", "");
-        }
-        if value.starts_with("0b") || value.starts_with("0B") {
-            radix = 2;
-            v = value.replace("0b", "").replace("0B", "");
-        }
-        if value.starts_with("0o") || value.starts_with("0O") {
-            radix = 8;
-            v = value.replace("0o", "").replace("0O", "");
-        }
-
-        if let Ok(v) = i64::from_str_radix(&v, radix) {
+        let (radix, v) = split_radix(&value);
+        if let Ok(v) = i64::from_str_radix(v, radix) {
             return Ok(IntegerLiteral {
                 token: Token {
                     token_type: INT,
                     literal: value,
+                    span: Span::default(),
+                    leading_trivia: vec![],
                 },
                 value: v,
             });
@@ -44,11 +32,21 @@ impl TryFrom<String> for IntegerLiteral {
     }
 }
 
+/// 去掉 0x、0b、0o 前缀，返回进制和剩下的数字
+pub fn split_radix(literal: &str) -> (u32, &str) {
+    let radix = match literal.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0b" | "0B") => 2,
+        Some("0o" | "0O") => 8,
+        _ => return (10, literal),
+    };
+    (radix, &literal[2..])
+}
+
 impl TryFrom<Box<&ExpressionStatement>> for IntegerLiteral {
     type Error = String;
 
     fn try_from(value: Box<&ExpressionStatement>) -> Result<Self, Self::Error> {
-        println!("the try from: {:?}", value);
         if value.token.token_type == INT {
             return Ok(IntegerLiteral {
                 token: value.token.clone(),
@@ -67,7 +65,6 @@ impl TryFrom<Box<&ExpressionStatement>> for IntegerLiteral {
 impl TryFrom<Box<&dyn Expression>> for IntegerLiteral {
     type Error = String;
     fn try_from(value: Box<&dyn Expression>) -> Result<Self, Self::Error> {
-        println!("wtf wtf: {:?}", value);
         let x = value.as_any();
         if x.is::<Self>() {
             // println!("x is IntegerLiteral {:?}", x);
@@ -82,7 +79,6 @@ impl TryFrom<Box<&dyn Expression>> for IntegerLiteral {
         //     let x = x.downcast_ref::<PrefixExpression>().unwrap();
         //     if x.operator == "-" || x.operator == "+" {}
         // }
-        println!("{}", format!("Cannot cast {:?} into IntegerLiteral", value));
         Err(format!("Cannot cast {:?} into IntegerLiteral", value))
     }
 }
@@ -93,11 +89,12 @@ impl std::fmt::Display for IntegerLiteral {
     }
 }
 
+#[allow(clippy::items_after_test_module)]
 #[cfg(test)]
 mod test {
     use crate::{
         ast::IntegerLiteral,
-        token::{Token, INT},
+        token::{Span, Token, INT},
     };
 
     #[test]
@@ -106,12 +103,15 @@ mod test {
             token: Token {
                 literal: "5".into(),
                 token_type: INT,
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             value: 5,
         };
         assert_eq!(format!("{s}"), "5");
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_int_literal_try_from() {
         let cases = vec![("1", 1), ("-1", -1)];
@@ -126,7 +126,7 @@ mod test {
 }
 
 use std::ops::{Add, Mul};
-#[allow(dead_code)]
+#[allow(dead_code, clippy::redundant_allocation)]
 pub(crate) fn test_literal_expression<N>(exp: Box<&dyn Expression>, expected: N) -> bool
 where
     N: Add<Output = N> + Mul<Output = N> + Default + Copy + Display,
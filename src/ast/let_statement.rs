@@ -10,6 +10,13 @@ pub struct LetStatement {
     pub value: Option<Rc<dyn Expression>>,
 }
 
+impl LetStatement {
+    /// 写在前面的 /// 文档注释
+    pub fn doc(&self) -> Option<String> {
+        self.token.doc_comment()
+    }
+}
+
 impl TryFrom<Box<&dyn Statement>> for LetStatement {
     type Error = String;
     fn try_from(value: Box<&dyn Statement>) -> Result<Self, Self::Error> {
@@ -28,9 +35,7 @@ impl TryFrom<Box<&dyn Expression>> for LetStatement {
     fn try_from(value: Box<&dyn Expression>) -> Result<Self, Self::Error> {
         let x = (value).as_any();
         if x.is::<LetStatement>() {
-            let x = x.downcast_ref::<LetStatement>();
-            if x.is_some() {
-                let x = x.unwrap();
+            if let Some(x) = x.downcast_ref::<LetStatement>() {
                 return Ok(LetStatement {
                     token: x.token.clone(),
                     name: x.name.clone(),
@@ -40,10 +45,10 @@ impl TryFrom<Box<&dyn Expression>> for LetStatement {
                     )), //x.value.clone(),
                 });
             } else {
-                return Err(format!(""));
+                return Err(String::new());
             }
         }
-        Err(format!(""))
+        Err(String::new())
     }
 }
 impl std::fmt::Display for LetStatement {
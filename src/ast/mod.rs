@@ -4,13 +4,20 @@ use std::{
     fmt::{Debug, Display},
 };
 
+#[allow(clippy::module_inception)]
 mod test;
 
 mod array_literal;
+pub mod assign_expression;
+pub mod big_integer_literal;
 pub mod block_statement;
 pub mod bool_literal;
+pub mod break_statement;
 pub mod call_expression;
+pub mod continue_statement;
 pub mod expression_statement;
+pub mod float_literal;
+pub mod for_statement;
 pub mod function_literal;
 pub mod hash_literal;
 pub mod identifier;
@@ -19,16 +26,27 @@ mod index_expression;
 pub mod infix_expression;
 pub mod int_literal;
 pub mod let_statement;
+pub mod null_literal;
 pub mod prefix_expression;
 pub mod program;
 pub mod return_statement;
 pub mod string_literal;
+pub mod template_literal;
+pub mod throw_statement;
+pub mod try_statement;
+pub mod while_statement;
 
 pub use array_literal::*;
+pub use assign_expression::*;
+pub use big_integer_literal::*;
 pub use block_statement::*;
 pub use bool_literal::*;
+pub use break_statement::*;
 pub use call_expression::*;
+pub use continue_statement::*;
 pub use expression_statement::*;
+pub use float_literal::*;
+pub use for_statement::*;
 pub use function_literal::*;
 pub use hash_literal::*;
 pub use identifier::*;
@@ -37,14 +55,21 @@ pub use index_expression::*;
 pub use infix_expression::*;
 pub use int_literal::*;
 pub use let_statement::*;
+pub use null_literal::*;
 pub use prefix_expression::*;
 pub use program::*;
 pub use return_statement::*;
 pub use string_literal::*;
+pub use template_literal::*;
+pub use throw_statement::*;
+pub use try_statement::*;
+pub use while_statement::*;
 
 pub trait Node: Debug + Display {
     fn token_literal(&self) -> String;
     fn as_any(&self) -> &dyn Any;
+    /// 节点在源码中的位置，取自节点的 token
+    fn span(&self) -> crate::token::Span;
 }
 
 pub trait Statement: Node {
@@ -0,0 +1,13 @@
+use crate::ast::*;
+use crate::token::*;
+
+#[ast_node(Expression)]
+pub struct NullLiteral {
+    pub token: Token,
+}
+
+impl std::fmt::Display for NullLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "null")
+    }
+}
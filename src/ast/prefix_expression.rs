@@ -16,7 +16,6 @@ impl TryFrom<Box<&dyn Expression>> for PrefixExpression {
     fn try_from(value: Box<&dyn Expression>) -> Result<Self, Self::Error> {
         let x = value.as_any();
         if x.is::<PrefixExpression>() {
-            println!("Object is PrefixExpression {:?}", value);
             let x = x.downcast_ref::<PrefixExpression>().unwrap();
             return Ok(PrefixExpression {
                 token: x.token.clone(),
@@ -43,12 +42,16 @@ mod test {
             token: Token {
                 literal: "".into(),
                 token_type: "",
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             operator: "-".into(),
             right: Some(Rc::new(IntegerLiteral {
                 token: Token {
                     literal: "".into(),
                     token_type: "",
+                    span: Span::default(),
+                    leading_trivia: vec![],
                 },
                 value: 5,
             })),
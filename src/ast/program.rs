@@ -9,7 +9,7 @@ pub struct Program {
 
 impl Node for Program {
     fn token_literal(&self) -> String {
-        if self.statement.len() > 0 {
+        if !self.statement.is_empty() {
             self.statement[0].token_literal().clone()
         } else {
             "".into()
@@ -18,6 +18,15 @@ impl Node for Program {
     fn as_any(&self) -> &dyn Any {
         self
     }
+    fn span(&self) -> crate::token::Span {
+        match (self.statement.first(), self.statement.last()) {
+            (Some(first), Some(last)) => crate::token::Span {
+                end: last.span().end,
+                ..first.span()
+            },
+            _ => Default::default(),
+        }
+    }
 }
 
 impl std::fmt::Display for Program {
@@ -27,7 +36,7 @@ impl std::fmt::Display for Program {
             .iter()
             .fold("".to_string(), |acc, b| format!("{acc}{b}"));
         // println!("\n\nProgram::Display: {}\n\n", &x);
-        write!(f, "{}", format!("{x}"))
+        write!(f, "{x}")
     }
 }
 
@@ -16,6 +16,8 @@ impl TryFrom<String> for StringLiteral {
             token: Token {
                 token_type: STRING,
                 literal: value.clone(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             value: Rc::new(value),
         })
@@ -26,7 +28,6 @@ impl TryFrom<Box<&ExpressionStatement>> for StringLiteral {
     type Error = String;
 
     fn try_from(value: Box<&ExpressionStatement>) -> Result<Self, Self::Error> {
-        println!("the try from: {:?}", value);
         if value.token.token_type == STRING {
             return Ok(Self {
                 token: value.token.clone(),
@@ -45,7 +46,6 @@ impl TryFrom<Box<&ExpressionStatement>> for StringLiteral {
 impl TryFrom<Box<&dyn Expression>> for StringLiteral {
     type Error = String;
     fn try_from(value: Box<&dyn Expression>) -> Result<Self, Self::Error> {
-        println!("wtf wtf: {:?}", value);
         let x = value.as_any();
         if x.is::<Self>() {
             // println!("x is IntegerLiteral {:?}", x);
@@ -65,6 +65,7 @@ impl std::fmt::Display for StringLiteral {
     }
 }
 
+#[allow(clippy::items_after_test_module)]
 #[cfg(test)]
 mod test {
     use crate::ast::string_literal::StringLiteral;
@@ -76,6 +77,8 @@ mod test {
             token: Token {
                 literal: "5".into(),
                 token_type: STRING,
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             value: Rc::new("5".into()),
         };
@@ -85,7 +88,7 @@ mod test {
 
 use std::ops::{Add, Mul};
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::redundant_allocation)]
 pub(crate) fn test_literal_expression<N>(exp: Box<&dyn Expression>, expected: N) -> bool
 where
     N: Add<Output = N> + Mul<Output = N> + Default + Copy + Display,
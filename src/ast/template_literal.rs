@@ -0,0 +1,52 @@
+use crate::ast::*;
+use crate::token::*;
+use std::rc::Rc;
+
+/// 模板字符串的一段
+#[derive(Debug, Clone)]
+pub enum TemplatePart {
+    Text(Rc<String>),
+    Expression(Rc<dyn Expression>),
+}
+
+/// "hello ${name}"，求值时把每一段拼成一个字符串
+#[ast_node(Expression)]
+pub struct TemplateLiteral {
+    pub token: Token,
+    pub parts: Vec<TemplatePart>,
+}
+
+impl std::fmt::Display for TemplateLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"")?;
+        for part in self.parts.iter() {
+            match part {
+                TemplatePart::Text(s) => write!(f, "{}", s)?,
+                TemplatePart::Expression(e) => write!(f, "${{{}}}", e)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_template_literal_display() {
+        let t = TemplateLiteral {
+            token: Token {
+                token_type: TEMPLATE,
+                literal: "a ${b}".into(),
+                span: Span::default(),
+                leading_trivia: vec![],
+            },
+            parts: vec![
+                TemplatePart::Text(Rc::new("a ".into())),
+                TemplatePart::Expression(Rc::new(Identifier::from("b"))),
+            ],
+        };
+        assert_eq!(format!("{}", t), "\"a ${b}\"");
+    }
+}
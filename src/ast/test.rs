@@ -28,12 +28,16 @@ mod test {
             token: Token {
                 token_type: INT,
                 literal: "1".into(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             operator: "-".into(),
             right: Some(Rc::new(IntegerLiteral {
                 token: Token {
                     token_type: INT,
                     literal: "1".into(),
+                    span: Span::default(),
+                    leading_trivia: vec![],
                 },
                 value: 1,
             })),
@@ -49,6 +53,8 @@ mod test {
             token: Token {
                 token_type: INT,
                 literal: "5".to_string(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
         };
     }
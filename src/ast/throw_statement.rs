@@ -0,0 +1,16 @@
+use crate::ast::*;
+use crate::token::*;
+use std::rc::Rc;
+
+/// throw value
+#[ast_node(Statement)]
+pub struct ThrowStatement {
+    pub token: Token,
+    pub value: Rc<dyn Expression>,
+}
+
+impl std::fmt::Display for ThrowStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "throw {};", self.value)
+    }
+}
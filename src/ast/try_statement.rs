@@ -0,0 +1,31 @@
+use crate::ast::*;
+use crate::token::*;
+use std::rc::Rc;
+
+/// try { body } catch (param) { catch_body } finally { finally_body }
+/// catch 和 finally 至少有一个，catch 的参数可以省略
+#[ast_node(Statement)]
+pub struct TryStatement {
+    pub token: Token,
+    // BlockStatement
+    pub body: Rc<dyn Statement>,
+    pub catch_param: Option<Rc<Identifier>>,
+    pub catch_body: Option<Rc<dyn Statement>>,
+    pub finally_body: Option<Rc<dyn Statement>>,
+}
+
+impl std::fmt::Display for TryStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "try {}", self.body)?;
+        if let Some(catch_body) = self.catch_body.as_ref() {
+            match self.catch_param.as_ref() {
+                Some(param) => write!(f, " catch ({}) {}", param, catch_body)?,
+                None => write!(f, " catch {}", catch_body)?,
+            }
+        }
+        if let Some(finally_body) = self.finally_body.as_ref() {
+            write!(f, " finally {}", finally_body)?;
+        }
+        Ok(())
+    }
+}
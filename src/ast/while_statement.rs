@@ -0,0 +1,18 @@
+use crate::ast::*;
+use crate::token::*;
+use std::rc::Rc;
+
+/// while (condition) { body }
+#[ast_node(Statement)]
+pub struct WhileStatement {
+    pub token: Token,
+    pub condition: Rc<dyn Expression>,
+    // BlockStatement
+    pub body: Rc<dyn Statement>,
+}
+
+impl std::fmt::Display for WhileStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "while {} {}", self.condition, self.body)
+    }
+}
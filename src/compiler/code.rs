@@ -0,0 +1,274 @@
+/// 字节码指令的定义
+/// 每条指令由一个字节的操作码加上若干个大端序的操作数组成
+pub type Instructions = Vec<u8>;
+pub type Opcode = u8;
+
+pub const OP_CONSTANT: Opcode = 0;
+pub const OP_POP: Opcode = 1;
+
+pub const OP_ADD: Opcode = 2;
+pub const OP_SUB: Opcode = 3;
+pub const OP_MUL: Opcode = 4;
+pub const OP_DIV: Opcode = 5;
+
+pub const OP_TRUE: Opcode = 6;
+pub const OP_FALSE: Opcode = 7;
+pub const OP_NULL: Opcode = 8;
+
+pub const OP_EQUAL: Opcode = 9;
+pub const OP_NOT_EQUAL: Opcode = 10;
+pub const OP_GREATER_THAN: Opcode = 11;
+pub const OP_LESS_THAN: Opcode = 12;
+
+pub const OP_MINUS: Opcode = 13;
+pub const OP_BANG: Opcode = 14;
+
+pub const OP_JUMP_NOT_TRUTHY: Opcode = 15;
+pub const OP_JUMP: Opcode = 16;
+
+pub const OP_GET_GLOBAL: Opcode = 17;
+pub const OP_SET_GLOBAL: Opcode = 18;
+pub const OP_GET_LOCAL: Opcode = 19;
+pub const OP_SET_LOCAL: Opcode = 20;
+pub const OP_GET_BUILTIN: Opcode = 21;
+pub const OP_GET_FREE: Opcode = 22;
+
+pub const OP_ARRAY: Opcode = 23;
+pub const OP_HASH: Opcode = 24;
+pub const OP_INDEX: Opcode = 25;
+
+pub const OP_CALL: Opcode = 26;
+pub const OP_RETURN_VALUE: Opcode = 27;
+pub const OP_RETURN: Opcode = 28;
+
+// 闭包创建前，先把要捕获的变量格子压到 VM 的 captures 栈上
+pub const OP_CAPTURE_LOCAL: Opcode = 29;
+pub const OP_CAPTURE_FREE: Opcode = 30;
+pub const OP_CLOSURE: Opcode = 31;
+
+pub const OP_BIT_AND: Opcode = 32;
+pub const OP_BIT_OR: Opcode = 33;
+pub const OP_BIT_XOR: Opcode = 34;
+pub const OP_POW: Opcode = 35;
+
+// for 循环：OpIter 把栈顶的值换成迭代器，OpIterNext 取下一个元素，取完了就跳到操作数的位置
+pub const OP_ITER: Opcode = 36;
+pub const OP_ITER_NEXT: Opcode = 37;
+
+// 赋值：OpSetFree 写闭包捕获的格子；OpSetIndex 的操作数是复合赋值对应的运算指令，0 表示普通赋值
+pub const OP_SET_FREE: Opcode = 38;
+pub const OP_SET_INDEX: Opcode = 39;
+
+// 模板字符串：把栈顶的若干段拼成一个字符串
+pub const OP_TEMPLATE: Opcode = 40;
+
+// null 相关的跳转，都只看栈顶不弹出：
+// OpJumpNull 在栈顶是 null 时跳转，用于 a?[b]；OpJumpNotNull 在栈顶不是 null 时跳转，否则弹出栈顶，用于 ??
+pub const OP_JUMP_NULL: Opcode = 41;
+pub const OP_JUMP_NOT_NULL: Opcode = 42;
+
+// try/catch：OpTry 登记一个错误处理位置，OpEndTry 撤销最近登记的那个；
+// 出错时 VM 回退到 OpTry 时的栈和调用帧，把错误压栈后跳到处理位置
+// OpThrow 把栈顶的值作为错误抛出
+pub const OP_TRY: Opcode = 43;
+pub const OP_END_TRY: Opcode = 44;
+pub const OP_THROW: Opcode = 45;
+
+pub const OP_MOD: Opcode = 46;
+pub const OP_LESS_EQUAL: Opcode = 47;
+pub const OP_GREATER_EQUAL: Opcode = 48;
+// 一元的 ~ 和 +
+pub const OP_BIT_NOT: Opcode = 49;
+pub const OP_PLUS: Opcode = 50;
+// catch (e)：把栈顶的错误转成 catch 到的 hash，没有参数的 catch 和 finally 拿到的是错误本身，可以原样再抛出
+pub const OP_CATCH: Opcode = 51;
+// hash 字面量：每个 key 求值后马上检查能不能作为 key，只看不弹出，和 evaluator 一样在 value 求值之前报错
+pub const OP_HASH_KEY: Opcode = 52;
+// 和 evaluator 一样在运行时才报的错误：
+// OpCheckGlobal 在全局变量还没有声明时报 assignment to undeclared variable；
+// OpOutsideLoop 报循环外的 break（操作数 0）或者 continue（操作数 1），在函数里时位置是调用它的地方
+pub const OP_CHECK_GLOBAL: Opcode = 53;
+pub const OP_OUTSIDE_LOOP: Opcode = 54;
+// 局部变量在 let 之前还没有值，这时和 evaluator 一样去外层找：
+// 操作数是跳转的位置和格子的下标，格子里还没有值时跳转
+pub const OP_JUMP_UNSET_LOCAL: Opcode = 55;
+pub const OP_JUMP_UNSET_FREE: Opcode = 56;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Definition {
+    pub name: &'static str,
+    pub operand_widths: &'static [usize],
+}
+
+pub fn lookup_definition(op: Opcode) -> Option<Definition> {
+    let (name, operand_widths): (&'static str, &'static [usize]) = match op {
+        OP_CONSTANT => ("OpConstant", &[2]),
+        OP_POP => ("OpPop", &[]),
+        OP_ADD => ("OpAdd", &[]),
+        OP_SUB => ("OpSub", &[]),
+        OP_MUL => ("OpMul", &[]),
+        OP_DIV => ("OpDiv", &[]),
+        OP_TRUE => ("OpTrue", &[]),
+        OP_FALSE => ("OpFalse", &[]),
+        OP_NULL => ("OpNull", &[]),
+        OP_EQUAL => ("OpEqual", &[]),
+        OP_NOT_EQUAL => ("OpNotEqual", &[]),
+        OP_GREATER_THAN => ("OpGreaterThan", &[]),
+        OP_LESS_THAN => ("OpLessThan", &[]),
+        OP_MINUS => ("OpMinus", &[]),
+        OP_BANG => ("OpBang", &[]),
+        OP_JUMP_NOT_TRUTHY => ("OpJumpNotTruthy", &[2]),
+        OP_JUMP => ("OpJump", &[2]),
+        OP_GET_GLOBAL => ("OpGetGlobal", &[2]),
+        OP_SET_GLOBAL => ("OpSetGlobal", &[2]),
+        OP_GET_LOCAL => ("OpGetLocal", &[1]),
+        OP_SET_LOCAL => ("OpSetLocal", &[1]),
+        OP_GET_BUILTIN => ("OpGetBuiltin", &[2]),
+        OP_GET_FREE => ("OpGetFree", &[1]),
+        OP_ARRAY => ("OpArray", &[2]),
+        OP_HASH => ("OpHash", &[2]),
+        OP_INDEX => ("OpIndex", &[]),
+        OP_CALL => ("OpCall", &[1]),
+        OP_RETURN_VALUE => ("OpReturnValue", &[]),
+        OP_RETURN => ("OpReturn", &[]),
+        OP_CAPTURE_LOCAL => ("OpCaptureLocal", &[1]),
+        OP_CAPTURE_FREE => ("OpCaptureFree", &[1]),
+        OP_CLOSURE => ("OpClosure", &[2, 1]),
+        OP_BIT_AND => ("OpBitAnd", &[]),
+        OP_BIT_OR => ("OpBitOr", &[]),
+        OP_BIT_XOR => ("OpBitXor", &[]),
+        OP_POW => ("OpPow", &[]),
+        OP_ITER => ("OpIter", &[]),
+        OP_ITER_NEXT => ("OpIterNext", &[2]),
+        OP_SET_FREE => ("OpSetFree", &[1]),
+        OP_SET_INDEX => ("OpSetIndex", &[1]),
+        OP_TEMPLATE => ("OpTemplate", &[2]),
+        OP_JUMP_NULL => ("OpJumpNull", &[2]),
+        OP_JUMP_NOT_NULL => ("OpJumpNotNull", &[2]),
+        OP_TRY => ("OpTry", &[2]),
+        OP_END_TRY => ("OpEndTry", &[]),
+        OP_THROW => ("OpThrow", &[]),
+        OP_MOD => ("OpMod", &[]),
+        OP_LESS_EQUAL => ("OpLessEqual", &[]),
+        OP_GREATER_EQUAL => ("OpGreaterEqual", &[]),
+        OP_BIT_NOT => ("OpBitNot", &[]),
+        OP_PLUS => ("OpPlus", &[]),
+        OP_CATCH => ("OpCatch", &[]),
+        OP_HASH_KEY => ("OpHashKey", &[]),
+        OP_CHECK_GLOBAL => ("OpCheckGlobal", &[2]),
+        OP_OUTSIDE_LOOP => ("OpOutsideLoop", &[1]),
+        OP_JUMP_UNSET_LOCAL => ("OpJumpUnsetLocal", &[2, 1]),
+        OP_JUMP_UNSET_FREE => ("OpJumpUnsetFree", &[2, 1]),
+        _ => return None,
+    };
+    Some(Definition {
+        name,
+        operand_widths,
+    })
+}
+
+/// 操作数要放得下它的宽度，编译器 emit 之前先用 check_operands 检查
+/// ```
+/// use my_rust_interpreter::compiler::*;
+/// assert_eq!(make(OP_CONSTANT, &[65534]), vec![OP_CONSTANT, 255, 254]);
+/// ```
+pub fn make(op: Opcode, operands: &[usize]) -> Instructions {
+    let def = match lookup_definition(op) {
+        Some(def) => def,
+        None => return vec![],
+    };
+    let mut instruction = vec![op];
+    def.operand_widths
+        .iter()
+        .zip(operands.iter())
+        .for_each(|(width, &operand)| match width {
+            2 => instruction.extend_from_slice(&(operand as u16).to_be_bytes()),
+            1 => instruction.push(operand as u8),
+            _ => (),
+        });
+    instruction
+}
+
+/// 检查每个操作数能不能放进它的宽度，放不下时直接截断会变成别的下标或者跳到指令中间
+/// ```
+/// use my_rust_interpreter::compiler::*;
+/// assert!(check_operands(OP_GET_LOCAL, &[255]).is_ok());
+/// assert_eq!(
+///     check_operands(OP_GET_LOCAL, &[256]),
+///     Err("operand 256 of OpGetLocal does not fit in 1 byte".to_string())
+/// );
+/// ```
+pub fn check_operands(op: Opcode, operands: &[usize]) -> Result<(), String> {
+    let Some(def) = lookup_definition(op) else {
+        return Err(format!("opcode {} undefined", op));
+    };
+    def.operand_widths
+        .iter()
+        .zip(operands.iter())
+        .try_for_each(|(width, &operand)| {
+            if operand < 1 << (width * 8) {
+                return Ok(());
+            }
+            let bytes = if *width == 1 { "byte" } else { "bytes" };
+            Err(format!(
+                "operand {} of {} does not fit in {} {}",
+                operand, def.name, width, bytes
+            ))
+        })
+}
+
+/// 读出一条指令的所有操作数，同时返回读取的字节数
+pub fn read_operands(def: &Definition, ins: &[u8]) -> (Vec<usize>, usize) {
+    let mut offset = 0;
+    let operands = def
+        .operand_widths
+        .iter()
+        .map(|width| {
+            let operand = match width {
+                2 => read_u16(&ins[offset..]) as usize,
+                1 => read_u8(&ins[offset..]) as usize,
+                _ => 0,
+            };
+            offset += width;
+            operand
+        })
+        .collect();
+    (operands, offset)
+}
+
+pub fn read_u16(ins: &[u8]) -> u16 {
+    u16::from_be_bytes([ins[0], ins[1]])
+}
+
+pub fn read_u8(ins: &[u8]) -> u8 {
+    ins[0]
+}
+
+/// 把字节码反汇编成可读的文本，一行一条指令
+pub fn disassemble(ins: &[u8]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < ins.len() {
+        let def = match lookup_definition(ins[i]) {
+            Some(def) => def,
+            None => {
+                out.push_str(&format!("ERROR: opcode {} undefined\n", ins[i]));
+                i += 1;
+                continue;
+            }
+        };
+        let (operands, read) = read_operands(&def, &ins[i + 1..]);
+        let operands = operands
+            .iter()
+            .map(|o| o.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if operands.is_empty() {
+            out.push_str(&format!("{:04} {}\n", i, def.name));
+        } else {
+            out.push_str(&format!("{:04} {} {}\n", i, def.name, operands));
+        }
+        i += 1 + read;
+    }
+    out
+}
@@ -0,0 +1,1018 @@
+use crate::compiler::*;
+use crate::evaluator::*;
+use num_bigint::BigInt;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// 编译的产物：主程序的指令和每个字节对应的源码位置、常量池，以及全局变量的名字（用于报错）
+#[derive(Debug, Clone)]
+pub struct Bytecode {
+    pub instructions: Instructions,
+    pub spans: Vec<Span>,
+    pub constants: Vec<Rc<dyn Object>>,
+    pub global_names: Vec<String>,
+}
+
+/// 常量池里可以复用的字面量，按类型和值区分，函数不复用
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Integer(i64),
+    // 按位比较，这样 0.0 和 -0.0 是两个常量
+    Float(u64),
+    BigInteger(BigInt),
+    String(Rc<String>),
+}
+
+impl ConstantKey {
+    fn of(obj: &Rc<dyn Object>) -> Option<Self> {
+        let obj = obj.as_any();
+        if let Some(v) = obj.downcast_ref::<Integer>() {
+            return Some(ConstantKey::Integer(v.value));
+        }
+        if let Some(v) = obj.downcast_ref::<Float>() {
+            return Some(ConstantKey::Float(v.value.to_bits()));
+        }
+        if let Some(v) = obj.downcast_ref::<BigInteger>() {
+            return Some(ConstantKey::BigInteger(v.value.clone()));
+        }
+        if let Some(v) = obj.downcast_ref::<StringObject>() {
+            return Some(ConstantKey::String(v.value.clone()));
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EmittedInstruction {
+    opcode: Opcode,
+    position: usize,
+}
+
+/// 正在编译的循环：continue 跳回的位置，以及等循环结束后回填的 break 跳转
+#[derive(Debug, Default)]
+struct LoopScope {
+    continue_target: usize,
+    breaks: Vec<usize>,
+}
+
+/// 正在编译的 try：break、continue 和 return 跳出去之前要撤销它登记的错误处理，再执行 finally
+#[derive(Debug, Clone)]
+struct TryScope {
+    finally: Option<Rc<dyn Statement>>,
+    // 进入 try 时外面有几层循环
+    loops: usize,
+}
+
+/// 每个函数体编译在自己的 scope 里，编译完再整体弹出
+#[derive(Debug, Default)]
+struct CompilationScope {
+    instructions: Instructions,
+    spans: Vec<Span>,
+    last_instruction: Option<EmittedInstruction>,
+    previous_instruction: Option<EmittedInstruction>,
+    loops: Vec<LoopScope>,
+    tries: Vec<TryScope>,
+}
+
+/// 把 AST 编译成给 vm::Vm 执行的字节码
+///
+/// ```
+/// use my_rust_interpreter::*;
+/// let p = Parser::new(Lexer::new("1 + 2"));
+/// let pr = p.parse_program().unwrap();
+/// let mut c = Compiler::new();
+/// c.compile(&pr).unwrap();
+/// let r = Vm::new(c.bytecode()).run().unwrap();
+/// assert_eq!(r.inspect(), "3");
+/// ```
+#[derive(Debug)]
+pub struct Compiler {
+    constants: Vec<Rc<dyn Object>>,
+    globals: Rc<SymbolTable>,
+    symbol_table: Rc<SymbolTable>,
+    scopes: Vec<CompilationScope>,
+    // 正在编译的最里层有位置的节点，emit 的指令都记在这个位置上
+    span: Span,
+    // 正在编译的程序最外层的语句
+    statement: Span,
+    // 字面量常量在常量池里的位置，同样的字面量只放一份
+    constant_indexes: HashMap<ConstantKey, usize>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        let globals = Rc::new(SymbolTable::new());
+        Compiler {
+            constants: vec![],
+            symbol_table: globals.clone(),
+            globals,
+            scopes: vec![CompilationScope::default()],
+            span: Span::default(),
+            statement: Span::default(),
+            constant_indexes: HashMap::new(),
+        }
+    }
+    pub fn bytecode(&self) -> Bytecode {
+        Bytecode {
+            instructions: self.current_instructions().clone(),
+            spans: self.scopes.last().unwrap().spans.clone(),
+            constants: self.constants.clone(),
+            global_names: self.globals.names(),
+        }
+    }
+    /// 和 evaluator 一样，指令出错时报告产生它的最里层的有位置的节点
+    pub fn compile(&mut self, node: &dyn Node) -> Result<(), String> {
+        let span = node.span();
+        if span.line == 0 {
+            return self.compile_node(node);
+        }
+        let outer = std::mem::replace(&mut self.span, span);
+        let result = self.compile_node(node);
+        self.span = outer;
+        result
+    }
+
+    fn compile_node(&mut self, node: &dyn Node) -> Result<(), String> {
+        let n = node.as_any();
+        if let Some(n) = n.downcast_ref::<Program>() {
+            for st in n.statement.iter() {
+                self.statement = st.span();
+                self.compile(st.upcast())?;
+            }
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<ExpressionStatement>() {
+            if let Some(ref ex) = n.expression {
+                self.compile(ex.upcast())?;
+                self.emit(OP_POP, &[])?;
+            }
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<BlockStatement>() {
+            for st in n.statement.iter() {
+                self.compile(st.upcast())?;
+            }
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<LetStatement>() {
+            return self.compile_let_statement(n);
+        }
+        if let Some(n) = n.downcast_ref::<ReturnStatement>() {
+            match n.return_value {
+                Some(ref v) => self.compile(v.upcast())?,
+                None => {
+                    self.emit(OP_NULL, &[])?;
+                }
+            }
+            self.leave_tries(0)?;
+            self.emit(OP_RETURN_VALUE, &[])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<AssignExpression>() {
+            return self.compile_assign_expression(n);
+        }
+        if let Some(n) = n.downcast_ref::<WhileStatement>() {
+            return self.compile_while_statement(n);
+        }
+        if let Some(n) = n.downcast_ref::<ForStatement>() {
+            return self.compile_for_statement(n);
+        }
+        if let Some(n) = n.downcast_ref::<TryStatement>() {
+            return self.compile_try_statement(n);
+        }
+        if let Some(n) = n.downcast_ref::<ThrowStatement>() {
+            self.compile(n.value.upcast())?;
+            self.emit(OP_THROW, &[])?;
+            return Ok(());
+        }
+        if n.is::<BreakStatement>() {
+            if self.scopes.last().unwrap().loops.is_empty() {
+                return self.compile_outside_loop(0);
+            }
+            self.leave_loop_tries()?;
+            let jump = self.emit(OP_JUMP, &[9999])?;
+            let lp = self.scopes.last_mut().unwrap().loops.last_mut().unwrap();
+            lp.breaks.push(jump);
+            return Ok(());
+        }
+        if n.is::<ContinueStatement>() {
+            let Some(lp) = self.scopes.last().unwrap().loops.last() else {
+                return self.compile_outside_loop(1);
+            };
+            let target = lp.continue_target;
+            self.leave_loop_tries()?;
+            self.emit(OP_JUMP, &[target])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<IntegerLiteral>() {
+            let c = self.add_constant(Rc::new(Integer { value: n.value }))?;
+            self.emit(OP_CONSTANT, &[c])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<FloatLiteral>() {
+            let c = self.add_constant(Rc::new(Float { value: n.value }))?;
+            self.emit(OP_CONSTANT, &[c])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<BigIntegerLiteral>() {
+            let c = self.add_constant(Rc::new(BigInteger {
+                value: n.value.clone(),
+            }))?;
+            self.emit(OP_CONSTANT, &[c])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<StringLiteral>() {
+            let c = self.add_constant(Rc::new(StringObject {
+                value: n.value.clone(),
+            }))?;
+            self.emit(OP_CONSTANT, &[c])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<TemplateLiteral>() {
+            for part in n.parts.iter() {
+                match part {
+                    TemplatePart::Text(s) => {
+                        let c = self.add_constant(Rc::new(StringObject { value: s.clone() }))?;
+                        self.emit(OP_CONSTANT, &[c])?;
+                    }
+                    TemplatePart::Expression(e) => self.compile(e.upcast())?,
+                }
+            }
+            self.emit(OP_TEMPLATE, &[n.parts.len()])?;
+            return Ok(());
+        }
+        if n.is::<NullLiteral>() {
+            self.emit(OP_NULL, &[])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<BooleanLiteral>() {
+            self.emit(if n.value { OP_TRUE } else { OP_FALSE }, &[])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<PrefixExpression>() {
+            let right = n
+                .right
+                .as_ref()
+                .ok_or_else(|| format!("missing operand: {}", n.operator))?;
+            self.compile(right.upcast())?;
+            match n.operator.as_str() {
+                "!" => self.emit(OP_BANG, &[])?,
+                "-" => self.emit(OP_MINUS, &[])?,
+                "+" => self.emit(OP_PLUS, &[])?,
+                "~" => self.emit(OP_BIT_NOT, &[])?,
+                op => return Err(format!("unknown operator: {}", op)),
+            };
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<InfixExpression>() {
+            return self.compile_infix_expression(n);
+        }
+        if let Some(n) = n.downcast_ref::<IfExpression>() {
+            return self.compile_if_expression(n);
+        }
+        if let Some(n) = n.downcast_ref::<Identifier>() {
+            return self.load_identifier(&n.value);
+        }
+        if let Some(n) = n.downcast_ref::<ArrayLiteral>() {
+            for el in n.elements.iter() {
+                self.compile(el.upcast())?;
+            }
+            self.emit(OP_ARRAY, &[n.elements.len()])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<HashLiteral>() {
+            let pairs = n.pairs.borrow();
+            for (k, v) in pairs.iter() {
+                self.compile(k.upcast())?;
+                self.emit_at(k.span(), OP_HASH_KEY, &[])?;
+                self.compile(v.upcast())?;
+            }
+            self.emit(OP_HASH, &[pairs.len() * 2])?;
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<IndexExpression>() {
+            self.compile(n.left.upcast())?;
+            // a?[b]：a 是 null 时直接把这个 null 当作结果
+            let jump_null = if n.optional {
+                Some(self.emit(OP_JUMP_NULL, &[9999])?)
+            } else {
+                None
+            };
+            self.compile(n.index.upcast())?;
+            self.emit(OP_INDEX, &[])?;
+            if let Some(pos) = jump_null {
+                let end = self.current_instructions().len();
+                self.change_operand(pos, end)?;
+            }
+            return Ok(());
+        }
+        if let Some(n) = n.downcast_ref::<FunctionLiteral>() {
+            return self.compile_function_literal(n, None);
+        }
+        if let Some(n) = n.downcast_ref::<CallExpression>() {
+            let f = n
+                .function
+                .as_ref()
+                .ok_or_else(|| format!("missing function: {}", n))?;
+            self.compile(f.upcast())?;
+            let args = n.arguments.clone().unwrap_or_default();
+            for arg in args.iter() {
+                self.compile(arg.upcast())?;
+            }
+            self.emit(OP_CALL, &[args.len()])?;
+            return Ok(());
+        }
+        Err(format!("cannot compile node: {}", node))
+    }
+
+    fn compile_let_statement(&mut self, n: &LetStatement) -> Result<(), String> {
+        // LetStatement.value 是包了一层的 ExpressionStatement
+        let value =
+            n.value
+                .as_ref()
+                .and_then(|v| match v.as_any().downcast_ref::<ExpressionStatement>() {
+                    Some(es) => es.expression.clone(),
+                    None => Some(v.clone()),
+                });
+        let value = match value {
+            Some(v) => v,
+            None => {
+                self.emit(OP_NULL, &[])?;
+                let symbol = self.symbol_table.define(&n.name.value);
+                self.store_definition(&symbol)?;
+                return Ok(());
+            }
+        };
+        // 函数先占位再编译，这样函数体里能递归引用自己
+        if let Some(f) = value.as_any().downcast_ref::<FunctionLiteral>() {
+            let symbol = self.symbol_table.define(&n.name.value);
+            self.compile_function_literal(f, Some(&n.name.value))?;
+            self.store_definition(&symbol)?;
+        } else {
+            self.compile(value.upcast())?;
+            let symbol = self.symbol_table.define(&n.name.value);
+            self.store_definition(&symbol)?;
+        }
+        Ok(())
+    }
+
+    fn compile_infix_expression(&mut self, n: &InfixExpression) -> Result<(), String> {
+        let (left, right) = match (n.left.as_ref(), n.right.as_ref()) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return Err(format!("missing operand: {}", n.operator)),
+        };
+        if n.operator == LOGICAND || n.operator == LOGICOR {
+            return self.compile_logical_expression(&n.operator, left, right);
+        }
+        if n.operator == NULLISH {
+            self.compile(left.upcast())?;
+            let jump_to_end = self.emit(OP_JUMP_NOT_NULL, &[9999])?;
+            self.compile(right.upcast())?;
+            let end = self.current_instructions().len();
+            self.change_operand(jump_to_end, end)?;
+            return Ok(());
+        }
+        let op = infix_opcode(&n.operator)?;
+        self.compile(left.upcast())?;
+        self.compile(right.upcast())?;
+        self.emit(op, &[])?;
+        Ok(())
+    }
+
+    /// 赋值之后再把新值读出来，作为整个表达式的值留在栈上
+    fn compile_assign_expression(&mut self, n: &AssignExpression) -> Result<(), String> {
+        let operator = n.operator.trim_end_matches('=');
+        let op = match operator {
+            "" => None,
+            o => Some(infix_opcode(o)?),
+        };
+        if let Some(id) = n.target.as_any().downcast_ref::<Identifier>() {
+            // 和读取一样，找不到的名字先当成还没赋值的全局变量，还没有 let 的局部变量运行时跳过；
+            // 全局变量在运行时才知道有没有声明过，先检查再求右边的值
+            let mut unset = self.symbol_table.resolve_all(&id.value);
+            let symbol = match unset.last() {
+                Some(s) if self.symbol_table.is_assigned(s) => unset.pop().unwrap(),
+                _ => self.globals.define(&id.value),
+            };
+            let check: fn(&mut Self, &Symbol) -> Result<(), String> = match op {
+                Some(_) => Self::load_symbol,
+                None => |_, _| Ok(()),
+            };
+            self.emit_fallbacks(&unset, check, |c| {
+                if symbol.scope == SymbolScope::Global {
+                    c.emit(OP_CHECK_GLOBAL, &[symbol.index])?;
+                }
+                check(c, &symbol)
+            })?;
+            self.compile(n.value.upcast())?;
+            if let Some(op) = op {
+                self.emit(op, &[])?;
+            }
+            self.emit_fallbacks(&unset, Self::store_symbol, |c| c.store_symbol(&symbol))?;
+            return self.emit_fallbacks(&unset, Self::load_symbol, |c| c.load_symbol(&symbol));
+        }
+        if let Some(ix) = n.target.as_any().downcast_ref::<IndexExpression>() {
+            self.compile(ix.left.upcast())?;
+            self.compile(ix.index.upcast())?;
+            self.compile(n.value.upcast())?;
+            self.emit(OP_SET_INDEX, &[op.unwrap_or(0) as usize])?;
+            return Ok(());
+        }
+        Err(format!("invalid assignment target: {}", n.target))
+    }
+
+    /// && 和 || 短路求值，结果总是 Boolean，和 evaluator 一致：
+    /// 左边能决定结果时直接跳过右边
+    fn compile_logical_expression(
+        &mut self,
+        operator: &str,
+        left: &Rc<dyn Expression>,
+        right: &Rc<dyn Expression>,
+    ) -> Result<(), String> {
+        self.compile(left.upcast())?;
+        let mut jump_to_false = vec![];
+        let mut jump_to_end = vec![];
+        if operator == LOGICAND {
+            jump_to_false.push(self.emit(OP_JUMP_NOT_TRUTHY, &[9999])?);
+        } else {
+            let jump_to_right = self.emit(OP_JUMP_NOT_TRUTHY, &[9999])?;
+            self.emit(OP_TRUE, &[])?;
+            jump_to_end.push(self.emit(OP_JUMP, &[9999])?);
+            let right_start = self.current_instructions().len();
+            self.change_operand(jump_to_right, right_start)?;
+        }
+        self.compile(right.upcast())?;
+        jump_to_false.push(self.emit(OP_JUMP_NOT_TRUTHY, &[9999])?);
+        self.emit(OP_TRUE, &[])?;
+        jump_to_end.push(self.emit(OP_JUMP, &[9999])?);
+
+        let false_start = self.current_instructions().len();
+        jump_to_false
+            .into_iter()
+            .try_for_each(|pos| self.change_operand(pos, false_start))?;
+        self.emit(OP_FALSE, &[])?;
+        let end = self.current_instructions().len();
+        jump_to_end
+            .into_iter()
+            .try_for_each(|pos| self.change_operand(pos, end))?;
+        Ok(())
+    }
+
+    /// else if 的每个分支都是一次条件跳转，走完分支后统一跳到整个表达式的结尾
+    fn compile_if_expression(&mut self, n: &IfExpression) -> Result<(), String> {
+        let branches = std::iter::once((&n.condition, n.consequence.as_ref().unwrap()))
+            .chain(n.else_ifs.iter().map(|(c, b)| (c, b)));
+        let mut jump_to_end = vec![];
+        let assigned = self.symbol_table.assigned();
+        for (condition, consequence) in branches {
+            self.compile(condition.upcast())?;
+            let jump_not_truthy = self.emit(OP_JUMP_NOT_TRUTHY, &[9999])?;
+
+            self.compile_branch(Some(consequence))?;
+            self.symbol_table.restore_assigned(assigned.clone());
+            jump_to_end.push(self.emit(OP_JUMP, &[9999])?);
+
+            let after_consequence = self.current_instructions().len();
+            self.change_operand(jump_not_truthy, after_consequence)?;
+        }
+
+        self.compile_branch(n.alternative.as_ref())?;
+        self.symbol_table.restore_assigned(assigned);
+        let after_alternative = self.current_instructions().len();
+        jump_to_end
+            .into_iter()
+            .try_for_each(|pos| self.change_operand(pos, after_alternative))?;
+        Ok(())
+    }
+
+    fn compile_while_statement(&mut self, n: &WhileStatement) -> Result<(), String> {
+        let start = self.current_instructions().len();
+        self.compile(n.condition.upcast())?;
+        let exit = self.emit(OP_JUMP_NOT_TRUTHY, &[9999])?;
+        let assigned = self.symbol_table.assigned();
+        self.compile_loop_body(&n.body, start)?;
+        self.symbol_table.restore_assigned(assigned);
+        let end = self.current_instructions().len();
+        self.change_operand(exit, end)?;
+        self.patch_breaks(end)?;
+        self.emit_loop_value()?;
+        Ok(())
+    }
+
+    /// 循环期间迭代器一直待在栈上，循环结束（包括 break）后再弹掉
+    fn compile_for_statement(&mut self, n: &ForStatement) -> Result<(), String> {
+        self.compile(n.iterable.upcast())?;
+        // 不能迭代的错误报在 iterable 的位置
+        self.emit_at(n.iterable.span(), OP_ITER, &[])?;
+        let start = self.current_instructions().len();
+        let exit = self.emit(OP_ITER_NEXT, &[9999])?;
+        let assigned = self.symbol_table.assigned();
+        let symbol = self.symbol_table.define(&n.variable.value);
+        self.store_definition(&symbol)?;
+        self.compile_loop_body(&n.body, start)?;
+        self.symbol_table.restore_assigned(assigned);
+        let end = self.current_instructions().len();
+        self.change_operand(exit, end)?;
+        self.patch_breaks(end)?;
+        self.emit(OP_POP, &[])?;
+        self.emit_loop_value()?;
+        Ok(())
+    }
+
+    fn compile_loop_body(&mut self, body: &Rc<dyn Statement>, start: usize) -> Result<(), String> {
+        self.scopes.last_mut().unwrap().loops.push(LoopScope {
+            continue_target: start,
+            breaks: vec![],
+        });
+        self.compile(body.upcast())?;
+        self.emit(OP_JUMP, &[start])?;
+        Ok(())
+    }
+
+    fn patch_breaks(&mut self, end: usize) -> Result<(), String> {
+        let lp = self.scopes.last_mut().unwrap().loops.pop().unwrap();
+        lp.breaks
+            .into_iter()
+            .try_for_each(|pos| self.change_operand(pos, end))
+    }
+
+    /// 和 evaluator 一样，整个循环语句的值是 null
+    fn emit_loop_value(&mut self) -> Result<(), String> {
+        self.emit(OP_NULL, &[])?;
+        self.emit(OP_POP, &[])?;
+        Ok(())
+    }
+
+    /// 正常执行完 try 或者 catch 之后执行 finally；出错时 VM 跳到 catch 的位置，栈顶是错误转成的 hash
+    /// 有 finally 时 catch 里再登记一次错误处理，catch 出错也先执行 finally 再把错误抛出去
+    /// 和循环一样，整个语句的值是 null
+    fn compile_try_statement(&mut self, n: &TryStatement) -> Result<(), String> {
+        let finally = n.finally_body.clone();
+        let assigned = self.symbol_table.assigned();
+        let handler = self.emit(OP_TRY, &[9999])?;
+        self.compile_guarded(&n.body, &finally)?;
+        self.symbol_table.restore_assigned(assigned.clone());
+        let mut ends = vec![self.emit(OP_JUMP, &[9999])?];
+
+        let catch_start = self.current_instructions().len();
+        self.change_operand(handler, catch_start)?;
+        if let Some(catch_body) = n.catch_body.as_ref() {
+            match n.catch_param.as_ref() {
+                Some(param) => {
+                    self.emit(OP_CATCH, &[])?;
+                    let symbol = self.symbol_table.define(&param.value);
+                    self.store_definition(&symbol)?;
+                }
+                None => {
+                    self.emit(OP_POP, &[])?;
+                }
+            }
+            if finally.is_none() {
+                self.compile(catch_body.upcast())?;
+                ends.push(self.emit(OP_JUMP, &[9999])?);
+            } else {
+                let rethrow = self.emit(OP_TRY, &[9999])?;
+                self.compile_guarded(catch_body, &finally)?;
+                ends.push(self.emit(OP_JUMP, &[9999])?);
+                let rethrow_start = self.current_instructions().len();
+                self.change_operand(rethrow, rethrow_start)?;
+            }
+            self.symbol_table.restore_assigned(assigned.clone());
+        }
+        // 到这里时栈顶是还没处理的错误
+        if n.catch_body.is_none() || finally.is_some() {
+            if let Some(f) = finally.as_ref() {
+                self.compile(f.upcast())?;
+            }
+            self.emit(OP_THROW, &[])?;
+            self.symbol_table.restore_assigned(assigned);
+        }
+
+        let end = self.current_instructions().len();
+        ends.into_iter()
+            .try_for_each(|pos| self.change_operand(pos, end))?;
+        self.emit_loop_value()?;
+        Ok(())
+    }
+
+    /// 在 OpTry 登记的错误处理下编译 body，正常结束时撤销错误处理并执行 finally
+    fn compile_guarded(
+        &mut self,
+        body: &Rc<dyn Statement>,
+        finally: &Option<Rc<dyn Statement>>,
+    ) -> Result<(), String> {
+        let scope = self.scopes.last_mut().unwrap();
+        let loops = scope.loops.len();
+        scope.tries.push(TryScope {
+            finally: finally.clone(),
+            loops,
+        });
+        self.compile(body.upcast())?;
+        self.scopes.last_mut().unwrap().tries.pop();
+        self.emit(OP_END_TRY, &[])?;
+        if let Some(f) = finally {
+            self.compile(f.upcast())?;
+        }
+        Ok(())
+    }
+
+    /// 跳出 try 之前，从里到外撤销 keep 层以内的错误处理，并执行它们的 finally
+    /// finally 本身不在这些 try 里面，编译它时先把外层的 try 去掉
+    fn leave_tries(&mut self, keep: usize) -> Result<(), String> {
+        let tries = std::mem::take(&mut self.scopes.last_mut().unwrap().tries);
+        let mut result = Ok(());
+        for i in (keep..tries.len()).rev() {
+            self.emit(OP_END_TRY, &[])?;
+            if let Some(f) = tries[i].finally.as_ref() {
+                self.scopes.last_mut().unwrap().tries = tries[..i].to_vec();
+                result = self.compile(f.upcast());
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+        self.scopes.last_mut().unwrap().tries = tries;
+        result
+    }
+
+    /// 循环外的 break 和 continue 在 evaluator 里是函数返回时才变成的错误，
+    /// 所以像 return 一样先跳出函数里所有的 try，函数自己的 catch 接不住它
+    /// 在程序最外层时位置是整条语句，在函数里时 VM 报在调用它的地方
+    fn compile_outside_loop(&mut self, statement: usize) -> Result<(), String> {
+        self.leave_tries(0)?;
+        let span = if self.scopes.len() == 1 {
+            self.statement
+        } else {
+            self.span
+        };
+        self.emit_at(span, OP_OUTSIDE_LOOP, &[statement])?;
+        Ok(())
+    }
+
+    /// break 和 continue 只跳出最里层循环里面的 try
+    fn leave_loop_tries(&mut self) -> Result<(), String> {
+        let scope = self.scopes.last().unwrap();
+        let loops = scope.loops.len();
+        let keep = scope.tries.iter().take_while(|t| t.loops < loops).count();
+        self.leave_tries(keep)
+    }
+
+    /// if 的分支作为表达式使用，需要在栈上留下一个值
+    fn compile_branch(&mut self, block: Option<&Rc<dyn Statement>>) -> Result<(), String> {
+        match block {
+            Some(b) => {
+                self.compile(b.upcast())?;
+                if self.last_instruction_is(OP_POP) {
+                    self.remove_last_pop();
+                } else {
+                    self.emit(OP_NULL, &[])?;
+                }
+            }
+            None => {
+                self.emit(OP_NULL, &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// let_name 是 let f = fn() {} 里的 f，匿名函数用它作为名字
+    fn compile_function_literal(
+        &mut self,
+        n: &FunctionLiteral,
+        let_name: Option<&str>,
+    ) -> Result<(), String> {
+        // fn a() {} 这种写法会在当前作用域里定义 a
+        let name = n.name.as_ref().map(|i| self.symbol_table.define(&i.value));
+
+        self.enter_scope();
+        let parameters = n.parameters.clone().unwrap_or_default();
+        parameters.iter().for_each(|p| {
+            let symbol = self.symbol_table.define(&p.value);
+            self.symbol_table.mark_assigned(&symbol);
+        });
+        // evaluator 里函数体里 let 的变量在整个函数里都能看到，先给它们都占好位置，
+        // 这样前面定义的闭包捕获的也是后面才 let 的变量
+        if let Some(ref body) = n.body {
+            let mut names = vec![];
+            declared_names(body.upcast(), &mut names);
+            names.iter().for_each(|name| {
+                self.symbol_table.define(name);
+            });
+        }
+        if let Some(ref body) = n.body {
+            self.compile(body.upcast())?;
+        }
+        if self.last_instruction_is(OP_POP) {
+            self.replace_last_pop_with_return();
+        }
+        if !self.last_instruction_is(OP_RETURN_VALUE) {
+            self.emit(OP_RETURN, &[])?;
+        }
+        let free_symbols = self.symbol_table.free_symbols();
+        let num_locals = self.symbol_table.num_definitions();
+        let (instructions, spans) = self.leave_scope();
+
+        for s in free_symbols.iter() {
+            match s.scope {
+                SymbolScope::Local => self.emit(OP_CAPTURE_LOCAL, &[s.index])?,
+                SymbolScope::Free => self.emit(OP_CAPTURE_FREE, &[s.index])?,
+                SymbolScope::Global => unreachable!("globals are never captured"),
+            };
+        }
+        let function = Rc::new(CompiledFunctionObject {
+            instructions: Rc::new(instructions),
+            spans: Rc::new(spans),
+            num_locals,
+            num_parameters: parameters.len(),
+            name: n
+                .name
+                .as_ref()
+                .map(|i| i.value.clone())
+                .or(let_name.map(String::from)),
+            source: Rc::new(inspect_function(&parameters, n.body.as_deref())),
+        });
+        let c = self.add_constant(function)?;
+        self.emit(OP_CLOSURE, &[c, free_symbols.len()])?;
+
+        if let Some(symbol) = name {
+            self.store_definition(&symbol)?;
+            self.load_symbol(&symbol)?;
+        }
+        Ok(())
+    }
+
+    fn load_identifier(&mut self, name: &str) -> Result<(), String> {
+        let mut unset = self.symbol_table.resolve_all(name);
+        if let Some(symbol) = unset.last().filter(|s| self.symbol_table.is_assigned(s)) {
+            let symbol = symbol.clone();
+            unset.pop();
+            return self.emit_fallbacks(&unset, Self::load_symbol, |c| c.load_symbol(&symbol));
+        }
+        self.emit_fallbacks(&unset, Self::load_symbol, |c| {
+            if BUILTINS.with(|b| b.contains_key(name)) {
+                let constant = c.add_constant(Rc::new(StringObject {
+                    value: Rc::new(name.to_string()),
+                }))?;
+                c.emit(OP_GET_BUILTIN, &[constant])?;
+                return Ok(());
+            }
+            // evaluator 是运行时才查找变量的，所以这里先当成一个还没赋值的全局变量，
+            // 真正读取时如果还没有值，VM 再报 identifier not found
+            let symbol = c.globals.define(name);
+            c.load_symbol(&symbol)
+        })
+    }
+
+    /// 依次检查可能还没有赋值的局部变量，第一个有值的用 access 读写，都没有值时执行 fallback
+    fn emit_fallbacks(
+        &mut self,
+        unset: &[Symbol],
+        access: fn(&mut Self, &Symbol) -> Result<(), String>,
+        fallback: impl FnOnce(&mut Self) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let mut jump_to_end = vec![];
+        for symbol in unset {
+            let op = match symbol.scope {
+                SymbolScope::Local => OP_JUMP_UNSET_LOCAL,
+                _ => OP_JUMP_UNSET_FREE,
+            };
+            let jump_to_next = self.emit(op, &[9999, symbol.index])?;
+            access(self, symbol)?;
+            jump_to_end.push(self.emit(OP_JUMP, &[9999])?);
+            let next = self.current_instructions().len();
+            self.change_operand(jump_to_next, next)?;
+        }
+        fallback(self)?;
+        let end = self.current_instructions().len();
+        jump_to_end
+            .into_iter()
+            .try_for_each(|pos| self.change_operand(pos, end))
+    }
+
+    fn load_symbol(&mut self, symbol: &Symbol) -> Result<(), String> {
+        match symbol.scope {
+            SymbolScope::Global => self.emit(OP_GET_GLOBAL, &[symbol.index])?,
+            SymbolScope::Local => self.emit(OP_GET_LOCAL, &[symbol.index])?,
+            SymbolScope::Free => self.emit(OP_GET_FREE, &[symbol.index])?,
+        };
+        Ok(())
+    }
+
+    /// let、for 的变量和 catch 的参数，赋值之后这个变量就一定有值了
+    fn store_definition(&mut self, symbol: &Symbol) -> Result<(), String> {
+        self.store_symbol(symbol)?;
+        self.symbol_table.mark_assigned(symbol);
+        Ok(())
+    }
+
+    fn store_symbol(&mut self, symbol: &Symbol) -> Result<(), String> {
+        match symbol.scope {
+            SymbolScope::Global => self.emit(OP_SET_GLOBAL, &[symbol.index])?,
+            SymbolScope::Local => self.emit(OP_SET_LOCAL, &[symbol.index])?,
+            SymbolScope::Free => self.emit(OP_SET_FREE, &[symbol.index])?,
+        };
+        Ok(())
+    }
+
+    /// 相同的字面量只放一份，常量的下标要放得进 OpConstant 的两个字节
+    fn add_constant(&mut self, obj: Rc<dyn Object>) -> Result<usize, String> {
+        let key = ConstantKey::of(&obj);
+        if let Some(index) = key.as_ref().and_then(|k| self.constant_indexes.get(k)) {
+            return Ok(*index);
+        }
+        let index = self.constants.len();
+        if index > u16::MAX as usize {
+            return Err(format!(
+                "too many constants: more than {}",
+                u16::MAX as usize + 1
+            ));
+        }
+        self.constants.push(obj);
+        if let Some(key) = key {
+            self.constant_indexes.insert(key, index);
+        }
+        Ok(index)
+    }
+
+    fn emit(&mut self, op: Opcode, operands: &[usize]) -> Result<usize, String> {
+        check_operands(op, operands)?;
+        let ins = make(op, operands);
+        let span = self.span;
+        let scope = self.scopes.last_mut().unwrap();
+        let position = scope.instructions.len();
+        scope.spans.resize(position + ins.len(), span);
+        scope.instructions.extend(ins);
+        scope.previous_instruction = scope.last_instruction;
+        scope.last_instruction = Some(EmittedInstruction {
+            opcode: op,
+            position,
+        });
+        Ok(position)
+    }
+
+    /// 指令出错时报告 span 而不是正在编译的节点的位置
+    fn emit_at(&mut self, span: Span, op: Opcode, operands: &[usize]) -> Result<usize, String> {
+        let outer = std::mem::replace(&mut self.span, span);
+        let position = self.emit(op, operands);
+        self.span = outer;
+        position
+    }
+
+    fn current_instructions(&self) -> &Instructions {
+        &self.scopes.last().unwrap().instructions
+    }
+
+    fn last_instruction_is(&self, op: Opcode) -> bool {
+        self.scopes
+            .last()
+            .unwrap()
+            .last_instruction
+            .is_some_and(|i| i.opcode == op)
+    }
+
+    fn remove_last_pop(&mut self) {
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(last) = scope.last_instruction {
+            scope.instructions.truncate(last.position);
+            scope.spans.truncate(last.position);
+            scope.last_instruction = scope.previous_instruction;
+        }
+    }
+
+    fn replace_last_pop_with_return(&mut self) {
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(last) = scope.last_instruction.as_mut() {
+            scope.instructions[last.position] = OP_RETURN_VALUE;
+            last.opcode = OP_RETURN_VALUE;
+        }
+    }
+
+    fn change_operand(&mut self, position: usize, operand: usize) -> Result<(), String> {
+        let scope = self.scopes.last_mut().unwrap();
+        let op = scope.instructions[position];
+        check_operands(op, &[operand])?;
+        let ins = make(op, &[operand]);
+        scope.instructions[position..position + ins.len()].copy_from_slice(&ins);
+        Ok(())
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(CompilationScope::default());
+        self.symbol_table = Rc::new(SymbolTable::extend(self.symbol_table.clone()));
+    }
+
+    fn leave_scope(&mut self) -> (Instructions, Vec<Span>) {
+        let scope = self.scopes.pop().unwrap();
+        self.symbol_table = self
+            .symbol_table
+            .outer
+            .clone()
+            .unwrap_or_else(|| self.globals.clone());
+        (scope.instructions, scope.spans)
+    }
+}
+
+fn infix_opcode(operator: &str) -> Result<Opcode, String> {
+    Ok(match operator {
+        "+" => OP_ADD,
+        "-" => OP_SUB,
+        "*" => OP_MUL,
+        "/" => OP_DIV,
+        "%" => OP_MOD,
+        "==" => OP_EQUAL,
+        "!=" => OP_NOT_EQUAL,
+        ">" => OP_GREATER_THAN,
+        "<" => OP_LESS_THAN,
+        "<=" => OP_LESS_EQUAL,
+        ">=" => OP_GREATER_EQUAL,
+        "&" => OP_BIT_AND,
+        "|" => OP_BIT_OR,
+        "^" => OP_BIT_XOR,
+        "^^" => OP_POW,
+        op => return Err(format!("unknown operator: {}", op)),
+    })
+}
+
+/// 在当前函数的作用域里定义的名字：let、for 的变量、catch 的参数和 fn a() {}，
+/// 包括 if、循环和 try 里面的，但不进入函数体
+fn declared_names(node: &dyn Node, names: &mut Vec<String>) {
+    let n = node.as_any();
+    let children: Vec<&dyn Node> = if let Some(n) = n.downcast_ref::<BlockStatement>() {
+        n.statement.iter().map(|s| s.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<LetStatement>() {
+        names.push(n.name.value.clone());
+        n.value.iter().map(|v| v.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<ExpressionStatement>() {
+        n.expression.iter().map(|e| e.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<ReturnStatement>() {
+        n.return_value.iter().map(|v| v.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<ThrowStatement>() {
+        vec![n.value.upcast()]
+    } else if let Some(n) = n.downcast_ref::<WhileStatement>() {
+        vec![n.condition.upcast(), n.body.upcast()]
+    } else if let Some(n) = n.downcast_ref::<ForStatement>() {
+        names.push(n.variable.value.clone());
+        vec![n.iterable.upcast(), n.body.upcast()]
+    } else if let Some(n) = n.downcast_ref::<TryStatement>() {
+        names.extend(n.catch_param.iter().map(|p| p.value.clone()));
+        let bodies = [
+            Some(&n.body),
+            n.catch_body.as_ref(),
+            n.finally_body.as_ref(),
+        ];
+        bodies.into_iter().flatten().map(|b| b.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<IfExpression>() {
+        let mut nodes = vec![n.condition.upcast()];
+        nodes.extend(n.consequence.iter().map(|b| b.upcast()));
+        n.else_ifs.iter().for_each(|(c, b)| {
+            nodes.push(c.upcast());
+            nodes.push(b.upcast());
+        });
+        nodes.extend(n.alternative.iter().map(|b| b.upcast()));
+        nodes
+    } else if let Some(n) = n.downcast_ref::<FunctionLiteral>() {
+        names.extend(n.name.iter().map(|i| i.value.clone()));
+        vec![]
+    } else if let Some(n) = n.downcast_ref::<AssignExpression>() {
+        vec![n.target.upcast(), n.value.upcast()]
+    } else if let Some(n) = n.downcast_ref::<PrefixExpression>() {
+        n.right.iter().map(|r| r.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<InfixExpression>() {
+        let operands = n.left.iter().chain(n.right.iter());
+        operands.map(|o| o.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<IndexExpression>() {
+        vec![n.left.upcast(), n.index.upcast()]
+    } else if let Some(n) = n.downcast_ref::<CallExpression>() {
+        let mut nodes: Vec<&dyn Node> = n.function.iter().map(|f| f.upcast()).collect();
+        nodes.extend(n.arguments.iter().flatten().map(|a| a.upcast()));
+        nodes
+    } else if let Some(n) = n.downcast_ref::<ArrayLiteral>() {
+        n.elements.iter().map(|e| e.upcast()).collect()
+    } else if let Some(n) = n.downcast_ref::<HashLiteral>() {
+        // pairs 在 RefCell 里，借用不能带出这个分支
+        n.pairs.borrow().iter().for_each(|(k, v)| {
+            declared_names(k.upcast(), names);
+            declared_names(v.upcast(), names);
+        });
+        vec![]
+    } else if let Some(n) = n.downcast_ref::<TemplateLiteral>() {
+        n.parts
+            .iter()
+            .filter_map(|p| match p {
+                TemplatePart::Expression(e) => Some(e.upcast()),
+                TemplatePart::Text(_) => None,
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+    children
+        .into_iter()
+        .for_each(|node| declared_names(node, names));
+}
@@ -0,0 +1,9 @@
+pub mod code;
+pub use code::*;
+#[allow(clippy::module_inception)]
+pub mod compiler;
+pub use compiler::*;
+pub mod symbol_table;
+pub use symbol_table::*;
+#[allow(clippy::module_inception)]
+mod test;
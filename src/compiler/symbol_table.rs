@@ -0,0 +1,158 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolScope {
+    Global,
+    Local,
+    Free,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: SymbolScope,
+    pub index: usize,
+}
+
+/// 编译期的作用域，和运行时的 Context 一一对应：
+/// 最外层是全局作用域，每个函数体是一层局部作用域
+#[derive(Debug)]
+pub struct SymbolTable {
+    pub outer: Option<Rc<SymbolTable>>,
+    store: RefCell<HashMap<String, Symbol>>,
+    num_definitions: Cell<usize>,
+    // 当前函数从外层捕获的变量，按 Free 的 index 排列，存的是外层原本的 Symbol
+    free_symbols: RefCell<Vec<Symbol>>,
+    // 编译到当前位置时一定已经赋过值的局部变量的 index，读写它们时不用先检查
+    assigned: RefCell<HashSet<usize>>,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable {
+            outer: None,
+            store: RefCell::new(HashMap::new()),
+            num_definitions: Cell::new(0),
+            free_symbols: RefCell::new(vec![]),
+            assigned: RefCell::new(HashSet::new()),
+        }
+    }
+    pub fn extend(outer: Rc<Self>) -> Self {
+        SymbolTable {
+            outer: Some(outer),
+            ..Self::new()
+        }
+    }
+    /// 同一个作用域里重复 let 同一个名字时复用之前的位置，
+    /// 这样之前创建的闭包也能看到新的值，和 Context::set 的行为一致
+    pub fn define(&self, name: &str) -> Symbol {
+        let scope = if self.outer.is_none() {
+            SymbolScope::Global
+        } else {
+            SymbolScope::Local
+        };
+        if let Some(s) = self.store.borrow().get(name) {
+            if s.scope == scope {
+                return s.clone();
+            }
+        }
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope,
+            index: self.num_definitions.get(),
+        };
+        self.num_definitions.set(self.num_definitions.get() + 1);
+        self.store
+            .borrow_mut()
+            .insert(name.to_string(), symbol.clone());
+        symbol
+    }
+    pub fn resolve(&self, name: &str) -> Option<Symbol> {
+        self.resolve_all(name).into_iter().next()
+    }
+    /// 从里到外这个名字可能指向的所有变量，到第一个一定有值的变量为止
+    /// 局部变量在 let 之前还没有值，evaluator 这时会去外层找，所以运行时要按顺序一个个试
+    pub fn resolve_all(&self, name: &str) -> Vec<Symbol> {
+        let mut symbols = vec![];
+        if let Some(s) = self.store.borrow().get(name).cloned() {
+            let assigned = self.is_assigned(&s);
+            symbols.push(s);
+            if assigned {
+                return symbols;
+            }
+        }
+        if let Some(outer) = self.outer.as_ref() {
+            symbols.extend(outer.resolve_all(name).into_iter().map(|s| match s.scope {
+                SymbolScope::Global => s,
+                _ => self.define_free(s),
+            }));
+        }
+        symbols
+    }
+    /// 同一个外层变量只捕获一次
+    fn define_free(&self, original: Symbol) -> Symbol {
+        let mut free_symbols = self.free_symbols.borrow_mut();
+        let index = match free_symbols.iter().position(|s| *s == original) {
+            Some(index) => index,
+            None => {
+                free_symbols.push(original.clone());
+                free_symbols.len() - 1
+            }
+        };
+        Symbol {
+            name: original.name,
+            scope: SymbolScope::Free,
+            index,
+        }
+    }
+    /// 全局变量在 VM 里自己会报错，这里当作一定有值；捕获的变量看外层原本的那个变量
+    pub fn is_assigned(&self, symbol: &Symbol) -> bool {
+        match symbol.scope {
+            SymbolScope::Global => true,
+            SymbolScope::Local => self.assigned.borrow().contains(&symbol.index),
+            SymbolScope::Free => {
+                let original = self.free_symbols.borrow()[symbol.index].clone();
+                self.outer
+                    .as_ref()
+                    .is_some_and(|outer| outer.is_assigned(&original))
+            }
+        }
+    }
+    /// 参数，以及 let、for 和 catch 无条件赋值之后的局部变量
+    pub fn mark_assigned(&self, symbol: &Symbol) {
+        if symbol.scope == SymbolScope::Local {
+            self.assigned.borrow_mut().insert(symbol.index);
+        }
+    }
+    /// 分支和循环体里的 let 不一定会执行，编译完之后恢复成进入之前的样子
+    pub fn assigned(&self) -> HashSet<usize> {
+        self.assigned.borrow().clone()
+    }
+    pub fn restore_assigned(&self, assigned: HashSet<usize>) {
+        self.assigned.replace(assigned);
+    }
+    pub fn num_definitions(&self) -> usize {
+        self.num_definitions.get()
+    }
+    pub fn free_symbols(&self) -> Vec<Symbol> {
+        self.free_symbols.borrow().clone()
+    }
+    /// 按 index 排好的全部名字，VM 用它来给未定义的全局变量报错
+    pub fn names(&self) -> Vec<String> {
+        let mut names = vec![String::new(); self.num_definitions.get()];
+        self.store
+            .borrow()
+            .values()
+            .filter(|s| s.scope != SymbolScope::Free)
+            .for_each(|s| names[s.index] = s.name.clone());
+        names
+    }
+}
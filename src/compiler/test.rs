@@ -0,0 +1,493 @@
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_make() {
+        let cases = [
+            (OP_CONSTANT, vec![65534], vec![OP_CONSTANT, 255, 254]),
+            (OP_ADD, vec![], vec![OP_ADD]),
+            (OP_GET_LOCAL, vec![255], vec![OP_GET_LOCAL, 255]),
+            (
+                OP_CLOSURE,
+                vec![65534, 255],
+                vec![OP_CLOSURE, 255, 254, 255],
+            ),
+        ];
+        cases.iter().for_each(|(op, operands, expected)| {
+            assert_eq!(make(*op, operands), *expected);
+        });
+    }
+
+    #[test]
+    fn test_read_operands() {
+        let cases = [
+            (OP_CONSTANT, vec![65535], 2),
+            (OP_GET_LOCAL, vec![255], 1),
+            (OP_CLOSURE, vec![65535, 255], 3),
+        ];
+        cases.iter().for_each(|(op, operands, bytes)| {
+            let ins = make(*op, operands);
+            let def = lookup_definition(*op).unwrap();
+            let (read, n) = read_operands(&def, &ins[1..]);
+            assert_eq!(n, *bytes);
+            assert_eq!(read, *operands);
+        });
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let ins = [
+            make(OP_ADD, &[]),
+            make(OP_GET_LOCAL, &[1]),
+            make(OP_CONSTANT, &[2]),
+            make(OP_CONSTANT, &[65535]),
+            make(OP_CLOSURE, &[65535, 255]),
+        ]
+        .concat();
+        let expected = r#"0000 OpAdd
+0001 OpGetLocal 1
+0003 OpConstant 2
+0006 OpConstant 65535
+0009 OpClosure 65535 255
+"#;
+        assert_eq!(disassemble(&ins), expected);
+    }
+
+    #[test]
+    fn test_compile_instructions() {
+        let cases = [
+            (
+                "1 + 2",
+                "0000 OpConstant 0\n0003 OpConstant 1\n0006 OpAdd\n0007 OpPop\n",
+            ),
+            ("-1", "0000 OpConstant 0\n0003 OpMinus\n0004 OpPop\n"),
+            (
+                "true; false",
+                "0000 OpTrue\n0001 OpPop\n0002 OpFalse\n0003 OpPop\n",
+            ),
+            (
+                "if (true) { 10 }; 3333;",
+                r#"0000 OpTrue
+0001 OpJumpNotTruthy 10
+0004 OpConstant 0
+0007 OpJump 11
+0010 OpNull
+0011 OpPop
+0012 OpConstant 1
+0015 OpPop
+"#,
+            ),
+            (
+                "if (true) { 1 } else if (false) { 2 } else { 3 }",
+                r#"0000 OpTrue
+0001 OpJumpNotTruthy 10
+0004 OpConstant 0
+0007 OpJump 23
+0010 OpFalse
+0011 OpJumpNotTruthy 20
+0014 OpConstant 1
+0017 OpJump 23
+0020 OpConstant 2
+0023 OpPop
+"#,
+            ),
+            (
+                "let one = 1; let two = one; two;",
+                r#"0000 OpConstant 0
+0003 OpSetGlobal 0
+0006 OpGetGlobal 0
+0009 OpSetGlobal 1
+0012 OpGetGlobal 1
+0015 OpPop
+"#,
+            ),
+            (
+                "[1, 2][0]",
+                r#"0000 OpConstant 0
+0003 OpConstant 1
+0006 OpArray 2
+0009 OpConstant 2
+0012 OpIndex
+0013 OpPop
+"#,
+            ),
+            (
+                "len([])",
+                "0000 OpGetBuiltin 0\n0003 OpArray 0\n0006 OpCall 1\n0008 OpPop\n",
+            ),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let bytecode = test_compile(input);
+            assert_eq!(disassemble(&bytecode.instructions), *expected);
+        });
+    }
+
+    #[test]
+    fn test_compile_closures() {
+        let bytecode = test_compile("fn(a) { fn(b) { a + b } }");
+        assert_eq!(
+            disassemble(&bytecode.instructions),
+            "0000 OpClosure 1 0\n0004 OpPop\n"
+        );
+        let inner = bytecode.constants[0]
+            .as_any()
+            .downcast_ref::<CompiledFunctionObject>()
+            .unwrap();
+        assert_eq!(
+            disassemble(&inner.instructions),
+            "0000 OpGetFree 0\n0002 OpGetLocal 0\n0004 OpAdd\n0005 OpReturnValue\n"
+        );
+        let outer = bytecode.constants[1]
+            .as_any()
+            .downcast_ref::<CompiledFunctionObject>()
+            .unwrap();
+        assert_eq!(
+            disassemble(&outer.instructions),
+            "0000 OpCaptureLocal 0\n0002 OpClosure 0 1\n0006 OpReturnValue\n"
+        );
+        assert_eq!(outer.num_locals, 1);
+        assert_eq!(outer.num_parameters, 1);
+    }
+
+    #[test]
+    fn test_symbol_table() {
+        let global = Rc::new(SymbolTable::new());
+        let a = global.define("a");
+        assert_eq!(a.scope, SymbolScope::Global);
+        assert_eq!(global.define("a"), a);
+
+        let local = Rc::new(SymbolTable::extend(global.clone()));
+        let b = local.define("b");
+        assert_eq!((b.scope, b.index), (SymbolScope::Local, 0));
+
+        let nested = SymbolTable::extend(local.clone());
+        assert_eq!(nested.resolve("a"), Some(a));
+        let free = nested.resolve("b").unwrap();
+        assert_eq!((free.scope, free.index), (SymbolScope::Free, 0));
+        assert_eq!(nested.free_symbols(), vec![b]);
+        assert!(nested.resolve("c").is_none());
+    }
+
+    #[test]
+    fn test_compile_logical_expression() {
+        let cases = [
+            (
+                "true && false",
+                r#"0000 OpTrue
+0001 OpJumpNotTruthy 12
+0004 OpFalse
+0005 OpJumpNotTruthy 12
+0008 OpTrue
+0009 OpJump 13
+0012 OpFalse
+0013 OpPop
+"#,
+            ),
+            (
+                "true || false",
+                r#"0000 OpTrue
+0001 OpJumpNotTruthy 8
+0004 OpTrue
+0005 OpJump 17
+0008 OpFalse
+0009 OpJumpNotTruthy 16
+0012 OpTrue
+0013 OpJump 17
+0016 OpFalse
+0017 OpPop
+"#,
+            ),
+            (
+                "null ?? 1",
+                r#"0000 OpNull
+0001 OpJumpNotNull 7
+0004 OpConstant 0
+0007 OpPop
+"#,
+            ),
+            (
+                "null?[1]",
+                r#"0000 OpNull
+0001 OpJumpNull 8
+0004 OpConstant 0
+0007 OpIndex
+0008 OpPop
+"#,
+            ),
+            (
+                "1 & 2 ^^ 3",
+                r#"0000 OpConstant 0
+0003 OpConstant 1
+0006 OpConstant 2
+0009 OpPow
+0010 OpBitAnd
+0011 OpPop
+"#,
+            ),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let bytecode = test_compile(input);
+            assert_eq!(disassemble(&bytecode.instructions), *expected);
+        });
+    }
+
+    #[test]
+    fn test_compile_loops() {
+        let cases = [
+            (
+                "while (true) { break; continue; }",
+                r#"0000 OpTrue
+0001 OpJumpNotTruthy 13
+0004 OpJump 13
+0007 OpJump 0
+0010 OpJump 0
+0013 OpNull
+0014 OpPop
+"#,
+            ),
+            (
+                "break; try { continue } finally { 1 }",
+                r#"0000 OpOutsideLoop 0
+0002 OpTry 20
+0005 OpEndTry
+0006 OpConstant 0
+0009 OpPop
+0010 OpOutsideLoop 1
+0012 OpEndTry
+0013 OpConstant 0
+0016 OpPop
+0017 OpJump 25
+0020 OpConstant 0
+0023 OpPop
+0024 OpThrow
+0025 OpNull
+0026 OpPop
+"#,
+            ),
+            (
+                "for (x in [1]) { x }",
+                r#"0000 OpConstant 0
+0003 OpArray 1
+0006 OpIter
+0007 OpIterNext 20
+0010 OpSetGlobal 0
+0013 OpGetGlobal 0
+0016 OpPop
+0017 OpJump 7
+0020 OpPop
+0021 OpNull
+0022 OpPop
+"#,
+            ),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let bytecode = test_compile(input);
+            assert_eq!(disassemble(&bytecode.instructions), *expected);
+        });
+    }
+
+    #[test]
+    fn test_compile_try_statement() {
+        let cases = [
+            (
+                "try { throw 1; } catch (e) { e }",
+                r#"0000 OpTry 11
+0003 OpConstant 0
+0006 OpThrow
+0007 OpEndTry
+0008 OpJump 22
+0011 OpCatch
+0012 OpSetGlobal 0
+0015 OpGetGlobal 0
+0018 OpPop
+0019 OpJump 22
+0022 OpNull
+0023 OpPop
+"#,
+            ),
+            // break 先撤销错误处理、执行 finally，再跳出循环
+            (
+                "while (true) { try { break; } finally { 2 } }",
+                r#"0000 OpTrue
+0001 OpJumpNotTruthy 33
+0004 OpTry 23
+0007 OpEndTry
+0008 OpConstant 0
+0011 OpPop
+0012 OpJump 33
+0015 OpEndTry
+0016 OpConstant 0
+0019 OpPop
+0020 OpJump 28
+0023 OpConstant 0
+0026 OpPop
+0027 OpThrow
+0028 OpNull
+0029 OpPop
+0030 OpJump 0
+0033 OpNull
+0034 OpPop
+"#,
+            ),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let bytecode = test_compile(input);
+            assert_eq!(disassemble(&bytecode.instructions), *expected);
+        });
+    }
+
+    #[test]
+    fn test_compile_assign_expression() {
+        let bytecode = test_compile("let a = [1]; a[0] += 2; fn() { a = 3 }; x = 1");
+        let expected = r#"0000 OpConstant 0
+0003 OpArray 1
+0006 OpSetGlobal 0
+0009 OpGetGlobal 0
+0012 OpConstant 1
+0015 OpConstant 2
+0018 OpSetIndex 2
+0020 OpPop
+0021 OpClosure 4 0
+0025 OpPop
+0026 OpCheckGlobal 1
+0029 OpConstant 0
+0032 OpSetGlobal 1
+0035 OpGetGlobal 1
+0038 OpPop
+"#;
+        assert_eq!(disassemble(&bytecode.instructions), expected);
+
+        let p = Parser::new(Lexer::new("fn() { let b = 1; fn() { b = 2 } }"));
+        let pr = p.parse_program().unwrap();
+        let mut c = Compiler::new();
+        assert!(c.compile(&pr).is_ok());
+        let inner = c.bytecode().constants[2].clone();
+        let inner = inner
+            .as_any()
+            .downcast_ref::<CompiledFunctionObject>()
+            .unwrap();
+        assert_eq!(
+            disassemble(&inner.instructions),
+            "0000 OpConstant 1\n0003 OpSetFree 0\n0005 OpGetFree 0\n0007 OpReturnValue\n"
+        );
+    }
+
+    #[test]
+    fn test_compile_unset_local() {
+        // if 里的 let 不一定执行，之后读 y 时先检查格子，没有值时和 evaluator 一样去找全局变量
+        let bytecode = test_compile("fn() { if (false) { let y = 1 }; y }");
+        let inner = bytecode.constants[1]
+            .as_any()
+            .downcast_ref::<CompiledFunctionObject>()
+            .unwrap();
+        let expected = r#"0000 OpFalse
+0001 OpJumpNotTruthy 13
+0004 OpConstant 0
+0007 OpSetLocal 0
+0009 OpNull
+0010 OpJump 14
+0013 OpNull
+0014 OpPop
+0015 OpJumpUnsetLocal 24 0
+0019 OpGetLocal 0
+0021 OpJump 27
+0024 OpGetGlobal 0
+0027 OpReturnValue
+"#;
+        assert_eq!(disassemble(&inner.instructions), expected);
+    }
+
+    #[test]
+    fn test_compile_template_literal() {
+        let bytecode = test_compile(r#""a${1}b""#);
+        let expected = r#"0000 OpConstant 0
+0003 OpConstant 1
+0006 OpConstant 2
+0009 OpTemplate 3
+0012 OpPop
+"#;
+        assert_eq!(disassemble(&bytecode.instructions), expected);
+    }
+
+    #[test]
+    fn test_compile_unknown_operator() {
+        let p = Parser::new(Lexer::new("1 + 2"));
+        let pr = p.parse_program().unwrap();
+        let exp = pr.statement[0]
+            .as_any()
+            .downcast_ref::<ExpressionStatement>()
+            .unwrap()
+            .expression
+            .clone()
+            .unwrap();
+        let infix = exp.as_any().downcast_ref::<InfixExpression>().unwrap();
+        let unknown = InfixExpression {
+            operator: "@".into(),
+            ..infix.clone()
+        };
+        let mut c = Compiler::new();
+        assert_eq!(c.compile(&unknown), Err("unknown operator: @".to_string()));
+    }
+
+    #[test]
+    fn test_compile_operand_out_of_range() {
+        let locals = (0..=256)
+            .map(|i| format!("let v{} = 0;", i))
+            .collect::<String>();
+        let jump = format!("if (true) {{ {} }}", "1; ".repeat(16384));
+        let constants = format!(
+            "[{}]",
+            (0..=65536)
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let cases = [
+            (
+                format!("fn() {{ {} }}", locals),
+                "operand 256 of OpSetLocal does not fit in 1 byte",
+            ),
+            (
+                jump,
+                "operand 65542 of OpJumpNotTruthy does not fit in 2 bytes",
+            ),
+            (constants, "too many constants: more than 65536"),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let p = Parser::new(Lexer::new(input));
+            let pr = p.parse_program().unwrap();
+            let mut c = Compiler::new();
+            assert_eq!(c.compile(&pr), Err(expected.to_string()));
+        });
+    }
+
+    #[test]
+    fn test_compile_constants_reused() {
+        let bytecode = test_compile(r#"1; 1; "a"; "a"; 1.0; 1n; fn() { 1 }"#);
+        // 同样的字面量只放一份，1 和 1n 类型不同不能合并，函数总是单独一份
+        let constants = bytecode
+            .constants
+            .iter()
+            .map(|c| format!("{}:{}", c.object_type(), c.inspect()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            constants,
+            [
+                "INTEGER:1",
+                "STRING_OBJECT:a",
+                "FLOAT:1.0",
+                "BIG_INTEGER:1",
+                "COMPILED_FUNCTION_OBJECT:compiled function(0)"
+            ]
+        );
+    }
+
+    fn test_compile(input: &str) -> Bytecode {
+        let p = Parser::new(Lexer::new(input));
+        let pr = p.parse_program().unwrap();
+        let mut c = Compiler::new();
+        assert!(c.compile(&pr).is_ok());
+        c.bytecode()
+    }
+}
@@ -0,0 +1,37 @@
+use crate::token::Span;
+
+/// 把错误信息和出错的那一行源码一起打印出来，用 ^ 标出出错的位置
+///
+/// ```
+/// use my_rust_interpreter::diagnostic::render_diagnostic;
+/// use my_rust_interpreter::token::Span;
+/// let span = Span { start: 4, end: 8, line: 1, column: 5 };
+/// assert_eq!(
+///     render_diagnostic("5 + true;", span, "type mismatch"),
+///     "error: type mismatch\n --> 1:5\n  |\n1 | 5 + true;\n  |     ^^^^\n"
+/// );
+/// ```
+pub fn render_diagnostic(source: &str, span: Span, message: &str) -> String {
+    let mut out = format!("error: {}\n", message);
+    let line = match span.line.checked_sub(1).and_then(|l| source.lines().nth(l)) {
+        Some(line) => line.trim_end_matches('\r'),
+        None => return out,
+    };
+    let gutter = " ".repeat(span.line.to_string().len());
+    // 前面的 tab 原样保留，这样 ^ 才能和源码对齐
+    let indent = line
+        .chars()
+        .take(span.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    let width = source
+        .get(span.start..span.end)
+        .map(|s| s.lines().next().unwrap_or("").chars().count())
+        .unwrap_or(0)
+        .max(1);
+    out.push_str(&format!("{}--> {}\n", gutter, span));
+    out.push_str(&format!("{} |\n", gutter));
+    out.push_str(&format!("{} | {}\n", span.line, line));
+    out.push_str(&format!("{} | {}{}\n", gutter, indent, "^".repeat(width)));
+    out
+}
@@ -0,0 +1,285 @@
+use crate::evaluator::*;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cell::Cell;
+
+/// 整数运算溢出时怎么处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerOverflow {
+    /// 报 OverflowError
+    #[default]
+    Error,
+    /// 结果提升成 BigInteger
+    Promote,
+}
+
+/// 整数除法除不尽时怎么处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerDivision {
+    /// 向 0 取整，7 / 2 == 3
+    #[default]
+    Truncate,
+    /// 结果是精确的 Rational，7 / 2 == 7/2，除得尽时还是整数
+    Exact,
+}
+
+thread_local! {
+    static INTEGER_OVERFLOW: Cell<IntegerOverflow> = const { Cell::new(IntegerOverflow::Error) };
+    static INTEGER_DIVISION: Cell<IntegerDivision> = const { Cell::new(IntegerDivision::Truncate) };
+}
+
+/// f 执行期间整数溢出按 mode 处理，Interpreter 和 Vm 运行脚本时用它设置
+pub fn with_integer_overflow<R>(mode: IntegerOverflow, f: impl FnOnce() -> R) -> R {
+    let previous = INTEGER_OVERFLOW.with(|m| m.replace(mode));
+    let result = f();
+    INTEGER_OVERFLOW.with(|m| m.set(previous));
+    result
+}
+
+/// 和 with_integer_overflow 一样，f 执行期间整数除法按 mode 处理
+pub fn with_integer_division<R>(mode: IntegerDivision, f: impl FnOnce() -> R) -> R {
+    let previous = INTEGER_DIVISION.with(|m| m.replace(mode));
+    let result = f();
+    INTEGER_DIVISION.with(|m| m.set(previous));
+    result
+}
+
+/// 当前的溢出处理方式，测试里让 VM 和 evaluator 用同样的设置
+pub fn integer_overflow() -> IntegerOverflow {
+    INTEGER_OVERFLOW.with(|m| m.get())
+}
+
+/// 当前的整数除法处理方式
+pub fn integer_division() -> IntegerDivision {
+    INTEGER_DIVISION.with(|m| m.get())
+}
+
+fn exact_division() -> bool {
+    INTEGER_DIVISION.with(|m| m.get()) == IntegerDivision::Exact
+}
+
+// BigInteger 最多这么多位，防止一次乘方就把内存耗光
+const MAX_BIG_INTEGER_BITS: u64 = 1 << 20;
+
+/// 两个 Integer 的 + - * / % ^^，每一步都检查溢出
+/// 除以 0、指数为负数和溢出都返回 ErrorObject，开启溢出提升时溢出的结果是 BigInteger
+pub fn eval_integer_arithmetic(operator: &str, a: i64, b: i64) -> Rc<dyn Object> {
+    if operator == "/" && exact_division() && a.checked_rem(b).is_some_and(|r| r != 0) {
+        return Rc::new(Rational {
+            value: BigRational::new(a.into(), b.into()),
+        });
+    }
+    let value = match operator {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        // 和 Rust 一样向 0 取整
+        "/" => a.checked_div(b),
+        // 余数的符号和被除数相同，-7 % 3 == -1
+        "%" => a.checked_rem(b),
+        "^^" => u32::try_from(b).ok().and_then(|e| a.checked_pow(e)),
+        _ => unreachable!("not an arithmetic operator: {}", operator),
+    };
+    if let Some(value) = value {
+        return Rc::new(Integer { value });
+    }
+    if (operator == "/" || operator == "%") && b == 0 {
+        return division_by_zero(&a, operator, &b);
+    }
+    if operator == "^^" && b < 0 {
+        return negative_exponent(&a, &b);
+    }
+    if INTEGER_OVERFLOW.with(|m| m.get()) == IntegerOverflow::Promote {
+        return eval_big_integer_arithmetic(operator, &BigInt::from(a), &BigInt::from(b));
+    }
+    Rc::new(ErrorObject::new(
+        ErrorKind::OverflowError,
+        format!("integer overflow: {} {} {}", a, operator, b),
+    ))
+}
+
+/// 整数取负，-i64::MIN 会溢出
+pub fn eval_integer_negation(value: i64) -> Rc<dyn Object> {
+    match value.checked_neg() {
+        Some(value) => Rc::new(Integer { value }),
+        None if INTEGER_OVERFLOW.with(|m| m.get()) == IntegerOverflow::Promote => {
+            Rc::new(BigInteger {
+                value: -BigInt::from(value),
+            })
+        }
+        None => Rc::new(ErrorObject::new(
+            ErrorKind::OverflowError,
+            format!("integer overflow: -({})", value),
+        )),
+    }
+}
+
+/// Integer 和 BigInteger 都能转成 BigInt
+pub fn to_big_int(obj: &Rc<dyn Object>) -> Option<BigInt> {
+    if let Some(i) = obj.as_any().downcast_ref::<Integer>() {
+        return Some(BigInt::from(i.value));
+    }
+    obj.as_any()
+        .downcast_ref::<BigInteger>()
+        .map(|b| b.value.clone())
+}
+
+/// 两边都是整数并且至少一边是 BigInteger 时的运算，算出来的整数也是 BigInteger
+pub fn eval_big_integer_infix(
+    operator: &str,
+    l: &Rc<dyn Object>,
+    r: &Rc<dyn Object>,
+) -> Rc<dyn Object> {
+    let (a, b) = (to_big_int(l).unwrap(), to_big_int(r).unwrap());
+    match operator {
+        "+" | "-" | "*" | "/" | "%" | "^^" => eval_big_integer_arithmetic(operator, &a, &b),
+        "&" => Rc::new(BigInteger { value: a & b }),
+        "|" => Rc::new(BigInteger { value: a | b }),
+        "^" => Rc::new(BigInteger { value: a ^ b }),
+        "<" => native_bool_to_boolean_object(a < b),
+        ">" => native_bool_to_boolean_object(a > b),
+        "<=" => native_bool_to_boolean_object(a <= b),
+        ">=" => native_bool_to_boolean_object(a >= b),
+        "==" => native_bool_to_boolean_object(a == b),
+        "!=" => native_bool_to_boolean_object(a != b),
+        _ => Rc::new(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!(
+                "unknown operator: {} {} {}",
+                l.object_type(),
+                operator,
+                r.object_type()
+            ),
+        )),
+    }
+}
+
+fn eval_big_integer_arithmetic(operator: &str, a: &BigInt, b: &BigInt) -> Rc<dyn Object> {
+    let value = match operator {
+        "+" => a + b,
+        "-" => a - b,
+        "*" if a.bits() + b.bits() > MAX_BIG_INTEGER_BITS => return too_large(),
+        "*" => a * b,
+        "/" | "%" if b.is_zero() => return division_by_zero(a, operator, b),
+        "/" if exact_division() && !(a % b).is_zero() => {
+            return Rc::new(Rational {
+                value: BigRational::new(a.clone(), b.clone()),
+            })
+        }
+        "/" => a / b,
+        "%" => a % b,
+        "^^" if b.is_negative() => return negative_exponent(a, b),
+        // 0、1 和 -1 的任意次方都不会变大，不受位数的限制
+        "^^" if a.bits() <= 1 => {
+            if b.is_zero() || (a.is_negative() && !b.bit(0)) {
+                BigInt::from(1)
+            } else {
+                a.clone()
+            }
+        }
+        "^^" => match b.to_u32() {
+            Some(e) if a.bits() * e as u64 <= MAX_BIG_INTEGER_BITS => a.pow(e),
+            _ => return too_large(),
+        },
+        _ => unreachable!("not an arithmetic operator: {}", operator),
+    };
+    Rc::new(BigInteger { value })
+}
+
+/// Integer、BigInteger 和 Rational 都能转成分数
+pub fn to_rational(obj: &Rc<dyn Object>) -> Option<BigRational> {
+    if let Some(r) = obj.as_any().downcast_ref::<Rational>() {
+        return Some(r.value.clone());
+    }
+    to_big_int(obj).map(BigRational::from_integer)
+}
+
+/// 至少一边是 Rational、另一边是整数或者 Rational 时的运算，结果是精确的
+/// 乘方的指数只能是整数，可以是负数
+pub fn eval_rational_infix(
+    operator: &str,
+    l: &Rc<dyn Object>,
+    r: &Rc<dyn Object>,
+) -> Rc<dyn Object> {
+    let (a, b) = (to_rational(l).unwrap(), to_rational(r).unwrap());
+    let value = match operator {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" if b.is_zero() => return division_by_zero(&l.inspect(), operator, &r.inspect()),
+        "/" => a / b,
+        "^^" if !b.is_integer() => {
+            return Rc::new(ErrorObject::new(
+                ErrorKind::ValueError,
+                format!("exponent must be an integer, got {}", r.inspect()),
+            ))
+        }
+        "^^" if a.is_zero() && b.is_negative() => {
+            return division_by_zero(&l.inspect(), operator, &r.inspect())
+        }
+        // 0、1 和 -1 的任意次方都不会变大，不受位数的限制
+        "^^" if a.is_integer() && a.numer().bits() <= 1 => {
+            if b.is_zero() || (a.is_negative() && !b.numer().bit(0)) {
+                BigRational::one()
+            } else {
+                a
+            }
+        }
+        "^^" => match b.to_integer().to_i32() {
+            Some(e)
+                if (a.numer().bits() + a.denom().bits()) * e.unsigned_abs() as u64
+                    <= MAX_BIG_INTEGER_BITS =>
+            {
+                a.pow(e)
+            }
+            _ => return too_large(),
+        },
+        "<" => return native_bool_to_boolean_object(a < b),
+        ">" => return native_bool_to_boolean_object(a > b),
+        "<=" => return native_bool_to_boolean_object(a <= b),
+        ">=" => return native_bool_to_boolean_object(a >= b),
+        "==" => return native_bool_to_boolean_object(a == b),
+        "!=" => return native_bool_to_boolean_object(a != b),
+        _ => {
+            return Rc::new(ErrorObject::new(
+                ErrorKind::TypeError,
+                format!(
+                    "unknown operator: {} {} {}",
+                    l.object_type(),
+                    operator,
+                    r.object_type()
+                ),
+            ))
+        }
+    };
+    Rc::new(Rational { value })
+}
+
+fn division_by_zero(
+    a: &dyn std::fmt::Display,
+    operator: &str,
+    b: &dyn std::fmt::Display,
+) -> Rc<dyn Object> {
+    Rc::new(ErrorObject::new(
+        ErrorKind::ZeroDivisionError,
+        format!("division by zero: {} {} {}", a, operator, b),
+    ))
+}
+
+fn negative_exponent(a: &dyn std::fmt::Display, b: &dyn std::fmt::Display) -> Rc<dyn Object> {
+    Rc::new(ErrorObject::new(
+        ErrorKind::ValueError,
+        format!("negative exponent: {} ^^ {}", a, b),
+    ))
+}
+
+fn too_large() -> Rc<dyn Object> {
+    Rc::new(ErrorObject::new(
+        ErrorKind::OverflowError,
+        format!(
+            "integer overflow: result exceeds {} bits",
+            MAX_BIG_INTEGER_BITS
+        ),
+    ))
+}
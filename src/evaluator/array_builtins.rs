@@ -0,0 +1,315 @@
+use crate::evaluator::*;
+use std::cmp::Ordering;
+
+type Array = Vec<Rc<dyn Object>>;
+type Value = Rc<dyn Object>;
+
+// range 生成的数组最多这么多个元素，防止一直分配到内存耗光
+const MAX_RANGE_LENGTH: usize = 1 << 24;
+
+/// 数组相关的内置函数，回调通过 call_function 调用，两个后端都能用
+/// 除了 each 以外都不修改原数组，而是返回新的数组
+pub fn array_builtins() -> Vec<(&'static str, Rc<dyn Object>)> {
+    vec![
+        (
+            // map([1, 2], fn(x) { x * 2 }) -> [2, 4]
+            "map",
+            Rc::new(BuiltinObject::typed("map", |(arr, f): (Array, Value)| {
+                arr.into_iter()
+                    .map(|x| callback(&f, vec![x]))
+                    .collect::<Result<Vec<_>, _>>()
+            })),
+        ),
+        (
+            "filter",
+            Rc::new(BuiltinObject::typed(
+                "filter",
+                |(arr, f): (Array, Value)| -> Result<_, ErrorObject> {
+                    let mut out = vec![];
+                    for x in arr {
+                        if is_truthy(Some(callback(&f, vec![x.clone()])?)) {
+                            out.push(x);
+                        }
+                    }
+                    Ok(out)
+                },
+            )),
+        ),
+        (
+            // 不传初始值时用第一个元素做初始值
+            // reduce([1, 2, 3], fn(acc, x) { acc + x }, 0) -> 6
+            "reduce",
+            Rc::new(BuiltinObject::typed(
+                "reduce",
+                |(arr, f, init): (Array, Value, Option<Value>)| {
+                    let mut items = arr.into_iter();
+                    let mut acc = match init.or_else(|| items.next()) {
+                        Some(acc) => acc,
+                        None => {
+                            return Err(ErrorObject::new(
+                                ErrorKind::ValueError,
+                                "reduce of empty array with no initial value",
+                            ))
+                        }
+                    };
+                    for x in items {
+                        acc = callback(&f, vec![acc, x])?;
+                    }
+                    Ok(acc)
+                },
+            )),
+        ),
+        (
+            // 只为了副作用，返回 null
+            "each",
+            Rc::new(BuiltinObject::typed(
+                "each",
+                |(arr, f): (Array, Value)| -> Result<_, ErrorObject> {
+                    for x in arr {
+                        callback(&f, vec![x])?;
+                    }
+                    Ok(())
+                },
+            )),
+        ),
+        (
+            // 稳定排序，默认按数字或者字符串排序
+            // 比较函数返回负数、0、正数分别表示小于、等于、大于
+            // sort([3, 1, 2], fn(a, b) { b - a }) -> [3, 2, 1]
+            "sort",
+            Rc::new(BuiltinObject::typed(
+                "sort",
+                |(arr, f): (Array, Option<Value>)| match f.as_ref() {
+                    Some(f) => merge_sort(arr, &|a, b| compare_with(f, a, b)),
+                    None => merge_sort(arr, &compare_values),
+                },
+            )),
+        ),
+        (
+            "reverse",
+            Rc::new(BuiltinObject::typed(
+                "reverse",
+                |(seq,): (Sequence,)| match seq {
+                    Sequence::Str(s) => s.chars().rev().collect::<String>().into_object(),
+                    Sequence::Array(arr) => arr.into_iter().rev().collect::<Vec<_>>().into_object(),
+                },
+            )),
+        ),
+        (
+            // 按字符或者元素切片，下标可以是负数，表示从结尾往前数
+            // slice("héllo", 1, 3) -> "él"
+            // slice([1, 2, 3], -2) -> [2, 3]
+            "slice",
+            Rc::new(BuiltinObject::typed(
+                "slice",
+                |(seq, start, end): (Sequence, i64, Option<i64>)| match seq {
+                    Sequence::Str(s) => {
+                        let chars = s.chars().collect::<Vec<_>>();
+                        let (start, end) = slice_range(chars.len(), start, end);
+                        chars[start..end].iter().collect::<String>().into_object()
+                    }
+                    Sequence::Array(arr) => {
+                        let (start, end) = slice_range(arr.len(), start, end);
+                        arr[start..end].to_vec().into_object()
+                    }
+                },
+            )),
+        ),
+        (
+            "concat",
+            Rc::new(BuiltinObject::typed(
+                "concat",
+                |(mut a, b): (Array, Array)| {
+                    a.extend(b);
+                    a
+                },
+            )),
+        ),
+        (
+            // 长度按短的那个算
+            // zip([1, 2], ["a", "b"]) -> [[1, "a"], [2, "b"]]
+            "zip",
+            Rc::new(BuiltinObject::typed("zip", |(a, b): (Array, Array)| {
+                a.into_iter()
+                    .zip(b)
+                    .map(|(x, y)| vec![x, y])
+                    .collect::<Vec<_>>()
+            })),
+        ),
+        (
+            // range(3) -> [0, 1, 2]
+            // range(1, 7, 2) -> [1, 3, 5]
+            "range",
+            Rc::new(BuiltinObject::typed(
+                "range",
+                |(a, b, step): (i64, Option<i64>, Option<i64>)| {
+                    let (start, end) = match b {
+                        Some(b) => (a, b),
+                        None => (0, a),
+                    };
+                    let step = step.unwrap_or(1);
+                    if step == 0 {
+                        return Err(ErrorObject::new(
+                            ErrorKind::ValueError,
+                            "argument[2] to `range` must not be zero",
+                        ));
+                    }
+                    // 先算出长度再分配，用 i128 避免 end - start 溢出
+                    let (start, end, step) = (start as i128, end as i128, step as i128);
+                    let len = match (end - start) / step {
+                        len if len <= 0 => 0,
+                        len if (end - start) % step == 0 => len,
+                        len => len + 1,
+                    };
+                    let too_large = || {
+                        ErrorObject::new(ErrorKind::ValueError, "result of `range` is too large")
+                    };
+                    if len > MAX_RANGE_LENGTH as i128 {
+                        return Err(too_large());
+                    }
+                    let mut out: Vec<i64> = vec![];
+                    out.try_reserve_exact(len as usize)
+                        .map_err(|_| too_large())?;
+                    out.extend((0..len).map(|k| (start + k * step) as i64));
+                    Ok(out)
+                },
+            )),
+        ),
+        (
+            // 字符串找子串，数组找相等的元素
+            "contains",
+            Rc::new(BuiltinObject::typed(
+                "contains",
+                |(seq, target): (Sequence, Value)| {
+                    position("contains", &seq, &target).map(|i| i >= 0)
+                },
+            )),
+        ),
+        (
+            // 第一次出现的下标，字符串按字符算，找不到是 -1
+            "index_of",
+            Rc::new(BuiltinObject::typed(
+                "index_of",
+                |(seq, target): (Sequence, Value)| position("index_of", &seq, &target),
+            )),
+        ),
+    ]
+}
+
+/// 字符串或者数组，给两种都能处理的内置函数用
+pub enum Sequence {
+    Str(String),
+    Array(Vec<Rc<dyn Object>>),
+}
+
+impl FromObject for Sequence {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        if let Ok(s) = String::from_object(obj) {
+            return Ok(Sequence::Str(s));
+        }
+        Vec::from_object(obj).map(Sequence::Array).map_err(|_| {
+            ConversionError::new(format!("{} or {}", STRING_OBJECT, ARRAY_OBJECT), obj)
+        })
+    }
+}
+
+fn position(name: &str, seq: &Sequence, target: &Rc<dyn Object>) -> Result<i64, ErrorObject> {
+    match seq {
+        Sequence::Str(s) => {
+            let sub = String::from_object(target).map_err(|e| {
+                ErrorObject::new(
+                    ErrorKind::TypeError,
+                    format!(
+                        "argument[1] to `{}` must be {}, got {}",
+                        name, e.expected, e.found
+                    ),
+                )
+            })?;
+            Ok(match s.find(&sub) {
+                Some(byte) => s[..byte].chars().count() as i64,
+                None => -1,
+            })
+        }
+        Sequence::Array(arr) => Ok(arr
+            .iter()
+            .position(|x| values_equal(x, target))
+            .map_or(-1, |i| i as i64)),
+    }
+}
+
+/// 和 == 的结果一致，不能用 == 比较的两个值算不相等
+fn values_equal(a: &Rc<dyn Object>, b: &Rc<dyn Object>) -> bool {
+    Rc::ptr_eq(a, b)
+        || eval_infix_expression("==", Some(a.clone()), Some(b.clone())).is_some_and(|r| {
+            r.as_any()
+                .downcast_ref::<Boolean>()
+                .is_some_and(|b| b.value)
+        })
+}
+
+/// 稳定的归并排序，第一次比较出错就返回错误
+/// 比较函数是脚本写的，结果可能前后矛盾，不能交给 sort_by（会 panic），这里只是顺序不确定
+fn merge_sort(
+    mut arr: Array,
+    cmp: &dyn Fn(&Value, &Value) -> Result<Ordering, ErrorObject>,
+) -> Result<Array, ErrorObject> {
+    if arr.len() <= 1 {
+        return Ok(arr);
+    }
+    let right = arr.split_off(arr.len() / 2);
+    let (left, right) = (merge_sort(arr, cmp)?, merge_sort(right, cmp)?);
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // 相等时先取左边的，保证稳定
+        let next = match cmp(a, b)? {
+            Ordering::Greater => right.next(),
+            _ => left.next(),
+        };
+        out.extend(next);
+    }
+    out.extend(left);
+    out.extend(right);
+    Ok(out)
+}
+
+fn compare_values(a: &Rc<dyn Object>, b: &Rc<dyn Object>) -> Result<Ordering, ErrorObject> {
+    if let (Some(x), Some(y)) = (number_to_f64(a), number_to_f64(b)) {
+        if let Some(ordering) = x.partial_cmp(&y) {
+            return Ok(ordering);
+        }
+    }
+    if let (Ok(x), Ok(y)) = (String::from_object(a), String::from_object(b)) {
+        return Ok(x.cmp(&y));
+    }
+    Err(ErrorObject::new(
+        ErrorKind::TypeError,
+        format!("cannot compare {} and {}", a.object_type(), b.object_type()),
+    ))
+}
+
+fn compare_with(
+    f: &Rc<dyn Object>,
+    a: &Rc<dyn Object>,
+    b: &Rc<dyn Object>,
+) -> Result<Ordering, ErrorObject> {
+    let result = callback(f, vec![a.clone(), b.clone()])?;
+    i64::from_object(&result).map(|i| i.cmp(&0)).map_err(|e| {
+        ErrorObject::new(
+            ErrorKind::TypeError,
+            format!(
+                "comparator passed to `sort` must return {}, got {}",
+                e.expected, e.found
+            ),
+        )
+    })
+}
+
+/// 调用脚本传进来的函数，回调出错时把错误原样往外传，种类和调用栈都保留
+fn callback(f: &Rc<dyn Object>, args: Vec<Rc<dyn Object>>) -> Result<Rc<dyn Object>, ErrorObject> {
+    let result = call_function(f, args);
+    match result.as_any().downcast_ref::<ErrorObject>() {
+        Some(err) => Err(err.clone()),
+        None => Ok(result),
+    }
+}
@@ -0,0 +1,59 @@
+use crate::evaluator::*;
+
+/// hash 相关的内置函数，结果都按 key 的插入顺序排列
+/// delete 和 merge 不修改传进来的 hash，而是返回新的 hash
+pub fn hash_builtins() -> Vec<(&'static str, Rc<dyn Object>)> {
+    vec![
+        (
+            "keys",
+            Rc::new(BuiltinObject::typed("keys", |(h,): (HashPairs,)| {
+                h.into_keys().collect::<Vec<_>>()
+            })),
+        ),
+        (
+            "values",
+            Rc::new(BuiltinObject::typed("values", |(h,): (HashPairs,)| {
+                h.into_values().collect::<Vec<_>>()
+            })),
+        ),
+        (
+            // entries({"a": 1}) -> [["a", 1]]
+            "entries",
+            Rc::new(BuiltinObject::typed("entries", |(h,): (HashPairs,)| {
+                h.into_iter()
+                    .map(|(k, v)| vec![k.to_object(), v])
+                    .collect::<Vec<_>>()
+            })),
+        ),
+        (
+            "has",
+            Rc::new(BuiltinObject::typed(
+                "has",
+                |(h, key): (HashPairs, HashKey)| h.contains_key(&key),
+            )),
+        ),
+        (
+            // key 不存在时原样返回一份
+            "delete",
+            Rc::new(BuiltinObject::typed(
+                "delete",
+                |(mut h, key): (HashPairs, HashKey)| {
+                    h.shift_remove(&key);
+                    h
+                },
+            )),
+        ),
+        (
+            // 相同的 key 用后一个 hash 的值，位置保持在前一个 hash 里的位置
+            // merge({"a": 1, "b": 2}, {"a": 3, "c": 4}) -> {"a": 3, "b": 2, "c": 4}
+            "merge",
+            Rc::new(BuiltinObject::typed(
+                "merge",
+                |(mut a, b): (HashPairs, HashPairs)| {
+                    a.extend(b);
+                    a
+                },
+            )),
+        ),
+    ]
+}
@@ -3,11 +3,25 @@ pub use crate::lexer::*;
 pub use crate::object::*;
 pub use crate::parser::*;
 pub use crate::token::*;
-use std::cell::RefCell;
+use crate::tracer::*;
+use num_traits::ToPrimitive;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 pub use std::rc::Rc;
 use std::vec::Vec;
 
+pub mod arithmetic;
+pub use arithmetic::*;
+pub mod array_builtins;
+pub use array_builtins::*;
+pub mod hash_builtins;
+pub use hash_builtins::*;
+pub mod number_builtins;
+pub use number_builtins::*;
+pub mod string_builtins;
+pub use string_builtins::*;
+
+#[allow(clippy::module_inception)]
 mod test;
 
 thread_local! {
@@ -20,117 +34,171 @@ thread_local! {
             "len",
             Rc::new(BuiltinObject { func: Rc::new(|args: Vec<Rc<dyn Object>>| {
                 match args.as_slice() {
-                    &[]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
-                    [_, _, ..]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
+                    &[]=> Some(Rc::new(ErrorObject::new(ErrorKind::ArgumentError, format!("wrong number of arguments. got={}, want=1", args.len())))),
+                    [_, _, ..]=> Some(Rc::new(ErrorObject::new(ErrorKind::ArgumentError, format!("wrong number of arguments. got={}, want=1", args.len())))),
                     [a] if a.as_ref().as_any().is::<StringObject>() => {
+                        // 按字符计数，字节数用 byte_len
                         let inner_string = a.as_any().downcast_ref::<StringObject>().unwrap() ;
-                        return Some(Rc::new(Integer { value: inner_string.value.to_string().len() as i64 }));
+                        Some(Rc::new(Integer { value: inner_string.value.chars().count() as i64 }))
                     },
                     [a] if a.as_ref().as_any().is::<ArrayObject>() =>{
                         let inner = a.as_any().downcast_ref::<ArrayObject>().unwrap();
                         return Some(Rc::new(Integer { value: inner.elements.borrow().len() as i64}));
                     },
+                    [a] if a.as_ref().as_any().is::<HashObject>() => {
+                        let inner = a.as_any().downcast_ref::<HashObject>().unwrap();
+                        Some(Rc::new(Integer { value: inner.len() as i64 }))
+                    },
                     [a] => {
-                        return Some(Rc::new(ErrorObject { message: format!( "argument to `len` not supported, got {}", a.object_type())}));
+                        Some(Rc::new(ErrorObject::new(ErrorKind::TypeError, format!( "argument to `len` not supported, got {}", a.object_type()))))
                     },
                 }
             }) }) as Rc<dyn Object>
         ),
         (
             "first",
-            Rc::new(BuiltinObject { func: Rc::new(|args: Vec<Rc<dyn Object>>| {
-                match args.as_slice() {
-                    &[]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
-                    [_, _, ..]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
-                    [a] if a.as_ref().as_any().is::<ArrayObject>() => {
-                        let inner = a.as_any().downcast_ref::<ArrayObject>().unwrap();
-                        return Some(inner.elements.borrow().first().unwrap_or(&NULLOBJ.with(|n| n.clone())).clone());
-                    },
-                    [a] => Some(Rc::new(ErrorObject { message: format!("argument to `first` must be ARRAY, got {}", a.object_type())})),
-                }
-            })})
+            Rc::new(BuiltinObject::typed("first", |(arr,): (Vec<Rc<dyn Object>>,)| arr.first().cloned()))
         ),
         (
             "last",
-            Rc::new(BuiltinObject { func: Rc::new(|args: Vec<Rc<dyn Object>>| {
-                match args.as_slice() {
-                    &[]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
-                    [_, _, ..]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
-                    [a] if a.as_ref().as_any().is::<ArrayObject>()  => {
-                        let inner = a.as_any().downcast_ref::<ArrayObject>().unwrap();
-                        return Some(inner.elements.borrow().last().unwrap_or(&NULLOBJ.with(|n| n.clone())).clone());
-                    },
-                    [a] => Some(Rc::new(ErrorObject { message: format!("argument to `first` must be ARRAY, got {}", a.object_type())}))
-                }
-            })})
+            Rc::new(BuiltinObject::typed("last", |(arr,): (Vec<Rc<dyn Object>>,)| arr.last().cloned()))
         ),
         (
             // returns the other data in a new Array
             // etc: rest([1,2,3]) -> [2,3]
             "rest",
-            Rc::new(BuiltinObject { func: Rc::new(|args: Vec<Rc<dyn Object>>| {
-                match args.as_slice() {
-                    &[]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
-                    [_, _, ..]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=1", args.len()) })),
-                    [a] if a.as_ref().as_any().is::<ArrayObject>()  => {
-                        let inner = a.as_any().downcast_ref::<ArrayObject>().unwrap();
-                        let els = inner
-                            .elements
-                            .borrow()
-                            .iter()
-                            .enumerate()
-                            .map(|(idx, val)| {
-                                if idx > 0 {
-                                    return Some(val.clone());
-                                }
-                                return None;
-                            })
-                            .filter(|val| val.is_some()).map(|v| v.unwrap())
-                            .collect::<Vec<_>>();
-                        return Some(Rc::new(ArrayObject {
-                            elements: RefCell::new(els),
-                        }));
-                    },
-                    [a] => Some(Rc::new(ErrorObject { message: format!("argument to `first` must be ARRAY, got {}", a.object_type())}))
-                }
-            })}),
+            Rc::new(BuiltinObject::typed("rest", |(arr,): (Vec<Rc<dyn Object>>,)| {
+                arr.into_iter().skip(1).collect::<Vec<_>>()
+            }))
         ),
         (
             // let a = [1,2,3]
             // push(a, 4);
             // a // -> [1,2,3,4]
             "push",
-            Rc::new(BuiltinObject { func: Rc::new(|args: Vec<Rc<dyn Object>>| {
-                match args.as_slice() {
-                    &[_] | &[]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=2", args.len()) })),
-                    [_, _, _, ..]=> Some(Rc::new(ErrorObject { message: format!("wrong number of arguments. got={}, want=2", args.len()) })),
-                    [a, target] if a.as_ref().as_any().is::<ArrayObject>()  => {
-                        let inner = a.as_any().downcast_ref::<ArrayObject>().unwrap();
-                        let mut els = inner
-                            .elements
-                            .borrow()
-                            .iter()
-                            .map(|v| v.clone())
-                            .collect::<Vec<Rc<dyn Object>>>();
-                        els.push(target.clone());
-                        return Some(Rc::new(ArrayObject {
-                            elements: RefCell::new(els),
-                        }));
-                    },
-                    [a, _, ..] => Some(Rc::new(ErrorObject { message: format!("argument[0] to `push` must be ARRAY, got {}", a.object_type())}))
-                }
-            })}),
+            Rc::new(BuiltinObject::typed("push", |(mut arr, target): (Vec<Rc<dyn Object>>, Rc<dyn Object>)| {
+                arr.push(target);
+                arr
+            }))
+        ),
+        (
+            "byte_len",
+            Rc::new(BuiltinObject::typed("byte_len", |(s,): (String,)| s.len() as i64))
+        ),
+    ].into_iter().chain(string_builtins()).chain(array_builtins()).chain(hash_builtins()).chain(number_builtins()).collect::<HashMap<&'static str, Rc<dyn Object>>>());
+
+    static CLOSURE_CALLERS: RefCell<Vec<Rc<ClosureCaller>>> = const { RefCell::new(vec![]) };
+    // 当前嵌套了多少层函数调用，evaluator 和 VM 的回调共用
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// 调用 evaluator 以外的函数对象，比如 VM 的闭包
+pub type ClosureCaller = dyn Fn(&Rc<dyn Object>, Vec<Rc<dyn Object>>) -> Option<Rc<dyn Object>>;
+
+/// 在 f 执行期间，内置函数调用回调时会把 evaluator 不认识的函数交给 caller
+/// VM 运行时用它让 map、sort 之类的内置函数能调用 VM 里的闭包
+pub fn with_closure_caller<R>(caller: Rc<ClosureCaller>, f: impl FnOnce() -> R) -> R {
+    CLOSURE_CALLERS.with(|c| c.borrow_mut().push(caller));
+    let result = f();
+    CLOSURE_CALLERS.with(|c| c.borrow_mut().pop());
+    result
+}
+
+/// 调用深度加一后执行 f，已经到了 max 层时不执行，直接返回 stack overflow
+/// 每层调用在宿主的栈上占用不少空间，剩余的栈不够时先在堆上分配新的栈段，
+/// 所以递归太深时报错而不是把宿主进程的栈撑爆
+pub fn with_call_depth(
+    max: usize,
+    f: impl FnOnce() -> Option<Rc<dyn Object>>,
+) -> Option<Rc<dyn Object>> {
+    let depth = CALL_DEPTH.with(|d| d.get());
+    if depth >= max {
+        return Some(Rc::new(ErrorObject::new(
+            ErrorKind::Error,
+            "stack overflow",
+        )));
+    }
+    CALL_DEPTH.with(|d| d.set(depth + 1));
+    let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, f);
+    CALL_DEPTH.with(|d| d.set(depth));
+    result
+}
+
+/// 当前嵌套了多少层函数调用
+pub fn call_depth() -> usize {
+    CALL_DEPTH.with(|d| d.get())
+}
+
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+/// 脚本函数的参数个数不对，两个后端报一样的错
+pub fn arity_error(name: Option<&str>, want: usize, got: usize) -> ErrorObject {
+    let function = name.map_or("anonymous function".to_string(), |n| format!("`{}`", n));
+    ErrorObject::new(
+        ErrorKind::ArgumentError,
+        format!(
+            "wrong number of arguments to {}: want={}, got={}",
+            function, want, got
         ),
-    ].iter().cloned().collect::<HashMap<&'static str, Rc<dyn Object>>>()); // Rc::new(HashMap::new());
+    )
+}
+
+/// 给内置函数用的调用入口，没有值时是 null
+pub fn call_function(func: &Rc<dyn Object>, args: Vec<Rc<dyn Object>>) -> Rc<dyn Object> {
+    let any = func.as_any();
+    let result = if any.is::<FunctionObject>() || any.is::<BuiltinObject>() {
+        apply_function(func.clone(), args)
+    } else if let Some(caller) = CLOSURE_CALLERS.with(|c| c.borrow().last().cloned()) {
+        caller(func, args)
+    } else {
+        return Rc::new(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!("calling non-function: {}", func.object_type()),
+        ));
+    };
+    result.unwrap_or_else(|| NULLOBJ.with(|n| n.clone()))
 }
 
 pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
+    let tracer = context.tracer();
+    if let Some(t) = tracer.as_ref() {
+        t.on_event(&TraceEvent::NodeEntered(node));
+    }
+    // 每个节点都有值，没有值的语句（比如 let）得到 null
+    let result = eval_node(node, context).or_else(|| Some(NULLOBJ.with(|n| n.clone())));
+    let result = with_error_span(result, node.span());
+    if let Some(t) = tracer.as_ref() {
+        if let Some(value) = result.as_ref() {
+            t.on_event(&TraceEvent::ValueProduced(node, value));
+        }
+        t.on_event(&TraceEvent::NodeExited(node));
+    }
+    result
+}
+
+/// 错误第一次从某个节点返回时记下这个节点的位置，外层的节点不会再覆盖
+fn with_error_span(result: Option<Rc<dyn Object>>, span: Span) -> Option<Rc<dyn Object>> {
+    if let Some(err) = result
+        .as_ref()
+        .and_then(|r| r.as_any().downcast_ref::<ErrorObject>())
+    {
+        if err.span.is_none() && span.line > 0 {
+            return Some(Rc::new(ErrorObject {
+                span: Some(span),
+                ..err.clone()
+            }));
+        }
+    }
+    result
+}
+
+fn eval_node(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
     let n = node.as_any();
     // println!("eval: {:?}", node);
     // Program
     // ExpressionStatement
     // IntegerLiteral
-    println!("eval: {}", node);
     if n.is::<Program>() {
         if let Some(n) = n.downcast_ref::<Program>() {
             return eval_program(n.statement.clone(), Some(context.clone()));
@@ -139,7 +207,11 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
     if n.is::<ExpressionStatement>() {
         if let Some(n) = n.downcast_ref::<ExpressionStatement>() {
             // println!("ExpressionStatement {:?}", n);
-            return eval(n.expression.as_ref().unwrap().upcast(), context.clone());
+            return match n.expression {
+                Some(ref ex) => eval(ex.upcast(), context.clone()),
+                // 没有表达式的语句和 let 一样，值是 null
+                None => Some(NULLOBJ.with(|val| val.clone())),
+            };
         }
     }
     if n.is::<IntegerLiteral>() {
@@ -147,6 +219,17 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
             return Some(Rc::new(Integer { value: n.value }));
         }
     }
+    if let Some(n) = n.downcast_ref::<FloatLiteral>() {
+        return Some(Rc::new(Float { value: n.value }));
+    }
+    if let Some(n) = n.downcast_ref::<BigIntegerLiteral>() {
+        return Some(Rc::new(BigInteger {
+            value: n.value.clone(),
+        }));
+    }
+    if n.is::<NullLiteral>() {
+        return Some(NULLOBJ.with(|val| val.clone()));
+    }
     if n.is::<BooleanLiteral>() {
         if let Some(n) = n.downcast_ref::<BooleanLiteral>() {
             return Some(if n.value {
@@ -163,7 +246,6 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
             // return Some(Rc::new(If));
         }
     }
-    // null is ident
     if n.is::<Identifier>() {
         if let Some(n) = n.downcast_ref::<Identifier>() {
             // println!("W====================== {}", n.token);
@@ -178,9 +260,10 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
                 return Some(val.clone());
             }
 
-            return Some(Rc::new(ErrorObject {
-                message: format!("identifier not found: {}", n),
-            }));
+            return Some(Rc::new(ErrorObject::new(
+                ErrorKind::NameError,
+                format!("identifier not found: {}", n),
+            )));
         }
         // if let Some(n) = n.downcast_ref::<Null>() {
         //     // return Some(NULLOBJ.with(|val| val.clone()));
@@ -195,7 +278,7 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
                     if r.as_any().is::<ErrorObject>() {
                         return result;
                     }
-                    context.set(n.name.clone(), r.clone());
+                    context.set(n.name.clone(), named_function(r, &n.name.value));
                 }
 
                 // if r is error, return
@@ -205,36 +288,80 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
     if n.is::<PrefixExpression>() {
         if let Some(n) = n.downcast_ref::<PrefixExpression>() {
             let right = eval(n.right.as_ref().unwrap().upcast(), context.clone());
+            if let Some(r) = right.as_ref().filter(|r| is_error(r)) {
+                return Some(r.clone());
+            }
             return eval_prefix_expression(&n.operator, right);
         }
     }
     if n.is::<InfixExpression>() {
         if let Some(n) = n.downcast_ref::<InfixExpression>() {
+            if n.operator == LOGICAND || n.operator == LOGICOR {
+                return eval_logical_expression(n, context.clone());
+            }
+            if n.operator == NULLISH {
+                return eval_nullish_expression(n, context.clone());
+            }
+            // 操作数出错时把错误原样往外传，左边出错时右边不再求值
             let left = eval(n.left.as_ref().unwrap().upcast(), context.clone());
+            if let Some(l) = left.as_ref().filter(|l| is_error(l)) {
+                return Some(l.clone());
+            }
             let right = eval(n.right.as_ref().unwrap().upcast(), context.clone());
+            if let Some(r) = right.as_ref().filter(|r| is_error(r)) {
+                return Some(r.clone());
+            }
             return eval_infix_expression(&n.operator, left, right);
         }
     }
     if n.is::<BlockStatement>() {
-        println!("eval block Statement");
         if let Some(n) = n.downcast_ref::<BlockStatement>() {
             return eval_block_statement(n.clone(), context.clone());
         }
     }
     if n.is::<ReturnStatement>() {
         if let Some(n) = n.downcast_ref::<ReturnStatement>() {
-            if n.return_value.is_some() {
-                if let Some(value) =
-                    eval(n.return_value.as_ref().unwrap().upcast(), context.clone())
-                {
-                    return Some(Rc::new(ReturnValue { value }));
+            let value = match n.return_value.as_ref() {
+                Some(return_value) => eval(return_value.upcast(), context.clone()),
+                None => Some(NULLOBJ.with(|val| val.clone())),
+            };
+            if let Some(value) = value {
+                if is_error(&value) {
+                    return Some(value);
                 }
+                return Some(Rc::new(ReturnValue { value }));
             }
         }
     }
+    if let Some(n) = n.downcast_ref::<AssignExpression>() {
+        return eval_assign_expression(n, context.clone());
+    }
+    if let Some(n) = n.downcast_ref::<WhileStatement>() {
+        return eval_while_statement(n, context.clone());
+    }
+    if let Some(n) = n.downcast_ref::<ForStatement>() {
+        return eval_for_statement(n, context.clone());
+    }
+    if let Some(n) = n.downcast_ref::<ThrowStatement>() {
+        let value = eval(n.value.upcast(), context.clone())?;
+        if is_error(&value) {
+            return Some(value);
+        }
+        return Some(Rc::new(ErrorObject::from_thrown(&value)));
+    }
+    if let Some(n) = n.downcast_ref::<TryStatement>() {
+        return eval_try_statement(n, context.clone());
+    }
+    if n.is::<BreakStatement>() {
+        return Some(Rc::new(BreakValue {}));
+    }
+    if n.is::<ContinueStatement>() {
+        return Some(Rc::new(ContinueValue {}));
+    }
     if n.is::<FunctionLiteral>() {
         if let Some(n) = n.downcast_ref::<FunctionLiteral>() {
             let function = Rc::new(FunctionObject {
+                name: n.name.as_ref().map(|i| i.value.clone()),
                 parameters: n.parameters.clone(),
                 body: n.body.clone(),
                 context: context.clone(),
@@ -257,10 +384,8 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
                         n.arguments.as_ref().unwrap_or(&vec![]),
                         context.clone(),
                     ) {
-                        Ok(args) => apply_function(r, args),
-                        Err(id) => Some(Rc::new(ErrorObject {
-                            message: format!("Cannot eval arguments at position: {}", id),
-                        })),
+                        Ok(args) => with_stack_frame(apply_function(r.clone(), args), &r, n),
+                        Err(err) => Some(err),
                     };
                 }
             }
@@ -273,21 +398,25 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
             }));
         }
     }
+    if let Some(t) = n.downcast_ref::<TemplateLiteral>() {
+        return eval_template_literal(t, context);
+    }
     if n.is::<ArrayLiteral>() {
         if let Some(arr) = n.downcast_ref::<ArrayLiteral>() {
             return match eval_expressions(&arr.elements.clone(), context.clone()) {
                 Ok(elements) => Some(Rc::new(ArrayObject {
                     elements: elements.into(),
                 })),
-                Err(id) => Some(Rc::new(ErrorObject {
-                    message: format!("Cannot eval arguments at position: {}", id),
-                })),
+                Err(err) => Some(err),
             };
         }
     }
     if n.is::<IndexExpression>() {
         if let Some(exp) = n.downcast_ref::<IndexExpression>() {
             let left = eval(exp.left.as_ref().upcast(), context.clone());
+            if exp.optional && left.as_ref().is_some_and(|l| l.as_any().is::<Null>()) {
+                return left;
+            }
             // if is error left return ErrorObject
             let index = eval(exp.index.as_ref().upcast(), context.clone());
             // if is error index
@@ -298,80 +427,194 @@ pub fn eval(node: &dyn Node, context: Rc<Context>) -> Option<Rc<dyn Object>> {
                 (Some(l), _) if is_error(&l) => Some(l),
                 (_, Some(i)) if is_error(&i) => Some(i),
                 // FIXME: ErrorObject message
-                _ => Some(Rc::new(ErrorObject {
-                    message: format!("cannot eval {}", exp),
-                })),
+                _ => Some(Rc::new(ErrorObject::new(
+                    ErrorKind::Error,
+                    format!("cannot eval {}", exp),
+                ))),
             };
         }
     }
-    if n.is::<HashLiteral>() {
-        if let Some(h) = n.downcast_ref::<HashLiteral>() {
-            return Some(Rc::new(HashObject {
-                pairs: RefCell::new(
-                    h.pairs
-                        .borrow()
-                        .iter()
-                        .map(|(k, v)| {
-                            (
-                                // FIXME: error Object handling
-                                k.clone(),
-                                eval(v.upcast(), context.clone()).unwrap(),
-                            )
-                        })
-                        .collect::<HashMap<Rc<String>, Rc<dyn Object>>>(),
-                ),
-            }));
-        }
+    if let Some(h) = n.downcast_ref::<HashLiteral>() {
+        return eval_hash_literal(h, context.clone());
     }
     None
 }
 
+pub fn eval_template_literal(t: &TemplateLiteral, context: Rc<Context>) -> Option<Rc<dyn Object>> {
+    let mut values = vec![];
+    for part in t.parts.iter() {
+        values.push(match part {
+            TemplatePart::Text(s) => Rc::new(StringObject { value: s.clone() }),
+            TemplatePart::Expression(e) => {
+                let v = eval(e.upcast(), context.clone())
+                    .unwrap_or_else(|| NULLOBJ.with(|n| n.clone()));
+                if is_error(&v) {
+                    return Some(v);
+                }
+                v
+            }
+        });
+    }
+    Some(concat_template(&values))
+}
+
+/// 模板字符串的每一段拼起来，字符串按原样，其他值用 inspect 的结果
+pub fn concat_template(values: &[Rc<dyn Object>]) -> Rc<dyn Object> {
+    Rc::new(StringObject {
+        value: Rc::new(values.iter().map(|v| v.inspect()).collect()),
+    })
+}
+
 pub fn is_error(object: &Rc<dyn Object>) -> bool {
     object.object_type() == ERROR_OBJECT
 }
+/// 调用函数，函数体里 return 的值在这里解开，不会继续往调用方的块里传
 pub fn apply_function(func: Rc<dyn Object>, args: Vec<Rc<dyn Object>>) -> Option<Rc<dyn Object>> {
     if let Some(f) = func.as_any().downcast_ref::<FunctionObject>() {
-        let extended_context = extend_function_context(f, &args);
-        if let Some(ref body) = f.body {
-            let evaluated = eval(body.as_ref().upcast(), extended_context);
-            return evaluated;
+        let want = f.parameters.as_ref().map_or(0, |p| p.len());
+        if want != args.len() {
+            return Some(Rc::new(arity_error(f.name.as_deref(), want, args.len())));
         }
+        return with_call_depth(f.context.max_call_depth(), || {
+            let extended_context = extend_function_context(f, &args);
+            let evaluated = match f.body {
+                Some(ref body) => eval(body.as_ref().upcast(), extended_context),
+                None => None,
+            };
+            match escaped_loop_control(evaluated) {
+                Some(r) => match r.as_any().downcast_ref::<ReturnValue>() {
+                    Some(rv) => Some(rv.value.clone()),
+                    None => Some(r),
+                },
+                None => Some(NULLOBJ.with(|n| n.clone())),
+            }
+        });
     }
     if let Some(f) = func.as_any().downcast_ref::<BuiltinObject>() {
-        return (f.func)(args.clone());
+        return (f.func)(args.clone()).or_else(|| Some(NULLOBJ.with(|n| n.clone())));
     }
-    None
+    Some(Rc::new(ErrorObject::new(
+        ErrorKind::TypeError,
+        format!("calling non-function: {}", func.object_type()),
+    )))
 }
 
 pub fn eval_index_expression(
     left: Rc<dyn Object>,
     index: Rc<dyn Object>,
 ) -> Option<Rc<dyn Object>> {
-    return match (left.object_type(), index.object_type()) {
+    match (left.object_type(), index.object_type()) {
         (ARRAY_OBJECT, INTEGER_OBJECT) => eval_array_index_expression(left, index),
-        _ => None,
+        (STRING_OBJECT, INTEGER_OBJECT) => eval_string_index_expression(left, index),
+        (HASH_OBJECT, _) => eval_hash_index_expression(left, index),
+        _ => Some(Rc::new(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!("index operator not supported: {}", left.object_type()),
+        ))),
+    }
+}
+
+/// key 不存在时得到 null，key 不能做 hash key 时是错误
+pub fn eval_hash_index_expression(
+    hash: Rc<dyn Object>,
+    index: Rc<dyn Object>,
+) -> Option<Rc<dyn Object>> {
+    let hash = hash.as_any().downcast_ref::<HashObject>()?;
+    let key = match HashKey::try_from(&index) {
+        Ok(key) => key,
+        Err(message) => return Some(Rc::new(ErrorObject::new(ErrorKind::TypeError, message))),
     };
+    let value = hash.get(&key);
+    Some(value.unwrap_or_else(|| NULLOBJ.with(|n| n.clone())))
+}
+
+/// key 和 value 按源码的顺序求值，重复的 key 后面的覆盖前面的
+pub fn eval_hash_literal(h: &HashLiteral, context: Rc<Context>) -> Option<Rc<dyn Object>> {
+    let mut pairs = HashPairs::new();
+    for (k, v) in h.pairs.borrow().iter() {
+        let key = match eval(k.upcast(), context.clone()) {
+            Some(key) if is_error(&key) => return Some(key),
+            Some(key) => key,
+            None => return None,
+        };
+        let key = match HashKey::try_from(&key) {
+            Ok(key) => key,
+            Err(message) => {
+                return with_error_span(
+                    Some(Rc::new(ErrorObject::new(ErrorKind::TypeError, message))),
+                    k.span(),
+                )
+            }
+        };
+        match eval(v.upcast(), context.clone()) {
+            Some(value) if is_error(&value) => return Some(value),
+            Some(value) => pairs.insert(key, value),
+            None => return None,
+        };
+    }
+    Some(Rc::new(HashObject::new(pairs)))
 }
 
 pub fn eval_array_index_expression(
     arr: Rc<dyn Object>,
     index: Rc<dyn Object>,
 ) -> Option<Rc<dyn Object>> {
-    return match (
+    match (
         arr.as_ref().as_any().downcast_ref::<ArrayObject>(),
         index.as_ref().as_any().downcast_ref::<Integer>(),
     ) {
         (Some(arr), Some(index)) => {
-            let max = arr.elements.borrow().len() - 1;
-            if index.value > max as i64 || index.value < 0 {
-                return Some(NULLOBJ.with(|n| n.clone()));
+            let elements = arr.elements.borrow();
+            match usize::try_from(index.value)
+                .ok()
+                .and_then(|i| elements.get(i))
+            {
+                Some(el) => Some(el.clone()),
+                None => Some(NULLOBJ.with(|n| n.clone())),
             }
-            Some(arr.elements.borrow()[index.value as usize].clone())
         }
         _ => Some(NULLOBJ.with(|n| n.clone())),
+    }
+}
+
+/// 字符串按字符取下标，越界时和数组一样得到 null
+pub fn eval_string_index_expression(
+    s: Rc<dyn Object>,
+    index: Rc<dyn Object>,
+) -> Option<Rc<dyn Object>> {
+    let s = s.as_any().downcast_ref::<StringObject>()?;
+    let index = index.as_any().downcast_ref::<Integer>()?;
+    let ch = usize::try_from(index.value)
+        .ok()
+        .and_then(|i| s.value.chars().nth(i));
+    Some(match ch {
+        Some(ch) => ch.to_string().into_object(),
+        None => NULLOBJ.with(|n| n.clone()),
+    })
+}
+
+/// 切片的范围，负数从结尾往前数，超出范围的部分截掉
+pub fn slice_range(len: usize, start: i64, end: Option<i64>) -> (usize, usize) {
+    let clamp = |i: i64| {
+        let i = if i < 0 { i + len as i64 } else { i };
+        i.clamp(0, len as i64) as usize
     };
+    let start = clamp(start);
+    let end = end.map_or(len, clamp);
+    (start, end.max(start))
 }
 //
+/// let f = fn() {} 时把匿名函数命名为 f，其他值原样返回
+fn named_function(value: &Rc<dyn Object>, name: &str) -> Rc<dyn Object> {
+    match value.as_any().downcast_ref::<FunctionObject>() {
+        Some(f) if f.name.is_none() => Rc::new(FunctionObject {
+            name: Some(name.to_string()),
+            ..f.clone()
+        }),
+        _ => value.clone(),
+    }
+}
+
 pub fn extend_function_context(func: &FunctionObject, args: &Vec<Rc<dyn Object>>) -> Rc<Context> {
     let context = Context::extend(func.context.clone());
     // func.parameters
@@ -383,33 +626,310 @@ pub fn extend_function_context(func: &FunctionObject, args: &Vec<Rc<dyn Object>>
     Rc::new(context)
 }
 
+/// 从左到右求值，遇到第一个错误就停下来把它返回
 pub fn eval_expressions(
     exps: &Vec<Rc<dyn Expression>>,
     context: Rc<Context>,
-) -> Result<Vec<Rc<dyn Object>>, usize> {
-    let exps: Vec<_> = exps
-        .iter()
-        .map(|exp| eval(exp.upcast(), context.clone()))
-        .collect();
-    if let Some((id, _)) = exps.iter().enumerate().find(|(_idx, item)| item.is_none()) {
-        return Err(id);
+) -> Result<Vec<Rc<dyn Object>>, Rc<dyn Object>> {
+    let mut values = Vec::with_capacity(exps.len());
+    for exp in exps {
+        let value =
+            eval(exp.upcast(), context.clone()).unwrap_or_else(|| NULLOBJ.with(|n| n.clone()));
+        if is_error(&value) {
+            return Err(value);
+        }
+        values.push(value);
     }
-    return Ok(exps.iter().map(|item| item.clone().unwrap()).collect());
+    Ok(values)
 }
 
+/// 依次检查 if 和每个 else if 的条件，表达式的值就是走到的那个分支的值，都没走到时是 null
 pub fn eval_if_expression(ex: &IfExpression, context: Rc<Context>) -> Option<Rc<dyn Object>> {
-    if is_truthy(eval(ex.condition.upcast(), context.clone())) {
-        return eval(ex.consequence.as_ref().unwrap().upcast(), context.clone());
-    } else if ex.alternative.is_some() {
-        return eval(ex.alternative.as_ref().unwrap().upcast(), context.clone());
-    } else {
-        return Some(NULLOBJ.with(|val| val.clone()));
+    let branches = std::iter::once((&ex.condition, ex.consequence.as_ref().unwrap()))
+        .chain(ex.else_ifs.iter().map(|(c, b)| (c, b)));
+    for (condition, consequence) in branches {
+        let condition = eval(condition.upcast(), context.clone());
+        if let Some(c) = condition.as_ref().filter(|c| is_error(c)) {
+            return Some(c.clone());
+        }
+        if is_truthy(condition) {
+            return eval(consequence.upcast(), context.clone());
+        }
+    }
+    match &ex.alternative {
+        Some(alternative) => eval(alternative.upcast(), context.clone()),
+        None => Some(NULLOBJ.with(|val| val.clone())),
+    }
+}
+
+/// 赋值表达式的值就是赋进去的值
+/// x += 1 这样的复合赋值先读出旧值，再用去掉 = 的运算符算出新值
+pub fn eval_assign_expression(
+    n: &AssignExpression,
+    context: Rc<Context>,
+) -> Option<Rc<dyn Object>> {
+    let operator = n.operator.trim_end_matches('=');
+    if let Some(id) = n.target.as_any().downcast_ref::<Identifier>() {
+        let id = Rc::new(id.clone());
+        let current = match context.get(&id) {
+            Some(current) => current,
+            None => return Some(undeclared_variable_error(&id.value)),
+        };
+        let value = eval(n.value.upcast(), context.clone())?;
+        if is_error(&value) {
+            return Some(value);
+        }
+        let value = if operator.is_empty() {
+            value
+        } else {
+            eval_infix_expression(operator, Some(current), Some(value))?
+        };
+        if !is_error(&value) && !context.assign(&id, value.clone()) {
+            return Some(undeclared_variable_error(&id.value));
+        }
+        return Some(value);
+    }
+    if let Some(ix) = n.target.as_any().downcast_ref::<IndexExpression>() {
+        let mut operands = vec![];
+        for exp in [&ix.left, &ix.index, &n.value] {
+            match eval(exp.upcast(), context.clone()) {
+                Some(r) if is_error(&r) => return Some(r),
+                Some(r) => operands.push(r),
+                None => return Some(NULLOBJ.with(|val| val.clone())),
+            }
+        }
+        let value = operands.pop().unwrap();
+        let index = operands.pop().unwrap();
+        let left = operands.pop().unwrap();
+        return Some(eval_index_assignment(left, index, operator, value));
+    }
+    Some(Rc::new(ErrorObject::new(
+        ErrorKind::TypeError,
+        format!("invalid assignment target: {}", n.target),
+    )))
+}
+
+fn undeclared_variable_error(name: &str) -> Rc<dyn Object> {
+    Rc::new(ErrorObject::new(
+        ErrorKind::NameError,
+        format!("assignment to undeclared variable: {}", name),
+    ))
+}
+
+/// a[i] = v 和 h["k"] = v，直接修改原来的数组或者 hash，所有引用它的地方都能看到
+/// operator 不为空时是复合赋值，vm 也复用这里
+pub fn eval_index_assignment(
+    left: Rc<dyn Object>,
+    index: Rc<dyn Object>,
+    operator: &str,
+    value: Rc<dyn Object>,
+) -> Rc<dyn Object> {
+    let error = |kind: ErrorKind, message: String| -> Rc<dyn Object> {
+        Rc::new(ErrorObject::new(kind, message))
+    };
+    let combine = |current: Option<Rc<dyn Object>>| -> Rc<dyn Object> {
+        if operator.is_empty() {
+            return value.clone();
+        }
+        match current {
+            Some(current) => eval_infix_expression(operator, Some(current), Some(value.clone()))
+                .unwrap_or_else(|| NULLOBJ.with(|val| val.clone())),
+            None => error(
+                ErrorKind::ValueError,
+                format!("key not found: {}", index.inspect()),
+            ),
+        }
+    };
+    let (l, i) = (left.as_any(), index.as_any());
+    if let (Some(arr), Some(i)) = (l.downcast_ref::<ArrayObject>(), i.downcast_ref::<Integer>()) {
+        let len = arr.elements.borrow().len();
+        if i.value < 0 || i.value as usize >= len {
+            return error(
+                ErrorKind::ValueError,
+                format!("index out of range: {}", i.value),
+            );
+        }
+        let current = arr.elements.borrow()[i.value as usize].clone();
+        let value = combine(Some(current));
+        if !is_error(&value) {
+            arr.elements.borrow_mut()[i.value as usize] = value.clone();
+        }
+        return value;
+    }
+    if let Some(h) = l.downcast_ref::<HashObject>() {
+        let key = match HashKey::try_from(&index) {
+            Ok(key) => key,
+            Err(message) => return error(ErrorKind::TypeError, message),
+        };
+        let value = combine(h.get(&key));
+        if !is_error(&value) {
+            h.insert(key, value.clone());
+        }
+        return value;
+    }
+    error(
+        ErrorKind::TypeError,
+        format!(
+            "index assignment not supported: {}[{}]",
+            left.object_type(),
+            index.object_type()
+        ),
+    )
+}
+
+/// 循环体和外层共用一个 context，循环变量就像每次迭代都 let 了一遍
+pub fn eval_while_statement(n: &WhileStatement, context: Rc<Context>) -> Option<Rc<dyn Object>> {
+    loop {
+        let condition = eval(n.condition.upcast(), context.clone());
+        if let Some(c) = condition.as_ref().filter(|c| is_error(c)) {
+            return Some(c.clone());
+        }
+        if !is_truthy(condition) {
+            break;
+        }
+        match eval_loop_body(&n.body, context.clone()) {
+            Ok(true) => continue,
+            Ok(false) => break,
+            Err(r) => return Some(r),
+        }
+    }
+    Some(NULLOBJ.with(|val| val.clone()))
+}
+
+pub fn eval_for_statement(n: &ForStatement, context: Rc<Context>) -> Option<Rc<dyn Object>> {
+    let iterable = match eval(n.iterable.upcast(), context.clone()) {
+        Some(r) if is_error(&r) => return Some(r),
+        Some(r) => r,
+        None => return Some(NULLOBJ.with(|val| val.clone())),
+    };
+    let items = match iterable_items(&iterable) {
+        Ok(items) => items,
+        Err(e) => return with_error_span(Some(e), n.iterable.span()),
+    };
+    for item in items {
+        context.set(n.variable.clone(), item);
+        match eval_loop_body(&n.body, context.clone()) {
+            Ok(true) => continue,
+            Ok(false) => break,
+            Err(r) => return Some(r),
+        }
+    }
+    Some(NULLOBJ.with(|val| val.clone()))
+}
+
+/// 执行一次循环体，Ok(true) 表示继续下一轮，Ok(false) 表示 break，
+/// return 和错误放在 Err 里交给外层
+fn eval_loop_body(body: &Rc<dyn Statement>, context: Rc<Context>) -> Result<bool, Rc<dyn Object>> {
+    match eval(body.upcast(), context) {
+        Some(r) if r.object_type() == BREAK_OBJECT => Ok(false),
+        Some(r) if r.object_type() == ERROR_OBJECT || r.object_type() == RETURN_VALUE_OBJECT => {
+            Err(r)
+        }
+        _ => Ok(true),
+    }
+}
+
+/// for 循环能遍历的东西：数组的元素、字符串的每个字符、hash 按插入顺序的 key
+/// 遍历的是开始时的快照，循环体里修改原数组不会影响这次循环
+pub fn iterable_items(obj: &Rc<dyn Object>) -> Result<Vec<Rc<dyn Object>>, Rc<dyn Object>> {
+    let o = obj.as_any();
+    if let Some(arr) = o.downcast_ref::<ArrayObject>() {
+        return Ok(arr.elements.borrow().clone());
+    }
+    if let Some(s) = o.downcast_ref::<StringObject>() {
+        return Ok(s
+            .value
+            .chars()
+            .map(|c| {
+                Rc::new(StringObject {
+                    value: Rc::new(c.to_string()),
+                }) as Rc<dyn Object>
+            })
+            .collect());
+    }
+    if let Some(h) = o.downcast_ref::<HashObject>() {
+        return Ok(h.pairs.borrow().keys().map(|k| k.to_object()).collect());
+    }
+    Err(Rc::new(ErrorObject::new(
+        ErrorKind::TypeError,
+        format!("not iterable: {}", obj.object_type()),
+    )))
+}
+
+/// 脚本函数返回错误时，在错误的调用栈上记下这次调用
+/// 和参数个数的报错一样用函数自己的名字，VM 只知道被调用的是哪个函数
+fn with_stack_frame(
+    result: Option<Rc<dyn Object>>,
+    func: &Rc<dyn Object>,
+    call: &CallExpression,
+) -> Option<Rc<dyn Object>> {
+    let err = result
+        .as_ref()
+        .and_then(|r| r.as_any().downcast_ref::<ErrorObject>());
+    let (Some(err), Some(f)) = (err, func.as_any().downcast_ref::<FunctionObject>()) else {
+        return result;
+    };
+    let mut err = err.clone();
+    err.stack.push(StackFrame {
+        function: f.name.clone().unwrap_or_else(|| "<anonymous>".to_string()),
+        span: call.span(),
+    });
+    Some(Rc::new(err))
+}
+
+/// try 里的错误交给 catch，catch 的参数是错误转成的 hash，和循环变量一样绑定在当前作用域里
+/// finally 总会执行，它自己出错、return、break 或者 continue 时会盖掉前面的结果
+/// 和循环一样，整个语句的值是 null
+pub fn eval_try_statement(n: &TryStatement, context: Rc<Context>) -> Option<Rc<dyn Object>> {
+    let mut result = eval(n.body.upcast(), context.clone());
+    if let Some(catch_body) = n.catch_body.as_ref() {
+        if let Some(err) = result
+            .as_ref()
+            .and_then(|r| r.as_any().downcast_ref::<ErrorObject>())
+        {
+            if let Some(param) = n.catch_param.as_ref() {
+                context.set(param.clone(), err.to_hash());
+            }
+            result = eval(catch_body.upcast(), context.clone());
+        }
+    }
+    if let Some(finally_body) = n.finally_body.as_ref() {
+        let r = eval(finally_body.upcast(), context);
+        if r.as_ref().is_some_and(is_control_flow) {
+            return r;
+        }
+    }
+    match result {
+        Some(r) if is_control_flow(&r) => Some(r),
+        _ => Some(NULLOBJ.with(|val| val.clone())),
+    }
+}
+
+/// 需要跳出当前语句继续往外传的值：错误、return、break 和 continue
+fn is_control_flow(obj: &Rc<dyn Object>) -> bool {
+    matches!(
+        obj.object_type(),
+        ERROR_OBJECT | RETURN_VALUE_OBJECT | BREAK_OBJECT | CONTINUE_OBJECT
+    )
+}
+
+/// 没有被循环接住的 break/continue 到了函数或者程序边界就是错误
+fn escaped_loop_control(result: Option<Rc<dyn Object>>) -> Option<Rc<dyn Object>> {
+    match result.as_ref().map(|r| r.object_type()) {
+        Some(BREAK_OBJECT) => Some(Rc::new(ErrorObject::new(
+            ErrorKind::Error,
+            "break outside loop",
+        ))),
+        Some(CONTINUE_OBJECT) => Some(Rc::new(ErrorObject::new(
+            ErrorKind::Error,
+            "continue outside loop",
+        ))),
+        _ => result,
     }
 }
 
 pub fn is_truthy(obj: Option<Rc<dyn Object>>) -> bool {
-    println!("is_truthy: {:?}", obj);
-    obj.map_or(false, |val| {
+    obj.is_some_and(|val| {
         let v_a = val.as_any();
         if v_a.is::<Null>() {
             return false;
@@ -421,6 +941,61 @@ pub fn is_truthy(obj: Option<Rc<dyn Object>>) -> bool {
     })
 }
 
+/// && 和 || 是短路求值的，左边已经能决定结果时右边不会被求值
+/// 结果总是 Boolean
+pub fn eval_logical_expression(
+    n: &InfixExpression,
+    context: Rc<Context>,
+) -> Option<Rc<dyn Object>> {
+    let left = eval(n.left.as_ref().unwrap().upcast(), context.clone());
+    if left.as_ref().is_some_and(is_error) {
+        return left;
+    }
+    let left = is_truthy(left);
+    if (n.operator == LOGICAND && !left) || (n.operator == LOGICOR && left) {
+        return Some(native_bool_to_boolean_object(left));
+    }
+    let right = eval(n.right.as_ref().unwrap().upcast(), context);
+    if right.as_ref().is_some_and(is_error) {
+        return right;
+    }
+    Some(native_bool_to_boolean_object(is_truthy(right)))
+}
+
+/// ?? 只有左边是 null 时才对右边求值，false、0 这些值会原样留下
+pub fn eval_nullish_expression(
+    n: &InfixExpression,
+    context: Rc<Context>,
+) -> Option<Rc<dyn Object>> {
+    let left = eval(n.left.as_ref().unwrap().upcast(), context.clone());
+    match left {
+        Some(l) if !l.as_any().is::<Null>() => Some(l),
+        _ => eval(n.right.as_ref().unwrap().upcast(), context),
+    }
+}
+
+pub fn native_bool_to_boolean_object(value: bool) -> Rc<dyn Object> {
+    if value {
+        TRUEOBJ.with(|val| val.clone())
+    } else {
+        FALSEOBJ.with(|val| val.clone())
+    }
+}
+
+/// Integer、BigInteger、Rational 和 Float 都可以参与浮点运算
+pub fn number_to_f64(obj: &Rc<dyn Object>) -> Option<f64> {
+    if let Some(i) = obj.as_any().downcast_ref::<Integer>() {
+        return Some(i.value as f64);
+    }
+    if let Some(b) = obj.as_any().downcast_ref::<BigInteger>() {
+        return b.value.to_f64();
+    }
+    if let Some(r) = obj.as_any().downcast_ref::<Rational>() {
+        return r.value.to_f64();
+    }
+    obj.as_any().downcast_ref::<Float>().map(|f| f.value)
+}
+
 pub fn eval_infix_expression(
     operator: &str,
     left: Option<Rc<dyn Object>>,
@@ -435,18 +1010,20 @@ pub fn eval_infix_expression(
             let r = r.as_any().downcast_ref::<Integer>().unwrap();
             // Some(Rc::new(Integer { value: val }))
             match operator {
-                "+" => Some(Rc::new(Integer {
-                    value: l.value + r.value,
-                })),
-                "-" => Some(Rc::new(Integer {
-                    value: l.value - r.value,
+                "+" | "-" | "*" | "/" | "%" | "^^" => {
+                    Some(eval_integer_arithmetic(operator, l.value, r.value))
+                }
+                "&" => Some(Rc::new(Integer {
+                    value: l.value & r.value,
                 })),
-                "*" => Some(Rc::new(Integer {
-                    value: l.value * r.value,
+                "|" => Some(Rc::new(Integer {
+                    value: l.value | r.value,
                 })),
-                "/" => Some(Rc::new(Integer {
-                    value: l.value / r.value,
+                "^" => Some(Rc::new(Integer {
+                    value: l.value ^ r.value,
                 })),
+                "<=" => Some(native_bool_to_boolean_object(l.value <= r.value)),
+                ">=" => Some(native_bool_to_boolean_object(l.value >= r.value)),
                 "<" => Some(if l.value < r.value {
                     TRUEOBJ.with(|val| val.clone())
                 } else {
@@ -467,7 +1044,15 @@ pub fn eval_infix_expression(
                 } else {
                     FALSEOBJ.with(|val| val.clone())
                 }),
-                _ => None,
+                _ => Some(Rc::new(ErrorObject::new(
+                    ErrorKind::TypeError,
+                    format!(
+                        "unknown operator: {} {} {}",
+                        l.object_type(),
+                        operator,
+                        r.object_type()
+                    ),
+                ))),
             }
         }
         (Some(l), Some(r))
@@ -488,16 +1073,54 @@ pub fn eval_infix_expression(
                 } else {
                     FALSEOBJ.with(|val| val.clone())
                 }),
-                _ => Some(Rc::new(ErrorObject {
-                    message: format!(
+                _ => Some(Rc::new(ErrorObject::new(
+                    ErrorKind::TypeError,
+                    format!(
                         "unknown operator: {} {} {}",
                         l.object_type(),
                         operator,
                         r.object_type()
                     ),
-                })),
+                ))),
+            }
+        }
+        // 只要有一边是 Float，整数就提升成 Float 再运算
+        (Some(l), Some(r))
+            if (l.as_any().is::<Float>() || r.as_any().is::<Float>())
+                && number_to_f64(l).is_some()
+                && number_to_f64(r).is_some() =>
+        {
+            let (a, b) = (number_to_f64(l).unwrap(), number_to_f64(r).unwrap());
+            match operator {
+                "+" => Some(Rc::new(Float { value: a + b })),
+                "-" => Some(Rc::new(Float { value: a - b })),
+                "*" => Some(Rc::new(Float { value: a * b })),
+                "/" => Some(Rc::new(Float { value: a / b })),
+                "^^" => Some(Rc::new(Float { value: a.powf(b) })),
+                "%" => Some(Rc::new(Float { value: a % b })),
+                "<" => Some(native_bool_to_boolean_object(a < b)),
+                ">" => Some(native_bool_to_boolean_object(a > b)),
+                "<=" => Some(native_bool_to_boolean_object(a <= b)),
+                ">=" => Some(native_bool_to_boolean_object(a >= b)),
+                "==" => Some(native_bool_to_boolean_object(a == b)),
+                "!=" => Some(native_bool_to_boolean_object(a != b)),
+                _ => Some(Rc::new(ErrorObject::new(
+                    ErrorKind::TypeError,
+                    format!(
+                        "unknown operator: {} {} {}",
+                        l.object_type(),
+                        operator,
+                        r.object_type()
+                    ),
+                ))),
             }
         }
+        (Some(l), Some(r)) if to_big_int(l).is_some() && to_big_int(r).is_some() => {
+            Some(eval_big_integer_infix(operator, l, r))
+        }
+        (Some(l), Some(r)) if to_rational(l).is_some() && to_rational(r).is_some() => {
+            Some(eval_rational_infix(operator, l, r))
+        }
         (Some(l), Some(r))
             if (left.as_ref().unwrap().as_any()).is::<StringObject>()
                 && (right.as_ref().unwrap().as_any()).is::<StringObject>() =>
@@ -518,27 +1141,43 @@ pub fn eval_infix_expression(
                 "+" => Some(Rc::new(StringObject {
                     value: Rc::new(format!("{}{}", l.value, r.value)),
                 })),
-                _ => Some(Rc::new(ErrorObject {
-                    message: format!(
+                // 按字典序比较，也就是逐个比较字符的码点
+                "<" => Some(native_bool_to_boolean_object(l.value < r.value)),
+                ">" => Some(native_bool_to_boolean_object(l.value > r.value)),
+                "<=" => Some(native_bool_to_boolean_object(l.value <= r.value)),
+                ">=" => Some(native_bool_to_boolean_object(l.value >= r.value)),
+                _ => Some(Rc::new(ErrorObject::new(
+                    ErrorKind::TypeError,
+                    format!(
                         "unknown operator: {} {} {}",
                         l.object_type(),
                         operator,
                         r.object_type()
                     ),
-                })),
+                ))),
             }
         }
-        (Some(a), Some(b)) => Some(Rc::new(ErrorObject {
-            message: format!(
+        // null 只和 null 相等，和其他任何值比较都不相等
+        (Some(l), Some(r))
+            if (operator == "==" || operator == "!=")
+                && (l.as_any().is::<Null>() || r.as_any().is::<Null>()) =>
+        {
+            let equal = l.as_any().is::<Null>() && r.as_any().is::<Null>();
+            Some(native_bool_to_boolean_object(equal == (operator == "==")))
+        }
+        (Some(a), Some(b)) => Some(Rc::new(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!(
                 "type mismatch: {} {} {}",
                 a.object_type(),
                 operator,
                 b.object_type()
             ),
-        })),
-        _ => Some(Rc::new(ErrorObject {
-            message: format!("{:?} {} {:?}", left.as_ref(), operator, right.as_ref()),
-        })),
+        ))),
+        _ => Some(Rc::new(ErrorObject::new(
+            ErrorKind::Error,
+            format!("{:?} {} {:?}", left.as_ref(), operator, right.as_ref()),
+        ))),
     }
 }
 
@@ -549,7 +1188,15 @@ pub fn eval_prefix_expression(
     match operator {
         "!" => eval_bang_operator_expression(right),
         "-" => eval_minus_prefix_operator_expression(right),
-        _ => Some(Rc::new(ErrorObject { message: "".into() })),
+        "+" | "~" => eval_number_prefix_expression(operator, right),
+        _ => Some(Rc::new(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!(
+                "unknown operator: {}{}",
+                operator,
+                right.map_or(NULL_OBJECT, |r| r.object_type())
+            ),
+        ))),
     }
 }
 
@@ -573,22 +1220,57 @@ pub fn eval_bang_operator_expression(right: Option<Rc<dyn Object>>) -> Option<Rc
     Some(FALSEOBJ.with(|val| val.clone()))
 }
 
+/// +x 原样返回数字，~x 按位取反，只能用于整数
+pub fn eval_number_prefix_expression(
+    operator: &str,
+    right: Option<Rc<dyn Object>>,
+) -> Option<Rc<dyn Object>> {
+    let right = right?;
+    let r = right.as_any();
+    match operator {
+        "+" if number_to_f64(&right).is_some() => Some(right),
+        "~" if r.is::<Integer>() => Some(Rc::new(Integer {
+            value: !r.downcast_ref::<Integer>().unwrap().value,
+        })),
+        "~" if r.is::<BigInteger>() => Some(Rc::new(BigInteger {
+            value: !&r.downcast_ref::<BigInteger>().unwrap().value,
+        })),
+        _ => Some(Rc::new(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!("unknown operator: {}{}", operator, right.object_type()),
+        ))),
+    }
+}
+
 pub fn eval_minus_prefix_operator_expression(
     right: Option<Rc<dyn Object>>,
 ) -> Option<Rc<dyn Object>> {
     if let Some(right) = right {
-        if right.object_type() == INTEGER_OBJECT {
-            return Some(Rc::new(Integer {
-                value: -Integer::try_from(right).unwrap().value,
+        if let Some(i) = right.as_any().downcast_ref::<Integer>() {
+            return Some(eval_integer_negation(i.value));
+        }
+        if let Some(b) = right.as_any().downcast_ref::<BigInteger>() {
+            return Some(Rc::new(BigInteger {
+                value: -b.value.clone(),
             }));
         }
-        return Some(Rc::new(ErrorObject {
-            message: format!("unknown operator: -{}", right.object_type()),
-        }));
+        if let Some(r) = right.as_any().downcast_ref::<Rational>() {
+            return Some(Rc::new(Rational {
+                value: -r.value.clone(),
+            }));
+        }
+        if let Some(f) = right.as_any().downcast_ref::<Float>() {
+            return Some(Rc::new(Float { value: -f.value }));
+        }
+        return Some(Rc::new(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!("unknown operator: -{}", right.object_type()),
+        )));
     }
-    Some(Rc::new(ErrorObject {
-        message: "unknown operator: -".into(),
-    }))
+    Some(Rc::new(ErrorObject::new(
+        ErrorKind::TypeError,
+        "unknown operator: -",
+    )))
 }
 
 pub fn eval_program(
@@ -602,8 +1284,10 @@ pub fn eval_program(
         // converter Statement to Node
         // rust not support convert sub-trait-object to parent-trait-object
         // so here using a upcast function to convert Statement/Expression to Node trait
-        println!("try eval st: {:?}", st);
-        result = eval(st.upcast(), context.clone());
+        result = with_error_span(
+            escaped_loop_control(eval(st.upcast(), context.clone())),
+            st.span(),
+        );
         // if
         if let Some(r) = result.as_ref() {
             if r.as_any().is::<ErrorObject>() {
@@ -627,14 +1311,16 @@ pub fn eval_block_statement(blk: BlockStatement, context: Rc<Context>) -> Option
     let mut result = None;
     for st in blk.statement.iter() {
         result = eval(st.upcast(), context.clone());
-        if result.is_some() {
-            let r = result.as_ref().unwrap();
+        if let Some(r) = result.as_ref() {
             if r.object_type() == ERROR_OBJECT {
                 return result;
             }
             if r.object_type() == RETURN_VALUE_OBJECT {
                 return result;
             }
+            if r.object_type() == BREAK_OBJECT || r.object_type() == CONTINUE_OBJECT {
+                return result;
+            }
         }
     }
     result
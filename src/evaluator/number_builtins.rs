@@ -0,0 +1,187 @@
+use crate::evaluator::*;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Num, ToPrimitive, Zero};
+
+type Value = Rc<dyn Object>;
+
+/// 数字类型之间的转换，进制只能是 2 到 36
+pub fn number_builtins() -> Vec<(&'static str, Rc<dyn Object>)> {
+    vec![
+        (
+            // 浮点数和分数向 0 取整，字符串按进制解析，不传进制时认 0x、0b、0o 前缀
+            // int("ff", 16) -> 255
+            "int",
+            Rc::new(BuiltinObject::typed(
+                "int",
+                |(x, radix): (Value, Option<i64>)| {
+                    let value = to_integer("int", &x, radix)?;
+                    value.to_i64().ok_or_else(|| {
+                        ErrorObject::new(
+                            ErrorKind::OverflowError,
+                            format!("integer overflow: {} does not fit in 64 bits", value),
+                        )
+                    })
+                },
+            )),
+        ),
+        (
+            // 和 int 一样，结果是 BigInteger
+            // big(2) ^^ 100 -> 1267650600228229401496703205376
+            "big",
+            Rc::new(BuiltinObject::typed(
+                "big",
+                |(x, radix): (Value, Option<i64>)| to_integer("big", &x, radix),
+            )),
+        ),
+        (
+            // rational(6, 4) -> 3/2，rational(0.5) -> 1/2
+            "rational",
+            Rc::new(BuiltinObject::typed(
+                "rational",
+                |(n, d): (Value, Option<Value>)| {
+                    let numer = to_exact("rational", 0, &n)?;
+                    let denom = match &d {
+                        Some(d) => to_exact("rational", 1, d)?,
+                        None => BigRational::from_integer(1.into()),
+                    };
+                    if denom.is_zero() {
+                        return Err(ErrorObject::new(
+                            ErrorKind::ZeroDivisionError,
+                            format!(
+                                "division by zero: {} / {}",
+                                n.inspect(),
+                                d.unwrap().inspect()
+                            ),
+                        ));
+                    }
+                    Ok(numer / denom)
+                },
+            )),
+        ),
+        (
+            // 不传进制时和 inspect 的结果一样，传了进制只能转整数
+            // to_string(255, 16) -> "ff"
+            "to_string",
+            Rc::new(BuiltinObject::typed(
+                "to_string",
+                |(x, radix): (Value, Option<i64>)| match radix {
+                    None => Ok(x.inspect()),
+                    Some(radix) => {
+                        let radix = check_radix("to_string", radix)?;
+                        match to_big_int(&x) {
+                            Some(value) => Ok(value.to_str_radix(radix)),
+                            None => Err(ErrorObject::new(
+                                ErrorKind::TypeError,
+                                format!(
+                                    "argument[0] to `to_string` must be INTEGER or BIG_INTEGER when a radix is given, got {}",
+                                    x.object_type()
+                                ),
+                            )),
+                        }
+                    }
+                },
+            )),
+        ),
+    ]
+}
+
+fn check_radix(name: &str, radix: i64) -> Result<u32, ErrorObject> {
+    match u32::try_from(radix) {
+        Ok(radix @ 2..=36) => Ok(radix),
+        _ => Err(ErrorObject::new(
+            ErrorKind::ValueError,
+            format!(
+                "argument[1] to `{}` must be between 2 and 36, got {}",
+                name, radix
+            ),
+        )),
+    }
+}
+
+/// int 和 big 共用的转换，结果是不限大小的整数
+fn to_integer(name: &str, x: &Rc<dyn Object>, radix: Option<i64>) -> Result<BigInt, ErrorObject> {
+    if let Some(s) = x.as_any().downcast_ref::<StringObject>() {
+        return parse_integer(name, &s.value, radix);
+    }
+    if radix.is_some() {
+        return Err(ErrorObject::new(
+            ErrorKind::TypeError,
+            format!(
+                "argument[0] to `{}` must be STRING when a radix is given, got {}",
+                name,
+                x.object_type()
+            ),
+        ));
+    }
+    if let Some(value) = to_big_int(x) {
+        return Ok(value);
+    }
+    if let Some(r) = x.as_any().downcast_ref::<Rational>() {
+        return Ok(r.value.to_integer());
+    }
+    if let Some(f) = x.as_any().downcast_ref::<Float>() {
+        return BigInt::from_f64(f.value.trunc()).ok_or_else(|| {
+            ErrorObject::new(
+                ErrorKind::ValueError,
+                format!("cannot convert {} to an integer", f.value),
+            )
+        });
+    }
+    Err(ErrorObject::new(
+        ErrorKind::TypeError,
+        format!(
+            "argument[0] to `{}` must be a number or STRING, got {}",
+            name,
+            x.object_type()
+        ),
+    ))
+}
+
+fn parse_integer(name: &str, s: &str, radix: Option<i64>) -> Result<BigInt, ErrorObject> {
+    let invalid = || {
+        ErrorObject::new(
+            ErrorKind::ValueError,
+            format!("invalid integer for `{}`: {:?}", name, s),
+        )
+    };
+    let text = s.trim();
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (radix, digits) = match radix {
+        Some(radix) => (check_radix(name, radix)?, text),
+        None => split_radix(text),
+    };
+    // from_str_radix 自己也认符号，这里已经处理过了，不能再出现
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return Err(invalid());
+    }
+    let value = BigInt::from_str_radix(digits, radix).map_err(|_| invalid())?;
+    Ok(if negative { -value } else { value })
+}
+
+/// rational 的参数：整数、分数或者有限的浮点数
+fn to_exact(name: &str, index: usize, x: &Rc<dyn Object>) -> Result<BigRational, ErrorObject> {
+    if let Some(value) = to_rational(x) {
+        return Ok(value);
+    }
+    if let Some(f) = x.as_any().downcast_ref::<Float>() {
+        return BigRational::from_float(f.value).ok_or_else(|| {
+            ErrorObject::new(
+                ErrorKind::ValueError,
+                format!("cannot convert {} to a rational", f.value),
+            )
+        });
+    }
+    Err(ErrorObject::new(
+        ErrorKind::TypeError,
+        format!(
+            "argument[{}] to `{}` must be a number, got {}",
+            index,
+            name,
+            x.object_type()
+        ),
+    ))
+}
@@ -0,0 +1,180 @@
+use crate::evaluator::*;
+
+// repeat 生成的字符串最多这么多字节，防止一次分配就把内存耗光
+const MAX_STRING_BYTES: usize = 1 << 28;
+
+/// 字符串相关的内置函数，下标和长度都按字符计算
+pub fn string_builtins() -> Vec<(&'static str, Rc<dyn Object>)> {
+    vec![
+        (
+            // 不传分隔符时按空白切分
+            // split("a,b", ",") -> ["a", "b"]
+            "split",
+            Rc::new(BuiltinObject::typed(
+                "split",
+                |(s, sep): (String, Option<String>)| match sep {
+                    None => s.split_whitespace().map(String::from).collect::<Vec<_>>(),
+                    Some(sep) if sep.is_empty() => s.chars().map(String::from).collect(),
+                    Some(sep) => s.split(sep.as_str()).map(String::from).collect(),
+                },
+            )),
+        ),
+        (
+            // join(["a", "b"], ",") -> "a,b"
+            "join",
+            Rc::new(BuiltinObject::typed(
+                "join",
+                |(parts, sep): (Vec<String>, Option<String>)| parts.join(&sep.unwrap_or_default()),
+            )),
+        ),
+        (
+            "trim",
+            Rc::new(BuiltinObject::typed("trim", |(s,): (String,)| {
+                s.trim().to_string()
+            })),
+        ),
+        (
+            "upper",
+            Rc::new(BuiltinObject::typed("upper", |(s,): (String,)| {
+                s.to_uppercase()
+            })),
+        ),
+        (
+            "lower",
+            Rc::new(BuiltinObject::typed("lower", |(s,): (String,)| {
+                s.to_lowercase()
+            })),
+        ),
+        (
+            // 替换所有出现的地方
+            "replace",
+            Rc::new(BuiltinObject::typed(
+                "replace",
+                |(s, from, to): (String, String, String)| s.replace(&from, &to),
+            )),
+        ),
+        (
+            "starts_with",
+            Rc::new(BuiltinObject::typed(
+                "starts_with",
+                |(s, prefix): (String, String)| s.starts_with(&prefix),
+            )),
+        ),
+        (
+            "ends_with",
+            Rc::new(BuiltinObject::typed(
+                "ends_with",
+                |(s, suffix): (String, String)| s.ends_with(&suffix),
+            )),
+        ),
+        (
+            // 从 start 开始取 len 个字符，不传 len 时取到结尾，start 可以是负数
+            // substr("héllo", 1, 2) -> "él"
+            "substr",
+            Rc::new(BuiltinObject::typed(
+                "substr",
+                |(s, start, len): (String, i64, Option<i64>)| {
+                    if len.is_some_and(|l| l < 0) {
+                        return Err(ErrorObject::new(
+                            ErrorKind::ValueError,
+                            "argument[2] to `substr` must not be negative",
+                        ));
+                    }
+                    let chars = s.chars().collect::<Vec<_>>();
+                    let (start, _) = slice_range(chars.len(), start, None);
+                    let end = len.map_or(chars.len(), |l| {
+                        (start as i64).saturating_add(l).min(chars.len() as i64) as usize
+                    });
+                    Ok(chars[start..end].iter().collect::<String>())
+                },
+            )),
+        ),
+        (
+            "chars",
+            Rc::new(BuiltinObject::typed("chars", |(s,): (String,)| {
+                s.chars().map(String::from).collect::<Vec<_>>()
+            })),
+        ),
+        (
+            "repeat",
+            Rc::new(BuiltinObject::typed(
+                "repeat",
+                |(s, n): (String, i64)| match usize::try_from(n) {
+                    Ok(n) => match s.len().checked_mul(n) {
+                        Some(len) if len <= MAX_STRING_BYTES => Ok(s.repeat(n)),
+                        _ => Err(ErrorObject::new(
+                            ErrorKind::ValueError,
+                            "result of `repeat` is too large",
+                        )),
+                    },
+                    Err(_) => Err(ErrorObject::new(
+                        ErrorKind::ValueError,
+                        "argument[1] to `repeat` must not be negative",
+                    )),
+                },
+            )),
+        ),
+        (
+            // 按顺序替换 {}，{{ 和 }} 表示花括号本身
+            // format("{} + {} = {}", 1, 2, 3) -> "1 + 2 = 3"
+            "format",
+            Rc::new(BuiltinObject {
+                func: Rc::new(|args: Vec<Rc<dyn Object>>| {
+                    let result = match args.split_first() {
+                        None => Err(ErrorObject::new(
+                            ErrorKind::ArgumentError,
+                            "wrong number of arguments. got=0, want=at least 1",
+                        )),
+                        Some((fmt, values)) => match String::from_object(fmt) {
+                            Ok(fmt) => format_string(&fmt, values).map_err(|message| {
+                                ErrorObject::new(ErrorKind::ValueError, message)
+                            }),
+                            Err(e) => Err(ErrorObject::new(
+                                ErrorKind::TypeError,
+                                format!(
+                                    "argument[0] to `format` must be {}, got {}",
+                                    e.expected, e.found
+                                ),
+                            )),
+                        },
+                    };
+                    Some(result.into_object())
+                }),
+            }),
+        ),
+    ]
+}
+
+fn format_string(fmt: &str, values: &[Rc<dyn Object>]) -> Result<String, String> {
+    let mut out = String::with_capacity(fmt.len());
+    let mut placeholders = 0;
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                out.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                // 和模板字符串一样，字符串按原样，其他值用 inspect 的结果
+                if let Some(v) = values.get(placeholders) {
+                    out.push_str(&v.inspect());
+                }
+                placeholders += 1;
+            }
+            ('{', _) | ('}', _) => {
+                return Err(format!("invalid format string: unmatched `{}`", c));
+            }
+            _ => out.push(c),
+        }
+    }
+    if placeholders != values.len() {
+        return Err(format!(
+            "format string has {} placeholders, got {} arguments",
+            placeholders,
+            values.len()
+        ));
+    }
+    Ok(out)
+}
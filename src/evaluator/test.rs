@@ -1,11 +1,12 @@
 #[cfg(test)]
 mod test {
     use crate::*;
+    use std::cell::RefCell;
     use std::collections::HashMap;
 
     #[allow(dead_code)]
     enum FinalResult {
-        STRING(String),
+        String(String),
         Int(i64),
         Bool(bool),
         Vec(Vec<i64>),
@@ -38,6 +39,7 @@ mod test {
         };
     }
 
+    #[allow(unused_macros)]
     macro_rules! my_hash {
         () => {};
     }
@@ -60,11 +62,481 @@ mod test {
             ("3 * 3 * 3 + 10", f!(Int, 37)),
             ("3 * (3 * 3) + 10", f!(Int, 37)),
             ("(5 + 10 * 2 + 15 / 3) * 2 + -10", f!(Int, 50)),
+            ("7 % 3", f!(Int, 1)),
+            ("-7 % 3", f!(Int, -1)),
+            ("1 + 10 % 4 * 2", f!(Int, 5)),
+            ("+5", f!(Int, 5)),
+            ("-+5", f!(Int, -5)),
+            ("7 % 0", f!(Err, "division by zero: 7 % 0")),
+            ("+true", f!(Err, "unknown operator: +BOOLEAN")),
+            ("-7 / 2", f!(Int, -3)),
+            ("5 / 0", f!(Err, "division by zero: 5 / 0")),
+            ("[1, 2 / 0, 3]", f!(Err, "division by zero: 2 / 0")),
+            ("len(1 % 0)", f!(Err, "division by zero: 1 % 0")),
+            (
+                "9223372036854775807 + 1",
+                f!(Err, "integer overflow: 9223372036854775807 + 1"),
+            ),
+            (
+                "-9223372036854775807 - 2",
+                f!(Err, "integer overflow: -9223372036854775807 - 2"),
+            ),
+            (
+                "4611686018427387904 * 2",
+                f!(Err, "integer overflow: 4611686018427387904 * 2"),
+            ),
+            (
+                "let m = -9223372036854775807 - 1; m / -1",
+                f!(Err, "integer overflow: -9223372036854775808 / -1"),
+            ),
+            (
+                "let m = -9223372036854775807 - 1; -m",
+                f!(Err, "integer overflow: -(-9223372036854775808)"),
+            ),
+            (
+                "let x = 9223372036854775807; x += 1",
+                f!(Err, "integer overflow: 9223372036854775807 + 1"),
+            ),
         ];
 
         tests.iter().for_each(|(input, expected)| {
             handle_test(input, expected);
         });
+
+        let kinds = [
+            ("1 / 0", "ZeroDivisionError"),
+            ("1 % 0", "ZeroDivisionError"),
+            ("9223372036854775807 * 2", "OverflowError"),
+            ("2 ^^ -1", "ValueError"),
+        ];
+        kinds.iter().for_each(|(input, kind)| {
+            let input = format!(
+                "let k = null; try {{ {} }} catch (e) {{ k = e[\"kind\"]; }} k",
+                input
+            );
+            assert_eq!(test_eval(&input).unwrap().inspect(), *kind);
+        });
+    }
+
+    #[test]
+    fn test_integer_overflow_promotion() {
+        let cases = [
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("2 ^^ 64", "18446744073709551616"),
+            (
+                "let m = -9223372036854775807 - 1; -m",
+                "9223372036854775808",
+            ),
+            (
+                "let m = -9223372036854775807 - 1; m / -1",
+                "9223372036854775808",
+            ),
+            ("2 ^^ 64 - 2 ^^ 64", "0"),
+            ("2 ^^ 64 % 10", "6"),
+            ("~(2 ^^ 64)", "-18446744073709551617"),
+            ("(2 ^^ 64 - 1) & 0xff", "255"),
+            ("(-1) ^^ (2 ^^ 70)", "1"),
+            ("2 ^^ 64 + 0.5", "1.8446744073709552e19"),
+            ("let x = 2 ^^ 62; x *= 4", "18446744073709551616"),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let r = with_integer_overflow(IntegerOverflow::Promote, || test_eval(input));
+            assert_eq!(r.unwrap().inspect(), *expected, "{}", input);
+        });
+
+        let r = with_integer_overflow(IntegerOverflow::Promote, || test_eval("2 ^^ 64"));
+        assert_eq!(r.unwrap().object_type(), BIG_INTEGER_OBJECT);
+        // 没有溢出的结果还是 Integer
+        let r = with_integer_overflow(IntegerOverflow::Promote, || test_eval("2 ^^ 62"));
+        assert_eq!(r.unwrap().object_type(), INTEGER_OBJECT);
+
+        let bools = [
+            ("2 ^^ 64 > 9223372036854775807", true),
+            ("2 ^^ 64 == 2 ^^ 64", true),
+            ("2 ^^ 64 != 1", true),
+            ("-(2 ^^ 64) >= 0", false),
+        ];
+        bools.iter().for_each(|(input, expected)| {
+            let r = with_integer_overflow(IntegerOverflow::Promote, || test_eval(input));
+            test_boolean_object(r, *expected);
+        });
+
+        let errors = [
+            ("2 ^^ 64 / 0", "division by zero: 18446744073709551616 / 0"),
+            (
+                "(2 ^^ 64) ^^ -1",
+                "negative exponent: 18446744073709551616 ^^ -1",
+            ),
+            (
+                "2 ^^ 10000000",
+                "integer overflow: result exceeds 1048576 bits",
+            ),
+            ("2 ^^ 64 + true", "type mismatch: BIG_INTEGER + BOOLEAN"),
+        ];
+        errors.iter().for_each(|(input, expected)| {
+            let r = with_integer_overflow(IntegerOverflow::Promote, || test_eval(input));
+            test_error_object(r, expected.to_string());
+        });
+    }
+
+    #[test]
+    fn test_big_integer_literal() {
+        let cases = [
+            ("123n", "123", BIG_INTEGER_OBJECT),
+            (
+                "0xffffffffffffffffn + 1",
+                "18446744073709551616",
+                BIG_INTEGER_OBJECT,
+            ),
+            (
+                "99999999999999999999n * 10",
+                "999999999999999999990",
+                BIG_INTEGER_OBJECT,
+            ),
+            ("-5n / 2", "-2", BIG_INTEGER_OBJECT),
+            (
+                "2n ^^ 100",
+                "1267650600228229401496703205376",
+                BIG_INTEGER_OBJECT,
+            ),
+            ("1n + 0.5", "1.5", FLOAT_OBJECT),
+            ("7n > 6", "true", BOOLEAN_OBJECT),
+            ("7 == 7n", "true", BOOLEAN_OBJECT),
+            // 不开启溢出提升也不会溢出
+            (
+                "9223372036854775807n + 1",
+                "9223372036854775808",
+                BIG_INTEGER_OBJECT,
+            ),
+        ];
+        cases.iter().for_each(|(input, expected, object_type)| {
+            let r = test_eval(input).unwrap();
+            assert_eq!(r.inspect(), *expected, "{}", input);
+            assert_eq!(r.object_type(), *object_type, "{}", input);
+        });
+    }
+
+    #[test]
+    fn test_rational() {
+        let cases = [
+            ("rational(6, 4)", "3/2"),
+            ("rational(6, -4)", "-3/2"),
+            ("rational(4, 2)", "2"),
+            ("rational(0.5)", "1/2"),
+            ("rational(1, 3) + rational(1, 6)", "1/2"),
+            ("rational(1, 3) * 3", "1"),
+            ("rational(1, 3) - 1", "-2/3"),
+            ("1 / rational(2, 3)", "3/2"),
+            ("rational(2, 3) ^^ 2", "4/9"),
+            ("rational(2, 3) ^^ -2", "9/4"),
+            ("-rational(1, 2)", "-1/2"),
+            (
+                "rational(10n ^^ 30, 3)",
+                "1000000000000000000000000000000/3",
+            ),
+            ("rational(1, 2) + 0.25", "0.75"),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let r = test_eval(input).unwrap();
+            assert_eq!(r.inspect(), *expected, "{}", input);
+        });
+
+        let bools = [
+            ("rational(1, 3) < rational(1, 2)", true),
+            ("rational(4, 2) == 2", true),
+            ("rational(1, 2) >= 1", false),
+            ("rational(1, 2) != rational(2, 4)", false),
+        ];
+        bools.iter().for_each(|(input, expected)| {
+            test_boolean_object(test_eval(input), *expected);
+        });
+
+        let errors = [
+            ("rational(1, 0)", "division by zero: 1 / 0"),
+            ("rational(1, 2) / 0", "division by zero: 1/2 / 0"),
+            ("rational(0) ^^ -1", "division by zero: 0 ^^ -1"),
+            (
+                "2 ^^ rational(1, 2)",
+                "exponent must be an integer, got 1/2",
+            ),
+            ("rational(1, 2) % 2", "unknown operator: RATIONAL % INTEGER"),
+            (
+                "rational(true)",
+                "argument[0] to `rational` must be a number, got BOOLEAN",
+            ),
+        ];
+        errors.iter().for_each(|(input, expected)| {
+            test_error_object(test_eval(input), expected.to_string());
+        });
+    }
+
+    #[test]
+    fn test_integer_division_exact() {
+        let cases = [
+            ("7 / 2", "7/2", RATIONAL_OBJECT),
+            ("-6 / 4", "-3/2", RATIONAL_OBJECT),
+            // 除得尽时还是整数
+            ("6 / 3", "2", INTEGER_OBJECT),
+            ("10n / 4", "5/2", RATIONAL_OBJECT),
+            ("10n / 5", "2", BIG_INTEGER_OBJECT),
+            ("1 / 3 + 2 / 3", "1", RATIONAL_OBJECT),
+            ("7 % 2", "1", INTEGER_OBJECT),
+            ("7.0 / 2", "3.5", FLOAT_OBJECT),
+        ];
+        cases.iter().for_each(|(input, expected, object_type)| {
+            let r = with_integer_division(IntegerDivision::Exact, || test_eval(input)).unwrap();
+            assert_eq!(r.inspect(), *expected, "{}", input);
+            assert_eq!(r.object_type(), *object_type, "{}", input);
+        });
+
+        let r = with_integer_division(IntegerDivision::Exact, || test_eval("1 / 0"));
+        test_error_object(r, "division by zero: 1 / 0".to_string());
+        // 默认还是向 0 取整
+        assert_eq!(test_eval("7 / 2").unwrap().inspect(), "3");
+    }
+
+    #[test]
+    fn test_number_builtins() {
+        let cases = [
+            ("int(3.9)", "3", INTEGER_OBJECT),
+            ("int(-3.9)", "-3", INTEGER_OBJECT),
+            ("int(\"42\")", "42", INTEGER_OBJECT),
+            ("int(\" -0x1f \")", "-31", INTEGER_OBJECT),
+            ("int(\"ff\", 16)", "255", INTEGER_OBJECT),
+            ("int(\"-101\", 2)", "-5", INTEGER_OBJECT),
+            ("int(rational(7, 2))", "3", INTEGER_OBJECT),
+            ("int(100n)", "100", INTEGER_OBJECT),
+            ("big(1)", "1", BIG_INTEGER_OBJECT),
+            ("big(1e20)", "100000000000000000000", BIG_INTEGER_OBJECT),
+            (
+                "big(\"123456789012345678901234567890\")",
+                "123456789012345678901234567890",
+                BIG_INTEGER_OBJECT,
+            ),
+            ("big(\"zz\", 36)", "1295", BIG_INTEGER_OBJECT),
+            ("to_string(255, 16)", "ff", STRING_OBJECT),
+            ("to_string(-255, 2)", "-11111111", STRING_OBJECT),
+            (
+                "to_string(2n ^^ 64, 16)",
+                "10000000000000000",
+                STRING_OBJECT,
+            ),
+            ("to_string(rational(1, 3))", "1/3", STRING_OBJECT),
+            ("to_string(1.5)", "1.5", STRING_OBJECT),
+            ("to_string(true)", "true", STRING_OBJECT),
+        ];
+        cases.iter().for_each(|(input, expected, object_type)| {
+            let r = test_eval(input).unwrap();
+            assert_eq!(r.inspect(), *expected, "{}", input);
+            assert_eq!(r.object_type(), *object_type, "{}", input);
+        });
+
+        let errors = [
+            (
+                "int(2n ^^ 64)",
+                "integer overflow: 18446744073709551616 does not fit in 64 bits",
+            ),
+            ("int(\"12a\")", "invalid integer for `int`: \"12a\""),
+            ("int(\"--1\")", "invalid integer for `int`: \"--1\""),
+            ("int(\"\")", "invalid integer for `int`: \"\""),
+            (
+                "int(\"1\", 37)",
+                "argument[1] to `int` must be between 2 and 36, got 37",
+            ),
+            (
+                "int(1.5, 10)",
+                "argument[0] to `int` must be STRING when a radix is given, got FLOAT",
+            ),
+            ("big(0.0 / 0.0)", "cannot convert NaN to an integer"),
+            (
+                "int([])",
+                "argument[0] to `int` must be a number or STRING, got ARRAY_OBJECT",
+            ),
+            (
+                "to_string(1.5, 2)",
+                "argument[0] to `to_string` must be INTEGER or BIG_INTEGER when a radix is given, got FLOAT",
+            ),
+            (
+                "to_string(1, 1)",
+                "argument[1] to `to_string` must be between 2 and 36, got 1",
+            ),
+        ];
+        errors.iter().for_each(|(input, expected)| {
+            test_error_object(test_eval(input), expected.to_string());
+        });
+    }
+
+    #[test]
+    fn test_bitwise_and_pow_expression() {
+        let tests = [
+            ("6 & 3", f!(Int, 2)),
+            ("6 | 3", f!(Int, 7)),
+            ("6 ^ 3", f!(Int, 5)),
+            ("-1 & 0xff", f!(Int, 255)),
+            ("~0", f!(Int, -1)),
+            ("~5 & 0xf", f!(Int, 10)),
+            ("~~7", f!(Int, 7)),
+            ("~1.5", f!(Err, "unknown operator: ~FLOAT")),
+            ("1 | 2 + 4", f!(Int, 7)),
+            ("2 ^^ 10", f!(Int, 1024)),
+            ("2 ^^ 3 ^^ 2", f!(Int, 512)),
+            ("2 * 3 ^^ 2", f!(Int, 18)),
+            ("-2 ^^ 2", f!(Int, -4)),
+            ("(-2) ^^ 3", f!(Int, -8)),
+            ("5 ^^ 0", f!(Int, 1)),
+            ("2 ^^ -1", f!(Err, "negative exponent: 2 ^^ -1")),
+            ("2 ^^ 63", f!(Err, "integer overflow: 2 ^^ 63")),
+            (
+                "true & false",
+                f!(Err, "unknown operator: BOOLEAN & BOOLEAN"),
+            ),
+        ];
+        tests.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+    }
+
+    #[test]
+    fn test_float_expression() {
+        let tests = [
+            ("1.5", "1.5"),
+            ("-2.5", "-2.5"),
+            ("1.5 + 1.5", "3.0"),
+            ("1 + 0.5", "1.5"),
+            ("0.5 * 4", "2.0"),
+            ("7 / 2.0", "3.5"),
+            ("7 / 2", "3"),
+            ("2.0 ^^ 0.5 * 2.0 ^^ 0.5", "2.0000000000000004"),
+            ("2e3 - 1", "1999.0"),
+            ("1. + 1", "2.0"),
+            ("1.0 / 0", "inf"),
+            ("1 == 1.0", "true"),
+            ("1.5 > 1", "true"),
+            ("2 < 1.5", "false"),
+            ("2 <= 2.0", "true"),
+            ("1.5 >= 2", "false"),
+            ("7.5 % 2", "1.5"),
+            ("+1.5", "1.5"),
+            ("0.1 + 0.2 != 0.3", "true"),
+            ("let avg = fn(a, b) { (a + b) / 2.0 }; avg(1, 2)", "1.5"),
+        ];
+        tests.iter().for_each(|(input, expected)| {
+            let evaluated = test_eval(input).unwrap();
+            assert_eq!(evaluated.inspect(), *expected, "{}", input);
+        });
+        handle_test("1.5 & 1", &f!(Err, "unknown operator: FLOAT & INTEGER"));
+        handle_test("1.5 + true", &f!(Err, "type mismatch: FLOAT + BOOLEAN"));
+    }
+
+    #[test]
+    fn test_logical_expression() {
+        let tests = [
+            ("true && true", f!(Bool, true)),
+            ("true && false", f!(Bool, false)),
+            ("false || true", f!(Bool, true)),
+            ("false || false", f!(Bool, false)),
+            ("1 && 0", f!(Bool, true)),
+            ("1 < 2 && 2 < 3", f!(Bool, true)),
+            ("true || false && false", f!(Bool, true)),
+            ("!true && false", f!(Bool, false)),
+            // 短路：右边不会被求值，所以不会报 identifier not found
+            ("false && foobar", f!(Bool, false)),
+            ("true || foobar", f!(Bool, true)),
+            (
+                "let a = []; false && push(a, 1); true || push(a, 1); len(a)",
+                f!(Int, 0),
+            ),
+            ("true && foobar", f!(Err, "identifier not found: foobar")),
+            ("foobar || true", f!(Err, "identifier not found: foobar")),
+        ];
+        tests.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+    }
+
+    #[test]
+    fn test_assign_expression() {
+        let tests = [
+            ("let a = 1; a = 2; a", f!(Int, 2)),
+            ("let a = 1; a = 5", f!(Int, 5)),
+            ("let a = 1; let b = 2; a = b = 3; a + b", f!(Int, 6)),
+            ("let a = 10; a += 2; a -= 4; a *= 3; a /= 6; a", f!(Int, 4)),
+            // 沿着 parent 找到外层的变量，而不是在函数里新建一个
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c(); c()",
+                f!(Int, 3),
+            ),
+            ("let n = 0; let f = fn() { n = n + 1; }; f(); f(); n", f!(Int, 2)),
+            ("let a = [1, 2, 3]; let b = a; a[1] = 10; b[1] *= 2; b", f!(Vec, vec![1, 20, 3])),
+            (r#"let s = "a"; s += "b"; s"#, f!(String, "ab")),
+            ("x = 1", f!(Err, "assignment to undeclared variable: x")),
+            ("x += 1", f!(Err, "assignment to undeclared variable: x")),
+            ("let f = fn() { y = 1 }; f()", f!(Err, "assignment to undeclared variable: y")),
+            // 是运行时的错误：可以被 catch，函数里赋值的变量也可以在函数定义之后才声明
+            (
+                r#"let r = ""; try { x = 1 } catch (e) { r = e["kind"]; } r"#,
+                f!(String, "NameError"),
+            ),
+            ("let f = fn() { y = 1 }; let y = 0; f(); y", f!(Int, 1)),
+            ("let a = 1; a += true", f!(Err, "type mismatch: INTEGER + BOOLEAN")),
+            ("let a = [1]; a[1] = 2", f!(Err, "index out of range: 1")),
+            ("let a = 1; a[0] = 2", f!(Err, "index assignment not supported: INTEGER[INTEGER]")),
+        ];
+        tests.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+    }
+
+    #[test]
+    fn test_loop_statements() {
+        let tests = [
+            (
+                "let i = 0; let s = 0; while (i < 5) { let i = i + 1; let s = s + i; } s",
+                f!(Int, 15),
+            ),
+            ("while (false) { 1 }", f!(Nil)),
+            (
+                "let s = 0; for (x in [1, 2, 3]) { let s = s * 10 + x; } s",
+                f!(Int, 123),
+            ),
+            (
+                r#"let s = "-"; for (c in "abc") { let s = c + s; } s"#,
+                f!(String, "cba-"),
+            ),
+            (
+                r#"let s = "-"; for (k in {"b": 1, "a": 2}) { let s = s + k; } s"#,
+                f!(String, "-ba"),
+            ),
+            // continue 跳过 2，break 在 4 的时候结束循环
+            (
+                "let s = 0; for (x in [1, 2, 3, 4, 5]) { if (x == 2) { continue; } if (x == 4) { break; } let s = s + x; } s",
+                f!(Int, 4),
+            ),
+            (
+                "let s = 0; for (x in [1, 2]) { for (y in [10, 20]) { if (y == 20) { break } let s = s + x * y; } } s",
+                f!(Int, 30),
+            ),
+            (
+                "let f = fn() { for (x in [1, 2, 3]) { if (x == 2) { return x; } } }; f()",
+                f!(Int, 2),
+            ),
+            ("for (x in [1]) { x + true }", f!(Err, "type mismatch: INTEGER + BOOLEAN")),
+            ("for (x in 1) { x }", f!(Err, "not iterable: INTEGER")),
+            ("break;", f!(Err, "break outside loop")),
+            ("fn() { continue }()", f!(Err, "continue outside loop")),
+            // 函数返回时才变成错误，函数自己的 catch 接不住，调用的地方可以
+            (
+                r#"let r = ""; try { fn() { break }() } catch (e) { r = e["message"]; } r"#,
+                f!(String, "break outside loop"),
+            ),
+            (
+                "let r = 0; let f = fn() { try { break } catch { r = 1 } finally { r += 10 } }; try { f() } catch { r += 100 } r",
+                f!(Int, 110),
+            ),
+        ];
+        tests.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
     }
 
     #[test]
@@ -76,6 +548,25 @@ mod test {
             ("1 > 2", f!(Bool, false)),
             ("1 < 1", f!(Bool, false)),
             ("1 > 1", f!(Bool, false)),
+            ("1 <= 1", f!(Bool, true)),
+            ("2 <= 1", f!(Bool, false)),
+            ("1 >= 1", f!(Bool, true)),
+            ("1 >= 2", f!(Bool, false)),
+            (r#""a" < "b""#, f!(Bool, true)),
+            (r#""abc" < "abd""#, f!(Bool, true)),
+            (r#""ab" < "abc""#, f!(Bool, true)),
+            (r#""b" > "abc""#, f!(Bool, true)),
+            (r#""B" < "a""#, f!(Bool, true)),
+            (r#""x" <= "x""#, f!(Bool, true)),
+            (r#""x" >= "y""#, f!(Bool, false)),
+            (
+                "true <= false",
+                f!(Err, "unknown operator: BOOLEAN <= BOOLEAN"),
+            ),
+            (
+                r#""1" < 2"#,
+                f!(Err, "type mismatch: STRING_OBJECT < INTEGER"),
+            ),
             ("1 == 1", f!(Bool, true)),
             ("1 != 1", f!(Bool, false)),
             ("1 == 2", f!(Bool, false)),
@@ -96,6 +587,7 @@ mod test {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_bang_operator() {
         let tests = vec![
@@ -122,6 +614,23 @@ mod test {
             ("if (1 > 2) { 10 }", f!(Nil)),
             ("if (1 > 2) { 10 } else { 20 }", f!(Int, 20)),
             ("if (1 < 2) { 10 } else { 20 }", f!(Int, 10)),
+            ("if (false) { 1 } else if (true) { 2 } else { 3 }", f!(Int, 2)),
+            ("if (false) { 1 } else if (false) { 2 } else { 3 }", f!(Int, 3)),
+            ("if (false) { 1 } else if (false) { 2 }", f!(Nil)),
+            (
+                "let x = 5; if (x < 0) { -1 } else if (x == 0) { 0 } else if (x < 10) { 1 } else { 2 }",
+                f!(Int, 1),
+            ),
+            // 前面的分支走到了，后面的条件不会求值
+            (
+                "let n = 0; if (true) { 1 } else if (n = 1) { 2 }; n",
+                f!(Int, 0),
+            ),
+            ("let x = if (1 > 2) { 1 } else if (2 > 1) { 5 }; x * 2", f!(Int, 10)),
+            (
+                "if (false) { 1 } else if (-true) { 2 }",
+                f!(Err, "unknown operator: -BOOLEAN"),
+            ),
         ];
 
         tests.iter().for_each(|(input, value)| {
@@ -129,6 +638,7 @@ mod test {
         })
     }
 
+    #[allow(clippy::unusual_byte_groupings, clippy::useless_vec)]
     #[test]
     fn test_hex_binary_string() {
         let tests = vec![
@@ -145,6 +655,7 @@ mod test {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_return_statements() {
         let tests = vec![
@@ -166,6 +677,7 @@ mod test {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_function_declaration() {
         let tests = vec![
@@ -207,6 +719,7 @@ mod test {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_function_eval() {
         let tests = vec![
@@ -222,6 +735,72 @@ mod test {
         });
     }
 
+    #[test]
+    fn test_function_inspect() {
+        let tests = [
+            ("fn(x, y) { x + y }", "fn(x, y) { (x + y) }"),
+            ("fn a() { return 1; }", "fn() { return 1; }"),
+            ("let f = fn(n) { fn() { n } }; f", "fn(n) { fn () { n } }"),
+            ("let f = fn(n) { fn() { n } }; f(1)", "fn() { n }"),
+        ];
+        tests.iter().for_each(|(input, expected)| {
+            assert_eq!(test_eval(input).unwrap().inspect(), *expected);
+        });
+    }
+
+    #[test]
+    fn test_call_semantics() {
+        let tests = [
+            (
+                "let f = fn(a, b) { a }; f(1)",
+                f!(Err, "wrong number of arguments to `f`: want=2, got=1"),
+            ),
+            (
+                "fn g() { 1 }; g(1)",
+                f!(Err, "wrong number of arguments to `g`: want=0, got=1"),
+            ),
+            (
+                "fn(a) { a }(1, 2)",
+                f!(
+                    Err,
+                    "wrong number of arguments to anonymous function: want=1, got=2"
+                ),
+            ),
+            // return 的值在调用处解开，调用方继续往下执行
+            ("let f = fn() { return 1; }; f() + 1", f!(Int, 2)),
+            ("let f = fn() { return 1; }; f(); 2", f!(Int, 2)),
+            (
+                "let f = fn(x) { if (x) { return 1; } 2 }; f(true) * 10 + f(false)",
+                f!(Int, 12),
+            ),
+            (
+                "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(500)",
+                f!(Int, 500),
+            ),
+            (
+                "let f = fn(n) { f(n + 1) }; f(0)",
+                f!(Err, "stack overflow"),
+            ),
+            (
+                "let f = fn(n) { 1 + f(n) }; f(0)",
+                f!(Err, "stack overflow"),
+            ),
+            // 内置函数回调脚本函数时也算一层调用
+            (
+                "let f = fn(x) { map([x], f) }; f(1)",
+                f!(Err, "stack overflow"),
+            ),
+            (
+                "let f = fn(n) { f(n + 1) }; try { f(0) } catch (e) { 1 }; 2",
+                f!(Int, 2),
+            ),
+        ];
+        tests.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+    }
+
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_error_object_eval() {
         let test_cases = vec![
@@ -251,6 +830,77 @@ mod test {
         });
     }
 
+    #[test]
+    fn test_error_object_span() {
+        let test_cases = [
+            ("5 + true;", 1, 3, "+"),
+            ("let a = 1;\nlet b = a - true;", 2, 11, "-"),
+            ("let f = fn() {\n  foobar\n};\nf();", 2, 3, "foobar"),
+            ("if (true) { -true }", 1, 13, "-"),
+        ];
+        test_cases.iter().for_each(|&(input, line, column, text)| {
+            let err = ErrorObject::try_from(test_eval(input).unwrap()).unwrap();
+            let span = err.span.unwrap();
+            assert_eq!((span.line, span.column), (line, column), "{}", input);
+            assert_eq!(&input[span.start..span.end], text);
+        });
+
+        let input = "let a = 1;\na + true;";
+        let err = ErrorObject::try_from(test_eval(input).unwrap()).unwrap();
+        assert_eq!(
+            err.render(input),
+            r#"error: type mismatch: INTEGER + BOOLEAN
+ --> 2:3
+  |
+2 | a + true;
+  |   ^
+"#
+        );
+    }
+
+    #[test]
+    fn test_eval_tracer() {
+        #[derive(Default)]
+        struct Recorder(RefCell<Vec<String>>);
+        impl Tracer for Recorder {
+            fn on_event(&self, event: &TraceEvent) {
+                let e = match event {
+                    TraceEvent::NodeEntered(n) => format!("enter {}", n),
+                    TraceEvent::NodeExited(n) => format!("exit {}", n),
+                    TraceEvent::ValueProduced(n, v) => format!("value {} = {}", n, v),
+                    _ => return,
+                };
+                self.0.borrow_mut().push(e);
+            }
+        }
+        let recorder = Rc::new(Recorder::default());
+        let context = Rc::new(Context::new());
+        context.set_tracer(recorder.clone());
+        let pr = Parser::new(Lexer::new("1 + 2")).parse_program().unwrap();
+        eval(&pr, context);
+        assert_eq!(
+            *recorder.0.borrow(),
+            [
+                "enter (1 + 2)",
+                "enter (1 + 2)",
+                "enter (1 + 2)",
+                "enter 1",
+                "value 1 = 1",
+                "exit 1",
+                "enter 2",
+                "value 2 = 2",
+                "exit 2",
+                "value (1 + 2) = 3",
+                "exit (1 + 2)",
+                "value (1 + 2) = 3",
+                "exit (1 + 2)",
+                "value (1 + 2) = 3",
+                "exit (1 + 2)",
+            ]
+        );
+    }
+
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_let_state() {
         let test_cases = vec![
@@ -300,6 +950,32 @@ mod test {
         assert!(x.downcast_ref::<Boolean>().unwrap().value);
     }
 
+    #[test]
+    fn test_template_literal() {
+        let cases = [
+            (r#"let name = "x"; "hi ${name}!""#, f!(String, "hi x!")),
+            (
+                r#""${1 + 2} ${[1, 2]} ${true} ${1.5}""#,
+                f!(String, "3 [12] true 1.5"),
+            ),
+            (r#""a${"b${1}"}""#, f!(String, "ab1")),
+            (r#""\${a}\t${"\"q\""}""#, f!(String, "${a}\t\"q\"")),
+            (r#""" + "a""#, f!(String, "a")),
+            (
+                "let a = 1; // one\n/* two */ \"${a /* three */ + 1}\"",
+                f!(String, "2"),
+            ),
+            (
+                r#""${1 + true}""#,
+                FinalResult::Err("type mismatch: INTEGER + BOOLEAN".into()),
+            ),
+        ];
+        cases.iter().for_each(|(case, out)| {
+            handle_test(case, out);
+        });
+    }
+
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_builtin_len_fn() {
         let cases = vec![
@@ -313,12 +989,244 @@ mod test {
                 FinalResult::Err("wrong number of arguments. got=2, want=1".into()),
             ),
             (r#"len([1])"#, FinalResult::Int(1)),
+            (r#"len("中文")"#, f!(Int, 2)),
+            (r#"byte_len("中文")"#, f!(Int, 6)),
+            (
+                r#"byte_len([1])"#,
+                FinalResult::Err(
+                    "argument to `byte_len` must be STRING_OBJECT, got ARRAY_OBJECT".into(),
+                ),
+            ),
+        ];
+        cases.iter().for_each(|(case, out)| {
+            handle_test(case, out);
+        });
+    }
+
+    #[test]
+    fn test_unicode_strings() {
+        let cases = [
+            ("let 名字 = \"héllo\"; 名字[1]", f!(String, "é")),
+            (r#""中文"[1]"#, f!(String, "文")),
+            (r#""ab"[2]"#, f!(Nil)),
+            (r#""ab"[-1]"#, f!(Nil)),
+            ("[][0]", f!(Nil)),
+            (r#"slice("héllo", 1, 3)"#, f!(String, "él")),
+            (r#"slice("héllo", 1)"#, f!(String, "éllo")),
+            (r#"slice("héllo", -2)"#, f!(String, "lo")),
+            (r#"slice("héllo", 3, 1)"#, f!(String, "")),
+            (r#"slice("héllo", 0, 100)"#, f!(String, "héllo")),
+            (
+                r#"slice("a")"#,
+                FinalResult::Err("wrong number of arguments. got=1, want=2 or 3".into()),
+            ),
+            (
+                r#"slice("a", 0, "1")"#,
+                FinalResult::Err(
+                    "argument[2] to `slice` must be INTEGER or NULL, got STRING_OBJECT".into(),
+                ),
+            ),
+        ];
+        cases.iter().for_each(|(case, out)| {
+            handle_test(case, out);
+        });
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        let cases = [
+            (r#"join(split("a,b,,c", ","), "-")"#, f!(String, "a-b--c")),
+            (r#"join(split("  a  b "), "|")"#, f!(String, "a|b")),
+            (r#"join(split("中文", ""))"#, f!(String, "中文")),
+            (r#"len(split("", ","))"#, f!(Int, 1)),
+            (r#"trim("  a b \n")"#, f!(String, "a b")),
+            (r#"upper("héllo")"#, f!(String, "HÉLLO")),
+            (r#"lower("ABC")"#, f!(String, "abc")),
+            (r#"replace("a-b-c", "-", "+")"#, f!(String, "a+b+c")),
+            (r#"contains("hello", "ell")"#, f!(Bool, true)),
+            (r#"starts_with("hello", "he")"#, f!(Bool, true)),
+            (r#"ends_with("hello", "he")"#, f!(Bool, false)),
+            (r#"index_of("中文abc", "b")"#, f!(Int, 3)),
+            (r#"index_of("abc", "x")"#, f!(Int, -1)),
+            (r#"substr("héllo", 1, 2)"#, f!(String, "él")),
+            (r#"substr("héllo", -3)"#, f!(String, "llo")),
+            (r#"substr("héllo", 4, 10)"#, f!(String, "o")),
+            (r#"len(chars("héllo"))"#, f!(Int, 5)),
+            (r#"chars("héllo")[1]"#, f!(String, "é")),
+            (r#"repeat("ab", 3)"#, f!(String, "ababab")),
+            (
+                r#"format("{} + {} = {}, {{ok}}", 1, 2.5, "x")"#,
+                f!(String, "1 + 2.5 = x, {ok}"),
+            ),
+            (
+                r#"repeat("ab", -1)"#,
+                FinalResult::Err("argument[1] to `repeat` must not be negative".into()),
+            ),
+            (
+                r#"repeat("ab", 9223372036854775807)"#,
+                FinalResult::Err("result of `repeat` is too large".into()),
+            ),
+            (
+                r#"repeat("", 9223372036854775807)"#,
+                f!(String, ""),
+            ),
+            (
+                r#"substr("ab", 0, -1)"#,
+                FinalResult::Err("argument[2] to `substr` must not be negative".into()),
+            ),
+            (
+                r#"trim(1)"#,
+                FinalResult::Err("argument to `trim` must be STRING_OBJECT, got INTEGER".into()),
+            ),
+            (
+                r#"replace("a", "b")"#,
+                FinalResult::Err("wrong number of arguments. got=2, want=3".into()),
+            ),
+            (
+                r#"join(["a", 1])"#,
+                FinalResult::Err(
+                    "argument[0] to `join` must be ARRAY_OBJECT of STRING_OBJECT, got ARRAY_OBJECT containing INTEGER".into(),
+                ),
+            ),
+            (
+                r#"format()"#,
+                FinalResult::Err("wrong number of arguments. got=0, want=at least 1".into()),
+            ),
+            (
+                r#"format(1)"#,
+                FinalResult::Err("argument[0] to `format` must be STRING_OBJECT, got INTEGER".into()),
+            ),
+            (
+                r#"format("{}, {}", 1)"#,
+                FinalResult::Err("format string has 2 placeholders, got 1 arguments".into()),
+            ),
+            (
+                r#"format("{", 1)"#,
+                FinalResult::Err("invalid format string: unmatched `{`".into()),
+            ),
+        ];
+        cases.iter().for_each(|(case, out)| {
+            handle_test(case, out);
+        });
+    }
+
+    #[test]
+    fn test_array_builtins() {
+        let cases = [
+            ("map([1, 2, 3], fn(x) { x * 2 })", f!(Vec, vec![2, 4, 6])),
+            ("map([], fn(x) { x })", f!(Vec, vec![])),
+            (
+                "let k = 10; map([1, 2], fn(x) { return x + k; })",
+                f!(Vec, vec![11, 12]),
+            ),
+            (
+                "map([1, 2], len)",
+                f!(Err, "argument to `len` not supported, got INTEGER"),
+            ),
+            ("filter(range(6), fn(x) { x > 2 })", f!(Vec, vec![3, 4, 5])),
+            ("reduce([1, 2, 3], fn(acc, x) { acc + x })", f!(Int, 6)),
+            ("reduce([], fn(acc, x) { acc + x }, 10)", f!(Int, 10)),
+            (
+                "reduce([], fn(acc, x) { acc + x })",
+                f!(Err, "reduce of empty array with no initial value"),
+            ),
+            (
+                "let s = 0; each([1, 2, 3], fn(x) { s += x; }); s",
+                f!(Int, 6),
+            ),
+            ("each([1], fn(x) { x })", f!(Nil)),
+            ("sort([3, 1, 2])", f!(Vec, vec![1, 2, 3])),
+            (
+                "sort([3, 1, 2], fn(a, b) { b - a })",
+                f!(Vec, vec![3, 2, 1]),
+            ),
+            (r#"join(sort(["b", "c", "a"]))"#, f!(String, "abc")),
+            // 比较函数前后矛盾时不会 panic，只是顺序不确定
+            ("sort([3, 1, 2], fn(a, b) { -1 })", f!(Vec, vec![3, 1, 2])),
+            ("len(sort(range(100), fn(a, b) { -1 }))", f!(Int, 100)),
+            ("len(sort(range(100), fn(a, b) { 1 }))", f!(Int, 100)),
+            (
+                "len(sort(range(100), fn(a, b) { (a * 7 + b * 13) % 3 - 1 }))",
+                f!(Int, 100),
+            ),
+            // 稳定排序，相等的元素保持原来的顺序
+            (
+                "map(sort([[1, 3], [0, 1], [1, 2], [0, 0]], fn(a, b) { a[0] - b[0] }), fn(p) { p[1] })",
+                f!(Vec, vec![1, 0, 3, 2]),
+            ),
+            ("let a = [2, 1]; sort(a); a", f!(Vec, vec![2, 1])),
+            (
+                "sort([[2], [1]])",
+                f!(Err, "cannot compare ARRAY_OBJECT and ARRAY_OBJECT"),
+            ),
+            (
+                "sort([1, 2], fn(a, b) { true })",
+                f!(
+                    Err,
+                    "comparator passed to `sort` must return INTEGER, got BOOLEAN"
+                ),
+            ),
+            (
+                "sort([1, 2], fn(a, b) { a + true })",
+                f!(Err, "type mismatch: INTEGER + BOOLEAN"),
+            ),
+            ("reverse([1, 2, 3])", f!(Vec, vec![3, 2, 1])),
+            (r#"reverse("héllo")"#, f!(String, "olléh")),
+            ("slice([1, 2, 3], -2)", f!(Vec, vec![2, 3])),
+            ("slice([1, 2, 3], 1, 2)", f!(Vec, vec![2])),
+            ("concat([1], [2, 3])", f!(Vec, vec![1, 2, 3])),
+            ("len(zip([1, 2, 3], [4, 5]))", f!(Int, 2)),
+            ("zip([1, 2], [3, 4])[1]", f!(Vec, vec![2, 4])),
+            ("range(3)", f!(Vec, vec![0, 1, 2])),
+            ("range(1, 7, 2)", f!(Vec, vec![1, 3, 5])),
+            ("range(3, 0, -1)", f!(Vec, vec![3, 2, 1])),
+            ("range(3, 0)", f!(Vec, vec![])),
+            ("range(0, 3, 5)", f!(Vec, vec![0])),
+            ("range(0, 10, 3)", f!(Vec, vec![0, 3, 6, 9])),
+            ("range(0, 9, 3)", f!(Vec, vec![0, 3, 6])),
+            (
+                "range(9223372036854775806, 9223372036854775807)",
+                f!(Vec, vec![9223372036854775806]),
+            ),
+            (
+                "range(9223372036854775807)",
+                f!(Err, "result of `range` is too large"),
+            ),
+            (
+                "range(-9223372036854775807 - 1, 9223372036854775807, 2)",
+                f!(Err, "result of `range` is too large"),
+            ),
+            (
+                "range(0, 3, 0)",
+                f!(Err, "argument[2] to `range` must not be zero"),
+            ),
+            ("contains([1, 2.0, \"a\"], 2)", f!(Bool, true)),
+            ("contains([[1]], [1])", f!(Bool, false)),
+            ("let a = [1]; contains([a], a)", f!(Bool, true)),
+            ("index_of([1, 2, 3], 3)", f!(Int, 2)),
+            ("index_of([1, 2, 3], true)", f!(Int, -1)),
+            (
+                r#"index_of("abc", 1)"#,
+                f!(
+                    Err,
+                    "argument[1] to `index_of` must be STRING_OBJECT, got INTEGER"
+                ),
+            ),
+            (
+                "contains(1, 1)",
+                f!(
+                    Err,
+                    "argument[0] to `contains` must be STRING_OBJECT or ARRAY_OBJECT, got INTEGER"
+                ),
+            ),
+            ("map([1], 1)", f!(Err, "calling non-function: INTEGER")),
         ];
         cases.iter().for_each(|(case, out)| {
             handle_test(case, out);
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_array_literal() {
         let cases = vec![
@@ -354,6 +1262,7 @@ mod test {
             handle_test(case, out);
         });
     }
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_first_builtin_fn() {
         let cases = vec![
@@ -366,18 +1275,24 @@ mod test {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_last_builtin_fn() {
         let cases = vec![
             ("last([1,2,3])", f!(Int, 3)),
             ("last([])", f!(Nil)),
             ("let a = [1,2,3]; last([1,2,3]); a", f!(Vec, vec![1, 2, 3])),
+            (
+                "last(1)",
+                FinalResult::Err("argument to `last` must be ARRAY_OBJECT, got INTEGER".into()),
+            ),
         ];
         cases.iter().for_each(|(case, out)| {
             handle_test(case, out);
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_rest_builtin_fn() {
         let cases = vec![
@@ -391,18 +1306,28 @@ mod test {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_push_builtin_fn() {
         let cases = vec![
             ("push([1,2,3], 4)", f!(Vec, vec![1, 2, 3, 4])),
             ("push([], 1)", f!(Vec, vec![1])),
             ("let a = []; push(a, 1); a", f!(Vec, vec![1])),
+            (
+                "push(1, 1)",
+                FinalResult::Err("argument[0] to `push` must be ARRAY_OBJECT, got INTEGER".into()),
+            ),
+            (
+                "push([])",
+                FinalResult::Err("wrong number of arguments. got=1, want=2".into()),
+            ),
         ];
         cases.iter().for_each(|(case, out)| {
             handle_test(case, out);
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_compose_builtin_array_fn() {
         let cases = vec![
@@ -473,6 +1398,7 @@ mod test {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_hash_eval() {
         let cases = vec![
@@ -486,14 +1412,201 @@ mod test {
         });
     }
 
-    #[allow(unused)]
+    #[test]
+    fn test_hash_index_expression() {
+        let cases = [
+            (r#"{"foo": 5}["foo"]"#, f!(Int, 5)),
+            (r#"{"foo": 5}["bar"]"#, f!(Nil)),
+            (r#"let key = "foo"; {"foo": 5}[key]"#, f!(Int, 5)),
+            (r#"{}["foo"]"#, f!(Nil)),
+            ("{5: 5}[5]", f!(Int, 5)),
+            ("{true: 5}[true]", f!(Int, 5)),
+            ("{false: 5}[false]", f!(Int, 5)),
+            (r#"{1: "int", "1": "string"}["1"]"#, f!(String, "string")),
+            (
+                r#"let two = "two"; {"one": 10 - 9, two: 1 + 1, 4 - 1: 3}[3]"#,
+                f!(Int, 3),
+            ),
+            ("{1: 1, 1: 2}[1]", f!(Int, 2)),
+            ("{7n: 1}[7]", f!(Int, 1)),
+            ("{7: 1}[7n]", f!(Int, 1)),
+            ("{7: 1, 7n: 2}[7]", f!(Int, 2)),
+            (
+                "{9223372036854775808n: 1}[9223372036854775807n + 1]",
+                f!(Int, 1),
+            ),
+            ("{9223372036854775808n: 1}[0]", f!(Nil)),
+            (r#"let h = {}; h[2] = 1; h[2] += 1; h[2]"#, f!(Int, 2)),
+            ("{[1]: 1}", f!(Err, "unusable as hash key: ARRAY_OBJECT")),
+            (
+                r#"{"a": 1}[fn(x) { x }]"#,
+                f!(Err, "unusable as hash key: FUNCTION_OBJECT"),
+            ),
+            ("{1: foo}", f!(Err, "identifier not found: foo")),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+    }
+
+    #[test]
+    fn test_hash_builtins() {
+        let cases = [
+            (
+                r#"join(keys({"b": 1, "a": 2, "c": 3}), ",")"#,
+                f!(String, "b,a,c"),
+            ),
+            (r#"values({"b": 1, "a": 2})"#, f!(Vec, vec![1, 2])),
+            (r#"entries({"a": 1, 2: 3})[1]"#, f!(Vec, vec![2, 3])),
+            (r#"len(entries({}))"#, f!(Int, 0)),
+            (r#"has({"a": 1}, "a")"#, f!(Bool, true)),
+            (r#"has({"a": 1}, 1)"#, f!(Bool, false)),
+            (r#"len({"a": 1, true: 2})"#, f!(Int, 2)),
+            (
+                r#"let h = {"a": 1, "b": 2}; "${delete(h, "a")} ${h}""#,
+                f!(String, "{ b:2 } { a:1,b:2 }"),
+            ),
+            (
+                r#""${merge({"a": 1, "b": 2}, {"c": 4, "a": 3})}""#,
+                f!(String, "{ a:3,b:2,c:4 }"),
+            ),
+            (
+                r#"let h = {}; h["z"] = 1; h["y"] = 2; h["z"] = 3; "${h}""#,
+                f!(String, "{ z:3,y:2 }"),
+            ),
+            (
+                r#"let s = ""; for (k in {"z": 1, "a": 2, "m": 3}) { s += k; } s"#,
+                f!(String, "zam"),
+            ),
+            (
+                "keys([1])",
+                f!(
+                    Err,
+                    "argument to `keys` must be HASH_OBJECT, got ARRAY_OBJECT"
+                ),
+            ),
+            (
+                r#"has({}, 1.5)"#,
+                f!(
+                    Err,
+                    "argument[1] to `has` must be INTEGER, BIG_INTEGER, BOOLEAN or STRING_OBJECT, got FLOAT"
+                ),
+            ),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+    }
+
+    #[test]
+    fn test_null_and_optional_access() {
+        let cases = [
+            ("null", f!(Nil)),
+            ("let a = 1;", f!(Nil)),
+            ("let f = fn() { let x = 1; }; f()", f!(Nil)),
+            ("null == null", f!(Bool, true)),
+            ("null != 0", f!(Bool, true)),
+            (r#"{"a": 1}["b"] == null"#, f!(Bool, true)),
+            ("!null", f!(Bool, true)),
+            ("null ?? 1", f!(Int, 1)),
+            ("0 ?? 1", f!(Int, 0)),
+            ("false ?? 1", f!(Bool, false)),
+            ("null ?? null ?? 2", f!(Int, 2)),
+            // 左边不是 null 时右边不会求值
+            ("1 ?? missing", f!(Int, 1)),
+            ("null ?? missing", f!(Err, "identifier not found: missing")),
+            (r#"let h = {"a": {"b": [1, 2]}}; h?.a?.b?[1]"#, f!(Int, 2)),
+            (r#"let h = {"a": 1}; h?.x?.y"#, f!(Nil)),
+            (r#"let h = null; h?.a ?? "none""#, f!(String, "none")),
+            // 左边是 null 时不会对下标求值
+            ("null?[missing]", f!(Nil)),
+            ("[1, 2]?[1]", f!(Int, 2)),
+            ("1?[0]", f!(Err, "index operator not supported: INTEGER")),
+            ("null[0]", f!(Err, "index operator not supported: NULL")),
+            ("1(2)", f!(Err, "calling non-function: INTEGER")),
+            ("-null", f!(Err, "unknown operator: -NULL")),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+
+        // 没有表达式的表达式语句也是 null
+        let stm = ExpressionStatement {
+            token: Token {
+                token_type: SEMICOLON,
+                literal: ";".into(),
+                span: Span::default(),
+                leading_trivia: vec![],
+            },
+            expression: None,
+        };
+        let evaluated = eval(&stm, Rc::new(Context::new()));
+        test_null_object(&evaluated);
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let cases = [
+            (r#"let r = ""; try { 1 + true; } catch (e) { r = e["kind"] + ": " + e["message"]; } r"#,
+                f!(String, "TypeError: type mismatch: INTEGER + BOOLEAN")),
+            (r#"let r = ""; try { missing; } catch (e) { r = e["kind"]; } r"#, f!(String, "NameError")),
+            (r#"let r = ""; try { len(); } catch (e) { r = e["kind"]; } r"#, f!(String, "ArgumentError")),
+            (r#"let r = ""; try { repeat("a", -1); } catch (e) { r = e["kind"]; } r"#, f!(String, "ValueError")),
+            // throw 字符串时 kind 是 Error，throw hash 时可以自己指定 kind
+            (r#"let r = ""; try { throw "boom"; } catch (e) { r = e["kind"] + ": " + e["message"]; } r"#,
+                f!(String, "Error: boom")),
+            (r#"let r = ""; try { throw {"kind": "Oops", "message": "m"}; } catch (e) { r = e["kind"]; } r"#,
+                f!(String, "Oops")),
+            ("throw 1 + 1", f!(Err, "2")),
+            ("let r = 0; try { r = 1; } catch (e) { r = 2; } r", f!(Int, 1)),
+            ("try { 1 } catch (e) { 2 }", f!(Nil)),
+            // finally 总会执行，catch 之后、没有 catch 的错误继续往外传之前都会执行
+            ("let r = []; try { throw 1; } catch (e) { r = concat(r, [1]); } finally { r = concat(r, [2]); } r", f!(Vec, vec![1, 2])),
+            ("let r = 0; try { throw 1; } finally { r = 1; }", f!(Err, "1")),
+            ("let r = 0; try { try { throw 1; } finally { r = 1; } } catch (e) { r = r + 10; } r", f!(Int, 11)),
+            ("let r = 0; let f = fn() { try { return 1; } finally { r = 2; } }; let v = f(); r", f!(Int, 2)),
+            ("let r = 0; while (true) { try { break; } finally { r = r + 1; } } r", f!(Int, 1)),
+            ("let r = 0; for (x in [1, 2]) { try { continue; } finally { r = r + x; } } r", f!(Int, 3)),
+            // catch 到的错误可以再抛出去，catch 里出错会被外层接住
+            (r#"let r = ""; try { try { missing; } catch (e) { throw e; } } catch (e) { r = e["kind"]; } r"#,
+                f!(String, "NameError")),
+            (r#"let r = ""; try { try { throw "a"; } catch (e) { throw "b"; } } catch (e) { r = e["message"]; } r"#,
+                f!(String, "b")),
+            (r#"try { throw "a"; } catch (e) { throw "b"; } finally { throw "c"; }"#, f!(Err, "c")),
+            // 错误穿过函数调用也能接住，高阶内置函数的回调里的错误也是
+            (r#"let f = fn() { throw "x" }; let r = 0; try { f(); } catch (e) { r = 1; } r"#, f!(Int, 1)),
+            (r#"let r = ""; try { map([1], fn(x) { x + "a" }); } catch (e) { r = e["kind"]; } r"#,
+                f!(String, "TypeError")),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            handle_test(input, expected);
+        });
+    }
+
+    #[test]
+    fn test_error_stack() {
+        let input =
+            "let inner = fn() {\n  throw \"bad\";\n};\nlet outer = fn() { inner() };\nouter();";
+        let err = ErrorObject::try_from(test_eval(input).unwrap()).unwrap();
+        assert_eq!(err.inspect(), "Error: bad");
+        let span = err.span.unwrap();
+        assert_eq!((span.line, span.column), (2, 3));
+        let stack = err.stack.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        assert_eq!(stack, ["at inner (4:25)", "at outer (5:6)"]);
+
+        // catch 到的 hash 里有位置和调用栈
+        let input = r#"let f = fn() { 1 + true }; let e = null; try { f(); } catch (err) { e = err; } "${e["line"]}:${e["column"]} ${e["stack"][0]}""#;
+        assert_eq!(test_eval(input).unwrap().inspect(), "1:18 at f (1:49)");
+    }
+
+    #[allow(unused, clippy::assertions_on_constants)]
     fn handle_test(case: &str, out: &FinalResult) {
         let input = case;
         let evaluated = test_eval(input);
         assert!(evaluated.is_some());
         dbg!(&evaluated);
         match out {
-            FinalResult::STRING(s) => {
+            FinalResult::String(s) => {
                 test_string_object(evaluated, s.to_string());
             }
             FinalResult::Int(i) => {
@@ -541,7 +1654,7 @@ mod test {
         assert!(x.downcast_ref::<Null>().is_some());
     }
 
-    #[allow(unused)]
+    #[allow(unused, clippy::needless_return)]
     fn test_parse(input: &str) -> Option<Program> {
         let l = Lexer::new(input);
         let p = Parser::new(l);
@@ -557,7 +1670,22 @@ mod test {
         assert!(pr.is_some());
         let pr = pr.unwrap();
         let context = Context::new();
-        return eval(&pr, Rc::new(context));
+        let evaluated = eval(&pr, Rc::new(context));
+        test_same_result_on_vm(&pr, &evaluated, input);
+        evaluated
+    }
+
+    /// 每个用例也在 VM 上执行一遍，结果的类型和 inspect 都要和 evaluator 一样
+    fn test_same_result_on_vm(pr: &Program, expected: &Option<Rc<dyn Object>>, input: &str) {
+        let mut c = Compiler::new();
+        assert!(c.compile(pr).is_ok(), "{}", input);
+        let mut vm = Vm::new(c.bytecode());
+        vm.set_integer_overflow(integer_overflow());
+        vm.set_integer_division(integer_division());
+        let got = vm.run().unwrap();
+        let expected = expected.as_ref().unwrap();
+        assert_eq!(got.object_type(), expected.object_type(), "{}", input);
+        assert_eq!(got.inspect(), expected.inspect(), "{}", input);
     }
 
     #[allow(unused)]
@@ -0,0 +1,198 @@
+use crate::evaluator::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// 给嵌入方用的解释器，持有一个全局 Context，多次 eval_str 之间共享变量
+///
+/// ```
+/// use my_rust_interpreter::*;
+/// use std::cell::Cell;
+///
+/// let calls = Rc::new(Cell::new(0));
+/// let interp = Interpreter::new();
+/// let counter = calls.clone();
+/// interp.register_fn("tick", move |_args| {
+///     counter.set(counter.get() + 1);
+///     Some(Rc::new(Integer { value: counter.get() }) as Rc<dyn Object>)
+/// });
+/// interp.set_global("base", Rc::new(Integer { value: 40 }));
+///
+/// let r = interp.eval_str("tick(); let answer = base + tick(); answer").unwrap();
+/// assert_eq!(r.unwrap().inspect(), "42");
+/// assert_eq!(calls.get(), 2);
+/// assert_eq!(interp.get_global("answer").unwrap().inspect(), "42");
+/// ```
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    context: Rc<Context>,
+    integer_overflow: Cell<IntegerOverflow>,
+    integer_division: Cell<IntegerDivision>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn context(&self) -> Rc<Context> {
+        self.context.clone()
+    }
+
+    /// 解析并执行一段脚本，返回最后一个语句的值
+    /// 出错时返回的是已经带上源码位置的错误信息，可以直接打印
+    pub fn eval_str(&self, source: &str) -> Result<Option<Rc<dyn Object>>, String> {
+        let p = Parser::new(Lexer::new(source));
+        let pr = p.parse_program();
+        if !p.errors().borrow().is_empty() {
+            return Err(p.diagnostics().concat());
+        }
+        let pr = match pr {
+            Some(pr) => pr,
+            None => return Err("error: failed to parse program\n".into()),
+        };
+        let result = with_integer_overflow(self.integer_overflow.get(), || {
+            with_integer_division(self.integer_division.get(), || {
+                eval(&pr, self.context.clone())
+            })
+        });
+        if let Some(err) = result
+            .as_ref()
+            .and_then(|r| r.as_any().downcast_ref::<ErrorObject>())
+        {
+            return Err(err.render(source));
+        }
+        Ok(result)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Rc<dyn Object>> {
+        self.context.get(&Rc::new(Identifier::from(name)))
+    }
+
+    pub fn set_global(&self, name: &str, value: Rc<dyn Object>) {
+        self.context.set(Rc::new(Identifier::from(name)), value);
+    }
+
+    /// 函数调用最多嵌套多少层，超过时报 stack overflow，默认是 DEFAULT_MAX_CALL_DEPTH
+    pub fn set_max_call_depth(&self, depth: usize) {
+        self.context.set_max_call_depth(depth);
+    }
+
+    /// 整数运算溢出时报错还是提升成 BigInteger，默认报错
+    pub fn set_integer_overflow(&self, mode: IntegerOverflow) {
+        self.integer_overflow.set(mode);
+    }
+
+    /// 整数除不尽时向 0 取整还是得到精确的 Rational，默认取整
+    pub fn set_integer_division(&self, mode: IntegerDivision) {
+        self.integer_division.set(mode);
+    }
+
+    /// 注册一个原生函数，同名时会遮住内置函数
+    pub fn register_fn<F>(&self, name: &str, func: F)
+    where
+        F: Fn(Vec<Rc<dyn Object>>) -> Option<Rc<dyn Object>> + 'static,
+    {
+        self.set_global(
+            name,
+            Rc::new(BuiltinObject {
+                func: Rc::new(func),
+            }),
+        );
+    }
+
+    /// 注册一个参数带类型的原生函数，参见 BuiltinObject::typed
+    pub fn register_typed_fn<A, R, F>(&self, name: &'static str, func: F)
+    where
+        A: FromArgs,
+        R: IntoObject,
+        F: Fn(A) -> R + 'static,
+    {
+        self.set_global(name, Rc::new(BuiltinObject::typed(name, func)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_interpreter() {
+        let interp = Interpreter::new();
+        assert_eq!(
+            interp.eval_str("let a = 1;").unwrap().unwrap().inspect(),
+            "null"
+        );
+        assert_eq!(interp.eval_str("a + 1").unwrap().unwrap().inspect(), "2");
+        assert!(interp.get_global("b").is_none());
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let sink = log.clone();
+        interp.register_fn("emit", move |args| {
+            sink.borrow_mut().extend(args.iter().map(|a| a.inspect()));
+            None
+        });
+        interp.eval_str("emit(a, \"x\"); emit(true)").unwrap();
+        assert_eq!(*log.borrow(), vec!["1", "x", "true"]);
+
+        // 注册的函数可以遮住内置函数
+        interp.register_fn("len", |_| Some(Rc::new(Integer { value: -1 })));
+        assert_eq!(
+            interp.eval_str("len([1])").unwrap().unwrap().inspect(),
+            "-1"
+        );
+
+        interp.register_typed_fn("add", |(a, b): (i64, i64)| a + b);
+        assert_eq!(
+            interp.eval_str("add(a, 2)").unwrap().unwrap().inspect(),
+            "3"
+        );
+        let r = interp.eval_str("add(a)");
+        assert!(r
+            .unwrap_err()
+            .starts_with("error: wrong number of arguments. got=1, want=2"));
+
+        let r = interp.eval_str("a + true");
+        assert!(r
+            .unwrap_err()
+            .starts_with("error: type mismatch: INTEGER + BOOLEAN"));
+        let r = interp.eval_str("let = 1");
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let interp = Interpreter::new();
+        assert!(interp
+            .eval_str("9223372036854775807 + 1")
+            .unwrap_err()
+            .starts_with("error: integer overflow: 9223372036854775807 + 1"));
+        interp.set_integer_overflow(IntegerOverflow::Promote);
+        let r = interp.eval_str("9223372036854775807 + 1").unwrap().unwrap();
+        assert_eq!(r.inspect(), "9223372036854775808");
+    }
+
+    #[test]
+    fn test_integer_division() {
+        let interp = Interpreter::new();
+        assert_eq!(interp.eval_str("7 / 2").unwrap().unwrap().inspect(), "3");
+        interp.set_integer_division(IntegerDivision::Exact);
+        let r = interp.eval_str("7 / 2").unwrap().unwrap();
+        assert_eq!(r.inspect(), "7/2");
+        assert_eq!(r.object_type(), RATIONAL_OBJECT);
+    }
+
+    #[test]
+    fn test_max_call_depth() {
+        let interp = Interpreter::new();
+        interp.set_max_call_depth(10);
+        interp
+            .eval_str("let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } }")
+            .unwrap();
+        assert_eq!(interp.eval_str("f(9)").unwrap().unwrap().inspect(), "0");
+        assert!(interp
+            .eval_str("f(10)")
+            .unwrap_err()
+            .starts_with("error: stack overflow"));
+    }
+}
@@ -12,6 +12,15 @@ pub struct Lexer {
     position: Cell<usize>,
     read_position: Cell<usize>,
     ch: Rc<RefCell<char>>,
+    // 每个字符在 input 中的字节偏移，最后多放一个 input.len()
+    byte_offsets: Vec<usize>,
+    line: Cell<usize>,
+    // 当前行第一个字符的位置（字符下标）
+    line_start: Cell<usize>,
+    // input 在整个源码中的起始位置，解析模板字符串里的 ${} 时用
+    origin: Span,
+    // 为 true 时空白和普通注释也放进 token 的 leading_trivia
+    lossless: Cell<bool>,
 }
 
 /// ```
@@ -22,18 +31,46 @@ pub struct Lexer {
 /// ```
 impl Lexer {
     pub fn new<T: Into<String> + Clone>(input: T) -> Self {
+        Self::with_origin(
+            input,
+            Span {
+                line: 1,
+                column: 1,
+                ..Span::default()
+            },
+        )
+    }
+    /// input 是更大的源码中的一段，origin 是它第一个字符的位置，产生的 span 都相对整个源码
+    pub fn with_origin<T: Into<String> + Clone>(input: T, origin: Span) -> Self {
+        let input: String = input.into();
+        let byte_offsets = input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(input.len()))
+            .collect();
         let l = Lexer {
-            input: input.clone().into(),
-            input_chars: input.into().chars().into_iter().collect(),
+            input_chars: input.chars().collect(),
+            input,
             position: Cell::new(0),
             read_position: Cell::new(0),
             ch: Rc::new(RefCell::new('0')),
+            byte_offsets,
+            line: Cell::new(1),
+            line_start: Cell::new(0),
+            origin,
+            lossless: Cell::new(false),
         };
         l.read_char();
         l
     }
+    /// 打开之后每个 token 都带着前面所有的空白和注释，格式化工具可以据此还原源码
+    pub fn set_lossless(&self, lossless: bool) {
+        self.lossless.set(lossless);
+    }
     pub fn next_token(&self) -> Token {
-        self.skip_white_space();
+        let leading_trivia = self.read_trivia();
+        let start = self.position.get().min(self.input_chars.len());
+        let (line, column) = (self.line.get(), start - self.line_start.get() + 1);
         let mut should_read_one_more = true;
         let t = *self.ch.borrow();
         let mut token_type = match t {
@@ -51,8 +88,22 @@ impl Lexer {
             '{' => token::LBRACE,
             '}' => token::RBRACE,
             ',' => token::COMMA,
-            '+' => token::PLUS,
-            '-' => token::MINUS,
+            '+' => {
+                if self.peek_char() == "=" {
+                    self.read_char();
+                    token::PLUS_ASSIGN
+                } else {
+                    token::PLUS
+                }
+            }
+            '-' => {
+                if self.peek_char() == "=" {
+                    self.read_char();
+                    token::MINUS_ASSIGN
+                } else {
+                    token::MINUS
+                }
+            }
             '!' => {
                 if self.peek_char() == "=" {
                     self.read_char();
@@ -85,14 +136,59 @@ impl Lexer {
                     token::BITAND
                 }
             }
-            '/' => token::SLASH,
-            '*' => token::ASTERISK,
-            '<' => token::LT,
-            '>' => token::GT,
-            '"' => {
-                self.read_char();
-                token::STRING
+            '?' => match self.peek_char().as_str() {
+                "?" => {
+                    self.read_char();
+                    token::NULLISH
+                }
+                "." => {
+                    self.read_char();
+                    token::OPTIONAL_DOT
+                }
+                "[" => {
+                    self.read_char();
+                    token::OPTIONAL_LBRACKET
+                }
+                _ => token::ILLEGAL,
+            },
+            '/' => {
+                if self.peek_char() == "=" {
+                    self.read_char();
+                    token::SLASH_ASSIGN
+                } else if self.peek_char() == "*" {
+                    // 闭合的块注释已经在 read_trivia 里跳过了，到这里的一定没有闭合
+                    token::UNTERMINATED_COMMENT
+                } else {
+                    token::SLASH
+                }
+            }
+            '*' => {
+                if self.peek_char() == "=" {
+                    self.read_char();
+                    token::ASTERISK_ASSIGN
+                } else {
+                    token::ASTERISK
+                }
+            }
+            '<' => {
+                if self.peek_char() == "=" {
+                    self.read_char();
+                    token::LT_EQ
+                } else {
+                    token::LT
+                }
             }
+            '>' => {
+                if self.peek_char() == "=" {
+                    self.read_char();
+                    token::GT_EQ
+                } else {
+                    token::GT
+                }
+            }
+            '%' => token::PERCENT,
+            '~' => token::TILDE,
+            '"' => token::STRING,
             '[' => token::LBRACKET,
             ']' => token::RBRACKET,
             '\0' => token::EOF,
@@ -101,35 +197,55 @@ impl Lexer {
         };
         let ch = match token_type {
             token::NOT_EQ => "!=".into(),
+            token::LT_EQ => "<=".into(),
+            token::GT_EQ => ">=".into(),
             token::EQ => "==".into(),
             token::POW => "^^".into(),
             token::LOGICOR => "||".into(),
             token::LOGICAND => "&&".into(),
-            token::STRING => self.read_string(),
+            token::NULLISH => "??".into(),
+            token::OPTIONAL_DOT => "?.".into(),
+            token::OPTIONAL_LBRACKET => "?[".into(),
+            token::PLUS_ASSIGN => "+=".into(),
+            token::MINUS_ASSIGN => "-=".into(),
+            token::ASTERISK_ASSIGN => "*=".into(),
+            token::SLASH_ASSIGN => "/=".into(),
+            token::STRING => {
+                let (tp, literal) = self.read_string();
+                token_type = tp;
+                literal
+            }
             token::LBRACKET => "[".into(),
             token::RBRACKET => "]".into(),
             token::IDENT => {
-                if is_letter(*self.ch.borrow()) {
+                if is_identifier_start(*self.ch.borrow()) {
                     should_read_one_more = false;
                     let idf = self.read_identifier();
                     let lidf = token::lookup_ident(&idf);
                     match lidf {
                         token::IDENT => (),
-                        _ => token_type = lidf.into(),
+                        _ => token_type = lidf,
                     };
                     idf
                 } else if is_digits(*self.ch.borrow()) {
                     // let idf = self.read_identifier();
                     should_read_one_more = false;
-                    token_type = token::INT;
-                    self.read_number()
+                    let (tp, number) = self.read_number();
+                    token_type = tp;
+                    number
                 } else {
                     token_type = token::ILLEGAL;
-                    self.ch.borrow().clone().into()
+                    (*self.ch.borrow()).into()
                 }
             }
+            token::UNTERMINATED_COMMENT => {
+                while self.position.get() < self.input_chars.len() {
+                    self.read_char();
+                }
+                self.input_chars[start..].iter().collect()
+            }
             token::EOF => '\0'.into(),
-            _ => self.ch.borrow().clone().into(),
+            _ => (*self.ch.borrow()).into(),
         };
 
         if should_read_one_more {
@@ -139,8 +255,75 @@ impl Lexer {
         Token {
             token_type,
             literal: ch,
+            span: self.span_since(start, line, column),
+            leading_trivia,
+        }
+    }
+    /// 从 start 到当前位置的 span，line 和 column 是 start 处的行列
+    fn span_since(&self, start: usize, line: usize, column: usize) -> Span {
+        let end = self.position.get().min(self.input_chars.len());
+        Span {
+            start: self.origin.start + self.byte_offsets[start],
+            end: self.origin.start + self.byte_offsets[end],
+            line: self.origin.line + line - 1,
+            column: if line == 1 {
+                self.origin.column + column - 1
+            } else {
+                column
+            },
         }
     }
+    /// 跳过空白和注释，返回要挂到下一个 token 上的 trivia
+    /// 没有闭合的块注释不跳过，留给 next_token 产生 UNTERMINATED_COMMENT
+    fn read_trivia(&self) -> Vec<Trivia> {
+        let mut trivia = vec![];
+        loop {
+            let start = self.position.get().min(self.input_chars.len());
+            let (line, column) = (self.line.get(), start - self.line_start.get() + 1);
+            let ch = *self.ch.borrow();
+            let kind = if matches!(ch, ' ' | '\t' | '\n' | '\r') {
+                self.skip_white_space();
+                TriviaKind::Whitespace
+            } else if ch == '/' && self.peek_char() == "/" {
+                // //// 和 Rust 一样只是普通注释
+                let is_doc = self.input_chars.get(start + 2) == Some(&'/')
+                    && self.input_chars.get(start + 3) != Some(&'/');
+                while !matches!(*self.ch.borrow(), '\n' | '\0') {
+                    self.read_char();
+                }
+                if is_doc {
+                    TriviaKind::DocComment
+                } else {
+                    TriviaKind::LineComment
+                }
+            } else if ch == '/' && self.peek_char() == "*" {
+                match block_comment_end(&self.input_chars, start + 2) {
+                    Some(end) => {
+                        while self.position.get() < end {
+                            self.read_char();
+                        }
+                        TriviaKind::BlockComment
+                    }
+                    None => break,
+                }
+            } else {
+                break;
+            };
+            if kind == TriviaKind::DocComment || self.lossless.get() {
+                let end = self.position.get().min(self.input_chars.len());
+                trivia.push(Trivia {
+                    kind,
+                    text: self.input_chars[start..end].iter().collect(),
+                    span: self.span_since(start, line, column),
+                });
+            }
+        }
+        trivia
+    }
+    /// 原始的源码，用来在报错时打印出错的那一行
+    pub fn input(&self) -> &str {
+        &self.input
+    }
     pub fn read_char(&self) {
         *self.ch.borrow_mut() = if self.read_position.get() >= self.input_chars.len() {
             '\0'
@@ -151,19 +334,24 @@ impl Lexer {
         self.position.set(self.read_position.get());
         self.read_position.set(self.read_position.get() + 1);
         // }
+        let p = self.position.get();
+        if p > 0 && p <= self.input_chars.len() && self.input_chars[p - 1] == '\n' {
+            self.line.set(self.line.get() + 1);
+            self.line_start.set(p);
+        }
     }
     pub fn peek_char(&self) -> String {
-        if self.read_position.get() > self.input_chars.len() {
+        if self.read_position.get() >= self.input_chars.len() {
             "".into()
         } else {
-            self.input_chars[self.read_position.get()].clone().into()
+            self.input_chars[self.read_position.get()].into()
         }
     }
     pub fn read_identifier(&self) -> String {
         let position = self.position.get();
         self.read_char();
         // 先读一个，后续的可以判断做
-        while is_letter(*self.ch.borrow()) {
+        while is_identifier_continue(*self.ch.borrow()) {
             self.read_char();
         }
         self.input_chars[position..self.position.get()]
@@ -171,7 +359,10 @@ impl Lexer {
             .map(|c| c.clone().to_string())
             .collect::<String>()
     }
-    pub fn read_number(&self) -> String {
+    /// 读一个数字，返回 INT、FLOAT 或者 BIGINT
+    /// 只有十进制的数字才能带小数部分和指数部分，比如 1.5、2e10、1.5e-3
+    /// 整数后面紧跟 n 的是 BIGINT，比如 123n、0xffn
+    pub fn read_number(&self) -> (TokenType, String) {
         let position = self.position.get();
         let is_read_hex =
             *self.ch.borrow() == '0' && (self.peek_char() == "x" || self.peek_char() == "X");
@@ -182,12 +373,69 @@ impl Lexer {
         {
             self.read_char();
         }
-        self.input_chars[position..self.position.get()]
+        let mut token_type = token::INT;
+        let is_decimal = !self.input_chars[position..self.position.get()]
+            .iter()
+            .any(|c| is_not_decimal_symbol(*c));
+        let char_at = |offset: usize| {
+            self.input_chars
+                .get(self.read_position.get() + offset)
+                .copied()
+                .unwrap_or('\0')
+        };
+        // 1. 这样小数点后面没有数字也是浮点数，和 1.0 一样；
+        // 但是 1.foo 和 1.. 这样 . 后面是标识符或者另一个 . 时不算小数点
+        let after_dot = char_at(0);
+        if is_decimal
+            && *self.ch.borrow() == '.'
+            && (is_digits(after_dot) || !(is_identifier_continue(after_dot) || after_dot == '.'))
+        {
+            token_type = token::FLOAT;
+            self.read_char();
+            while is_digits(*self.ch.borrow()) || *self.ch.borrow() == '_' {
+                self.read_char();
+            }
+        }
+        // 指数的 e 后面必须有数字，1e、1e+ 和 1.5e 都是不合法的数字；
+        // 和 1a 一样，e 后面是标识符的字符时不算指数
+        let ch = *self.ch.borrow();
+        let mut is_valid = true;
+        if is_decimal
+            && (ch == 'e' || ch == 'E')
+            && (is_digits(char_at(0)) || !is_identifier_continue(char_at(0)))
+        {
+            token_type = token::FLOAT;
+            self.read_char();
+            if *self.ch.borrow() == '+' || *self.ch.borrow() == '-' {
+                self.read_char();
+            }
+            is_valid = is_digits(*self.ch.borrow());
+            while is_digits(*self.ch.borrow()) {
+                self.read_char();
+            }
+        }
+        // 123nx 这样 n 后面还有标识符的字符时，n 不算后缀；浮点数不能带 n 后缀
+        if *self.ch.borrow() == 'n' && !is_identifier_continue(char_at(0)) {
+            if token_type == token::INT {
+                token_type = token::BIGINT;
+            } else {
+                is_valid = false;
+            }
+            self.read_char();
+        }
+        if !is_valid {
+            let raw = self.input_chars[position..self.position.get()]
+                .iter()
+                .collect::<String>();
+            return (token::INVALID_NUMBER, raw);
+        }
+        let literal = self.input_chars[position..self.position.get()]
             .iter()
             .map(|c| c.clone().to_string())
             // 不知道这里去掉之后是否合适？
             .filter(|val| val != "_")
-            .collect::<String>()
+            .collect::<String>();
+        (token_type, literal)
     }
     pub fn skip_white_space(&self) {
         while *self.ch.borrow() == ' '
@@ -198,15 +446,174 @@ impl Lexer {
             self.read_char();
         }
     }
-    pub fn read_string(&self) -> String {
-        let position = self.position.get();
-        self.read_char();
-        while is_valid_identifier_char(*self.ch.borrow()) {
+    /// 从开头的引号读到结尾的引号，停在结尾的引号上
+    /// 没有 ${} 的字符串返回 STRING，literal 是处理过转义的值；
+    /// 有 ${} 的返回 TEMPLATE，literal 是原样的内容，由 parser 拆开；
+    /// 读到结尾也没有引号返回 UNTERMINATED_STRING，转义不对返回 INVALID_ESCAPE
+    pub fn read_string(&self) -> (TokenType, String) {
+        let start = self.position.get() + 1;
+        let end = string_end(&self.input_chars, start);
+        let stop = end.map_or(self.input_chars.len(), |(e, _)| e);
+        while self.position.get() < stop {
             self.read_char();
         }
-        self.input_chars[position..self.position.get()]
-            .iter()
-            .map(|c| c.clone().to_string())
-            .collect::<String>()
+        let raw = self.input_chars[start..stop].iter().collect::<String>();
+        match end {
+            None => (token::UNTERMINATED_STRING, raw),
+            Some((_, true)) => (token::TEMPLATE, raw),
+            Some((_, false)) => match unescape(&raw) {
+                Ok(value) => (token::STRING, value),
+                Err(seq) => (token::INVALID_ESCAPE, seq),
+            },
+        }
+    }
+}
+
+/// 从 start 开始找字符串结尾的引号，跳过转义的引号和 ${} 里的内容
+/// 返回引号的下标，以及中间有没有 ${}
+pub fn string_end(chars: &[char], start: usize) -> Option<(usize, bool)> {
+    let mut i = start;
+    let mut is_template = false;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Some((i, is_template)),
+            '\\' => i += 1,
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                is_template = true;
+                i = interpolation_end(chars, i + 2)?;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 从 /* 后面开始找配对的 */，返回 */ 后面的下标，块注释可以嵌套
+pub fn block_comment_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start;
+    let mut depth = 1;
+    while i + 1 < chars.len() {
+        match (chars[i], chars[i + 1]) {
+            ('/', '*') => {
+                depth += 1;
+                i += 2;
+            }
+            ('*', '/') => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// 从 ${ 后面开始找配对的 }，里面可以有嵌套的 {} 和字符串
+pub fn interpolation_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start;
+    let mut depth = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            '"' => i = string_end(chars, i + 1)?.0,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 处理转义：\n \t \r \0 \" \\ \$ 和 \u{XXXX}
+/// 出错时返回不认识的那段转义序列
+pub fn unescape(raw: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('0') => out.push('\0'),
+            Some(c @ ('"' | '\\' | '$')) => out.push(c),
+            Some('u') => {
+                let mut seq = String::from("\\u");
+                let mut code = None;
+                match chars.next() {
+                    Some('{') => {
+                        seq.push('{');
+                        for c in chars.by_ref() {
+                            seq.push(c);
+                            if c == '}' {
+                                code = u32::from_str_radix(&seq[3..seq.len() - 1], 16).ok();
+                                break;
+                            }
+                            // 最多 6 位十六进制数字
+                            if !c.is_ascii_hexdigit() || seq.len() > 9 {
+                                break;
+                            }
+                        }
+                    }
+                    Some(c) => seq.push(c),
+                    None => {}
+                }
+                match code.and_then(char::from_u32) {
+                    Some(c) => out.push(c),
+                    None => return Err(seq),
+                }
+            }
+            Some(c) => return Err(format!("\\{}", c)),
+            None => return Err("\\".into()),
+        }
+    }
+    Ok(out)
+}
+
+/// 模板字符串的一段：普通文本（还没处理转义），或者 ${} 里的源码和它在内容中的字符下标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSegment {
+    Text(String),
+    Code(usize, String),
+}
+
+/// 把 TEMPLATE token 的内容拆成文本和 ${} 两种片段
+pub fn split_template(raw: &str) -> Vec<TemplateSegment> {
+    let chars = raw.chars().collect::<Vec<_>>();
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                text.extend(chars[i..chars.len().min(i + 2)].iter());
+                i += 2;
+            }
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                if !text.is_empty() {
+                    segments.push(TemplateSegment::Text(std::mem::take(&mut text)));
+                }
+                let end = interpolation_end(&chars, i + 2).unwrap_or(chars.len());
+                let code = chars[i + 2..end].iter().collect();
+                segments.push(TemplateSegment::Code(i + 2, code));
+                i = end + 1;
+            }
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        segments.push(TemplateSegment::Text(text));
     }
+    segments
 }
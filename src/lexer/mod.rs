@@ -1,3 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod lexer;
 pub use lexer::*;
+#[allow(clippy::module_inception)]
 mod test;
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod test {
-    use {crate::lexer::Lexer, crate::token};
+    use {crate::lexer::*, crate::token};
 
     #[test]
     fn canary_test() {
@@ -88,6 +88,7 @@ let result = add(five, ten);"#;
             assert_eq!(p_token.literal, test.1);
         });
     }
+    // /* 是块注释的开头，所以下面的 / 和 * 之间隔了一个空格
     #[test]
     fn test_next_token_short_code_1() {
         let input = r#"let five = 5;
@@ -96,7 +97,7 @@ let add = fn(x, y) {
     x + y;
 };
 let result = add(five, ten);
-!-/*5;
+!-/ *5;
 5 < 10 > 5;
 "#;
 
@@ -169,7 +170,7 @@ let add = fn(x, y) {
     x + y;
 };
 let result = add(five, ten);
-!-/*5;
+!-/ *5;
 5 < 10 > 5;
 if ( 5 < 10 ) {
     return true;
@@ -299,6 +300,7 @@ if ( 5 < 10 ) {
         assert_eq!(count, 3);
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_string_literal() {
         let input = r#""foobar""#;
@@ -315,6 +317,131 @@ if ( 5 < 10 ) {
         });
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let cases = [
+            (r#""a\nb""#, token::STRING, "a\nb"),
+            (r#""\t\"\\\$\0""#, token::STRING, "\t\"\\$\0"),
+            (r#""\u{4e2d}\u{1F600}""#, token::STRING, "中😀"),
+            (r#""""#, token::STRING, ""),
+            (r#""abc"#, token::UNTERMINATED_STRING, "abc"),
+            (r#""a\"#, token::UNTERMINATED_STRING, "a\\"),
+            (r#""a\qb""#, token::INVALID_ESCAPE, "\\q"),
+            (r#""\u{zz}""#, token::INVALID_ESCAPE, "\\u{z"),
+            (r#""\u{110000}""#, token::INVALID_ESCAPE, "\\u{110000}"),
+            (r#""\u41""#, token::INVALID_ESCAPE, "\\u4"),
+            (r#""$a {b}""#, token::STRING, "$a {b}"),
+            (r#""hi ${name}!""#, token::TEMPLATE, "hi ${name}!"),
+            (
+                r#""${ {"}": "\""}["}"] }""#,
+                token::TEMPLATE,
+                r#"${ {"}": "\""}["}"] }"#,
+            ),
+            (r#""${a""#, token::UNTERMINATED_STRING, r#"${a""#),
+        ];
+        cases.iter().for_each(|(input, token_type, literal)| {
+            let lex = Lexer::new(*input);
+            let tk = lex.next_token();
+            assert_eq!(
+                (tk.token_type, tk.literal.as_str()),
+                (*token_type, *literal),
+                "{}",
+                input
+            );
+            assert_eq!(lex.next_token().token_type, token::EOF, "{}", input);
+        });
+
+        // 空字符串后面的 token 不会被吞掉
+        let lex = Lexer::new(r#""" + "a""#);
+        let tokens = (0..4)
+            .map(|_| lex.next_token().token_type)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [token::STRING, token::PLUS, token::STRING, token::EOF]
+        );
+    }
+
+    #[test]
+    fn test_split_template() {
+        assert_eq!(
+            split_template(r#"a\${b} ${c + 1}${"}"}"#),
+            vec![
+                TemplateSegment::Text(r#"a\${b} "#.into()),
+                TemplateSegment::Code(9, "c + 1".into()),
+                TemplateSegment::Code(17, r#""}""#.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "let a = 1; // one\n/* two /* nested */ still */ a /*/ */ / 2\n/// doc\n//// plain\nlet";
+        let tests = [
+            (token::LET, "let"),
+            (token::IDENT, "a"),
+            (token::ASSIGN, "="),
+            (token::INT, "1"),
+            (token::SEMICOLON, ";"),
+            (token::IDENT, "a"),
+            (token::SLASH, "/"),
+            (token::INT, "2"),
+            (token::LET, "let"),
+            (token::EOF, "\0"),
+        ];
+        let lex = Lexer::new(input);
+        let tokens = tests.iter().map(|_| lex.next_token()).collect::<Vec<_>>();
+        tests.iter().zip(tokens.iter()).for_each(|(test, tk)| {
+            assert_eq!((tk.token_type, tk.literal.as_str()), *test);
+        });
+        // 普通模式下只有文档注释会留下来
+        assert!(tokens[..8].iter().all(|tk| tk.leading_trivia.is_empty()));
+        assert_eq!(tokens[8].leading_trivia.len(), 1);
+        assert_eq!(tokens[8].doc_comment(), Some("doc".into()));
+
+        let lex = Lexer::new("1 /* a /* b */");
+        assert_eq!(lex.next_token().token_type, token::INT);
+        let tk = lex.next_token();
+        assert_eq!(
+            (tk.token_type, tk.literal.as_str()),
+            (token::UNTERMINATED_COMMENT, "/* a /* b */")
+        );
+        assert_eq!(lex.next_token().token_type, token::EOF);
+    }
+
+    #[test]
+    fn test_lossless_trivia() {
+        let input = "  /// add\n/// two\nlet add = fn(a) { a /* x */ + 1 }; // end\n";
+        let lex = Lexer::new(input);
+        lex.set_lossless(true);
+        let mut output = String::new();
+        loop {
+            let tk = lex.next_token();
+            tk.leading_trivia.iter().for_each(|t| {
+                assert_eq!(&input[t.span.start..t.span.end], t.text);
+                output.push_str(&t.text);
+            });
+            output.push_str(&input[tk.span.start..tk.span.end]);
+            if tk.token_type == token::EOF {
+                assert_eq!(
+                    tk.leading_trivia.iter().map(|t| t.kind).collect::<Vec<_>>(),
+                    [
+                        token::TriviaKind::Whitespace,
+                        token::TriviaKind::LineComment,
+                        token::TriviaKind::Whitespace
+                    ]
+                );
+                break;
+            }
+            if tk.token_type == token::LET {
+                assert_eq!(tk.leading_trivia.len(), 5);
+                assert_eq!(tk.doc_comment(), Some("add\ntwo".into()));
+            }
+        }
+        assert_eq!(output, input);
+    }
+
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_array_literal() {
         let input = r#"[1, 2]"#;
@@ -364,6 +491,7 @@ if ( 5 < 10 ) {
         });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_hash_table_literal() {
         let input = r#"{"foo": "bar"}"#;
@@ -385,19 +513,228 @@ if ( 5 < 10 ) {
             assert_eq!(p_token.literal, test.1);
         });
     }
-    //     #[test]
-    //     fn test_unicode() {
-    //         let input = r#"let abcd = 1;
-    // let 中文名字 = 1;"#;
-    //         let lex = Lexer::new(input);
-    //         let mut tk = lex.next_token();
-    //         let mut count = 0;
-    //         while tk.token_type != token::EOF {
-    //             count += 1;
-    //             println!("{:?}", tk);
-    //             tk = lex.next_token();
-    //         }
-    //         // FIXME: 等待 is_letter 方法支持unicode更多字符
-    //         assert_eq!(count, 10);
-    //     }
+    #[test]
+    fn test_assign_operators() {
+        let input = "a = 1; a += 2; a -= 3; a *= 4; a /= 5; a == a";
+        let tests = [
+            (token::IDENT, "a"),
+            (token::ASSIGN, "="),
+            (token::INT, "1"),
+            (token::SEMICOLON, ";"),
+            (token::IDENT, "a"),
+            (token::PLUS_ASSIGN, "+="),
+            (token::INT, "2"),
+            (token::SEMICOLON, ";"),
+            (token::IDENT, "a"),
+            (token::MINUS_ASSIGN, "-="),
+            (token::INT, "3"),
+            (token::SEMICOLON, ";"),
+            (token::IDENT, "a"),
+            (token::ASTERISK_ASSIGN, "*="),
+            (token::INT, "4"),
+            (token::SEMICOLON, ";"),
+            (token::IDENT, "a"),
+            (token::SLASH_ASSIGN, "/="),
+            (token::INT, "5"),
+            (token::SEMICOLON, ";"),
+            (token::IDENT, "a"),
+            (token::EQ, "=="),
+            (token::IDENT, "a"),
+            (token::EOF, "\0"),
+        ];
+        let lex = Lexer::new(input);
+
+        tests.iter().for_each(|test| {
+            let p_token = lex.next_token();
+            assert_eq!(p_token.token_type, test.0);
+            assert_eq!(p_token.literal, test.1);
+        });
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let input = "a <= b >= c < d > e % f ~g";
+        let tests = [
+            (token::IDENT, "a"),
+            (token::LT_EQ, "<="),
+            (token::IDENT, "b"),
+            (token::GT_EQ, ">="),
+            (token::IDENT, "c"),
+            (token::LT, "<"),
+            (token::IDENT, "d"),
+            (token::GT, ">"),
+            (token::IDENT, "e"),
+            (token::PERCENT, "%"),
+            (token::IDENT, "f"),
+            (token::TILDE, "~"),
+            (token::IDENT, "g"),
+            (token::EOF, "\0"),
+        ];
+        let lex = Lexer::new(input);
+        tests.iter().for_each(|test| {
+            let tk = lex.next_token();
+            assert_eq!((tk.token_type, tk.literal.as_str()), *test);
+        });
+    }
+
+    #[test]
+    fn test_null_operators() {
+        let input = "null ?? a?.b?[0] ? c";
+        let tests = [
+            (token::NULL, "null"),
+            (token::NULLISH, "??"),
+            (token::IDENT, "a"),
+            (token::OPTIONAL_DOT, "?."),
+            (token::IDENT, "b"),
+            (token::OPTIONAL_LBRACKET, "?["),
+            (token::INT, "0"),
+            (token::RBRACKET, "]"),
+            (token::ILLEGAL, "?"),
+            (token::IDENT, "c"),
+            (token::EOF, "\0"),
+        ];
+        let lex = Lexer::new(input);
+        tests.iter().for_each(|test| {
+            let tk = lex.next_token();
+            assert_eq!((tk.token_type, tk.literal.as_str()), *test);
+        });
+    }
+
+    #[test]
+    fn test_try_keywords() {
+        let input = "try catch finally throw trying";
+        let tests = [
+            (token::TRY, "try"),
+            (token::CATCH, "catch"),
+            (token::FINALLY, "finally"),
+            (token::THROW, "throw"),
+            (token::IDENT, "trying"),
+            (token::EOF, "\0"),
+        ];
+        let lex = Lexer::new(input);
+        tests.iter().for_each(|test| {
+            let tk = lex.next_token();
+            assert_eq!((tk.token_type, tk.literal.as_str()), *test);
+        });
+    }
+
+    #[test]
+    fn test_float_number() {
+        let input = "1.5 2e3 1.25E-2 1_000.5 0x1e 7 1e x 1ex 1.5e 1e+ 1e+x 1. [2.];";
+        let tests = [
+            (token::FLOAT, "1.5"),
+            (token::FLOAT, "2e3"),
+            (token::FLOAT, "1.25E-2"),
+            (token::FLOAT, "1000.5"),
+            (token::INT, "0x1e"),
+            (token::INT, "7"),
+            // e 后面没有数字，不是合法的数字
+            (token::INVALID_NUMBER, "1e"),
+            (token::IDENT, "x"),
+            // e 后面是标识符的字符，不是指数
+            (token::INT, "1"),
+            (token::IDENT, "ex"),
+            (token::INVALID_NUMBER, "1.5e"),
+            (token::INVALID_NUMBER, "1e+"),
+            (token::INVALID_NUMBER, "1e+"),
+            (token::IDENT, "x"),
+            // 小数点后面没有数字也是浮点数
+            (token::FLOAT, "1."),
+            (token::LBRACKET, "["),
+            (token::FLOAT, "2."),
+            (token::RBRACKET, "]"),
+            (token::SEMICOLON, ";"),
+            (token::EOF, "\0"),
+        ];
+        let lex = Lexer::new(input);
+
+        tests.iter().for_each(|test| {
+            let p_token = lex.next_token();
+            assert_eq!(p_token.token_type, test.0);
+            assert_eq!(p_token.literal, test.1);
+        });
+    }
+
+    #[test]
+    fn test_big_integer_number() {
+        let input = "123n 0xffn 1_000n 7 123nx 1.5n 1e3n 1.5nx";
+        let tests = [
+            (token::BIGINT, "123n"),
+            (token::BIGINT, "0xffn"),
+            (token::BIGINT, "1000n"),
+            (token::INT, "7"),
+            // n 后面还有标识符的字符，不是后缀
+            (token::INT, "123"),
+            (token::IDENT, "nx"),
+            // 浮点数不能带 n 后缀
+            (token::INVALID_NUMBER, "1.5n"),
+            (token::INVALID_NUMBER, "1e3n"),
+            (token::FLOAT, "1.5"),
+            (token::IDENT, "nx"),
+            (token::EOF, "\0"),
+        ];
+        let lex = Lexer::new(input);
+
+        tests.iter().for_each(|test| {
+            let p_token = lex.next_token();
+            assert_eq!(p_token.token_type, test.0);
+            assert_eq!(p_token.literal, test.1);
+        });
+    }
+
+    #[test]
+    fn test_token_span() {
+        let input = "let a = \"中文\";\n  a + 10;";
+        let tests = [
+            (token::LET, 0, 3, 1, 1),
+            (token::IDENT, 4, 5, 1, 5),
+            (token::ASSIGN, 6, 7, 1, 7),
+            // 字节偏移，中文每个字三个字节，包括两边的引号
+            (token::STRING, 8, 16, 1, 9),
+            (token::SEMICOLON, 16, 17, 1, 13),
+            (token::IDENT, 20, 21, 2, 3),
+            (token::PLUS, 22, 23, 2, 5),
+            (token::INT, 24, 26, 2, 7),
+            (token::SEMICOLON, 26, 27, 2, 9),
+            (token::EOF, 27, 27, 2, 10),
+        ];
+        let lex = Lexer::new(input);
+
+        tests
+            .iter()
+            .for_each(|&(token_type, start, end, line, column)| {
+                let tk = lex.next_token();
+                assert_eq!(tk.token_type, token_type);
+                assert_eq!(
+                    (tk.span.start, tk.span.end, tk.span.line, tk.span.column),
+                    (start, end, line, column),
+                    "{:?}",
+                    tk
+                );
+            });
+    }
+
+    #[test]
+    fn test_unicode_identifier() {
+        let input = "let abcd = 1;\nlet 中文名字 = 1; _x1 + café2 + ǅ";
+        let lex = Lexer::new(input);
+        let mut tokens = vec![];
+        let mut tk = lex.next_token();
+        while tk.token_type != token::EOF {
+            tokens.push((tk.token_type, tk.literal.clone()));
+            tk = lex.next_token();
+        }
+        assert_eq!(tokens.len(), 15);
+        assert_eq!(tokens[6], (token::IDENT, "中文名字".to_string()));
+        assert_eq!(tokens[10], (token::IDENT, "_x1".to_string()));
+        assert_eq!(tokens[12], (token::IDENT, "café2".to_string()));
+        assert_eq!(tokens[14], (token::IDENT, "ǅ".to_string()));
+
+        // 数字和标点不能作为标识符的开头
+        let lex = Lexer::new("1a ·");
+        let tokens = (0..3)
+            .map(|_| lex.next_token().token_type)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, [token::INT, token::IDENT, token::ILLEGAL]);
+    }
 }
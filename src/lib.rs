@@ -1,17 +1,32 @@
+// derive(FromObject, IntoObject) 生成的代码用 ::my_rust_interpreter 开头的路径，crate 自己里面也要能用
+extern crate self as my_rust_interpreter;
+
 pub mod ast;
+pub mod compiler;
+pub mod diagnostic;
 pub mod evaluator;
+pub mod interpreter;
 pub mod lexer;
 pub mod object;
 pub mod parser;
 pub mod repl;
+pub mod runner;
 pub mod token;
+pub mod tracer;
 pub mod utils;
+pub mod vm;
 
 #[allow(unused_imports)]
 pub use ast::*;
 #[allow(unused_imports)]
+pub use compiler::*;
+#[allow(unused_imports)]
+pub use diagnostic::*;
+#[allow(unused_imports)]
 pub use evaluator::*;
 #[allow(unused_imports)]
+pub use interpreter::*;
+#[allow(unused_imports)]
 pub use lexer::*;
 #[allow(unused_imports)]
 pub use object::*;
@@ -20,6 +35,12 @@ pub use parser::*;
 #[allow(unused_imports)]
 pub use repl::*;
 #[allow(unused_imports)]
+pub use runner::*;
+#[allow(unused_imports)]
 pub use token::*;
 #[allow(unused_imports)]
+pub use tracer::*;
+#[allow(unused_imports)]
 pub use utils::*;
+#[allow(unused_imports)]
+pub use vm::*;
@@ -1,7 +1,78 @@
 pub use my_rust_interpreter::repl;
+use my_rust_interpreter::runner::run_source;
+use std::process::ExitCode;
 
-fn main() {
-    // let cargo_file = include_str!("../Cargo.toml");
-    // println!("Hello, world! {}", cargo_file);
-    repl::start();
+const USAGE: &str = r#"Usage: my-rust-interpreter [OPTIONS] [FILE]
+
+Without FILE or -e, start the REPL.
+
+Options:
+  -e <CODE>    run CODE instead of a file
+  -p, --print  print the value of the last expression
+  -h, --help   print this help
+"#;
+
+enum Source {
+    Repl,
+    File(String),
+    Code(String),
+}
+
+fn parse_args(args: &[String]) -> Result<(Source, bool), String> {
+    let mut source = Source::Repl;
+    let mut print_result = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-p" | "--print" => print_result = true,
+            "-e" => match iter.next() {
+                Some(code) => source = Source::Code(code.clone()),
+                None => return Err("-e requires an argument".into()),
+            },
+            "-h" | "--help" => return Err(String::new()),
+            a if a.starts_with('-') => return Err(format!("unknown option: {}", a)),
+            path => source = Source::File(path.to_string()),
+        }
+    }
+    Ok((source, print_result))
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (source, print_result) = match parse_args(&args) {
+        Ok(r) => r,
+        Err(msg) => {
+            if !msg.is_empty() {
+                eprintln!("{}\n", msg);
+            }
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let code = match source {
+        Source::Repl => {
+            repl::start();
+            return ExitCode::SUCCESS;
+        }
+        Source::Code(code) => code,
+        Source::File(path) => match std::fs::read_to_string(&path) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("cannot read {}: {}", path, e);
+                return ExitCode::from(2);
+            }
+        },
+    };
+    match run_source(&code) {
+        Ok(result) => {
+            if let (true, Some(r)) = (print_result, result) {
+                println!("{}", r);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(msg) => {
+            eprint!("{}", msg);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,27 @@
+pub use crate::object::*;
+use ast_macro::object;
+use num_bigint::BigInt;
+pub use std::rc::Rc;
+
+/// 任意精度的整数，开启溢出提升时整数运算溢出的结果就是它
+#[object(BIG_INTEGER_OBJECT)]
+pub struct BigInteger {
+    pub value: BigInt,
+}
+
+impl ObjectInspect for BigInteger {
+    fn _inspect(&self) -> String {
+        self.value.to_string()
+    }
+}
+
+impl TryFrom<Rc<dyn Object>> for BigInteger {
+    type Error = String;
+
+    fn try_from(value: Rc<dyn Object>) -> Result<Self, Self::Error> {
+        match value.as_any().downcast_ref::<BigInteger>() {
+            Some(v) => Ok(v.clone()),
+            None => Err(ConversionError::new(BIG_INTEGER_OBJECT, &value).to_string()),
+        }
+    }
+}
@@ -22,6 +22,6 @@ impl TryFrom<Rc<dyn Object>> for Boolean {
                 return Ok((*v).clone());
             }
         }
-        Err("Str".into())
+        Err(ConversionError::new(BOOLEAN_OBJECT, &value).to_string())
     }
 }
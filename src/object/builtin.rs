@@ -1,7 +1,17 @@
 pub use crate::object::*;
 use ast_macro::object;
 
-pub type BuiltinFunction = fn(args: Vec<Rc<dyn Object>>) -> Option<Rc<dyn Object>>;
+/// 原生函数，除了 fn 指针也可以是捕获了宿主状态的闭包
+pub trait NativeFunction: Fn(Vec<Rc<dyn Object>>) -> Option<Rc<dyn Object>> {}
+impl<F: Fn(Vec<Rc<dyn Object>>) -> Option<Rc<dyn Object>>> NativeFunction for F {}
+
+impl std::fmt::Debug for dyn NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native function>")
+    }
+}
+
+pub type BuiltinFunction = dyn NativeFunction;
 #[object(BUILTIN_OBJECT)]
 pub struct BuiltinObject {
     // function
@@ -13,3 +23,32 @@ impl ObjectInspect for BuiltinObject {
         "builtin function".into()
     }
 }
+
+impl BuiltinObject {
+    /// 用带类型的参数写原生函数，参数个数和类型不对时返回 ErrorObject
+    ///
+    /// ```
+    /// use my_rust_interpreter::*;
+    ///
+    /// let repeat = BuiltinObject::typed("repeat", |(s, n): (String, i64)| s.repeat(n as usize));
+    /// let r = (repeat.func)(vec!["ab".into_object(), 2.into_object()]).unwrap();
+    /// assert_eq!(r.inspect(), "abab");
+    /// let r = (repeat.func)(vec!["ab".into_object(), true.into_object()]).unwrap();
+    /// assert_eq!(r.inspect(), "TypeError: argument[1] to `repeat` must be INTEGER, got BOOLEAN");
+    /// ```
+    pub fn typed<A, R, F>(name: &'static str, func: F) -> Self
+    where
+        A: FromArgs,
+        R: IntoObject,
+        F: Fn(A) -> R + 'static,
+    {
+        BuiltinObject {
+            func: Rc::new(move |args: Vec<Rc<dyn Object>>| {
+                Some(match A::from_args(name, &args) {
+                    Ok(a) => func(a).into_object(),
+                    Err(err) => Rc::new(err),
+                })
+            }),
+        }
+    }
+}
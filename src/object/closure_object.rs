@@ -0,0 +1,21 @@
+pub use crate::object::*;
+use ast_macro::object;
+use std::cell::RefCell;
+pub use std::rc::Rc;
+
+/// 一个被捕获的变量。闭包和定义它的函数共享同一个格子，
+/// 这样和 evaluator 里共享 Context 的语义保持一致；let 之前格子里是 None
+pub type FreeVariable = Rc<RefCell<Option<Rc<dyn Object>>>>;
+
+/// 和 evaluator 的 FunctionObject 一样，类型是 FUNCTION_OBJECT
+#[object(FUNCTION_OBJECT)]
+pub struct ClosureObject {
+    pub function: Rc<CompiledFunctionObject>,
+    pub free: Vec<FreeVariable>,
+}
+
+impl ObjectInspect for ClosureObject {
+    fn _inspect(&self) -> String {
+        self.function.source.to_string()
+    }
+}
@@ -0,0 +1,25 @@
+pub use crate::object::*;
+use crate::token::Span;
+use ast_macro::object;
+pub use std::rc::Rc;
+
+/// 编译器产出的函数体，作为常量存放在 Bytecode 里
+/// 运行时需要包装成 ClosureObject 才能被调用
+#[object(COMPILED_FUNCTION_OBJECT)]
+pub struct CompiledFunctionObject {
+    pub instructions: Rc<Vec<u8>>,
+    // 每个字节对应的源码位置，和 instructions 一样长
+    pub spans: Rc<Vec<Span>>,
+    pub num_locals: usize,
+    pub num_parameters: usize,
+    // 和 FunctionObject::name 一样，报错时用
+    pub name: Option<String>,
+    // inspect_function 的结果，闭包 inspect 出来和 FunctionObject 一样
+    pub source: Rc<String>,
+}
+
+impl ObjectInspect for CompiledFunctionObject {
+    fn _inspect(&self) -> String {
+        format!("compiled function({})", self.num_parameters)
+    }
+}
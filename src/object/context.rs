@@ -1,26 +1,42 @@
 use crate::object::*;
+use crate::tracer::Tracer;
 use crate::Identifier;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 
-#[derive(Debug)]
+/// 默认的最大调用深度，函数调用嵌套超过它时报 stack overflow
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1024;
+
 pub struct Context {
     pub parent: Option<Rc<Context>>,
     pub scope: RefCell<HashMap<Rc<Identifier>, Rc<dyn Object>>>,
+    tracer: RefCell<Option<Rc<dyn Tracer>>>,
+    max_call_depth: Cell<Option<usize>>,
 }
 
-impl Context {
-    pub fn default() -> Self {
-        Context {
-            scope: RefCell::new(HashMap::new()),
-            parent: None,
-        }
+impl std::fmt::Debug for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("parent", &self.parent)
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
     }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Context {
     pub fn new() -> Self {
         Context {
             scope: RefCell::new(HashMap::new()),
             parent: None,
+            tracer: RefCell::new(None),
+            max_call_depth: Cell::new(None),
         }
     }
     pub fn set(&self, name: Rc<Identifier>, val: Rc<dyn Object>) {
@@ -35,10 +51,45 @@ impl Context {
         }
         None
     }
+    /// 修改已经存在的变量，沿着 parent 往上找到最近的那个定义
+    /// 变量从来没有被 let 过的时候返回 false
+    pub fn assign(&self, name: &Rc<Identifier>, val: Rc<dyn Object>) -> bool {
+        if let Some(current) = self.scope.borrow_mut().get_mut(name) {
+            *current = val;
+            return true;
+        }
+        match self.parent {
+            Some(ref parent) => parent.assign(name, val),
+            None => false,
+        }
+    }
     pub fn extend(parent: Rc<Self>) -> Self {
         Context {
             scope: RefCell::new(HashMap::new()),
             parent: Some(parent.clone()),
+            tracer: RefCell::new(None),
+            max_call_depth: Cell::new(None),
+        }
+    }
+    /// 注册到最外层的 Context 上，所有子 Context 都会用到它
+    pub fn set_tracer(&self, tracer: Rc<dyn Tracer>) {
+        self.tracer.replace(Some(tracer));
+    }
+    pub fn tracer(&self) -> Option<Rc<dyn Tracer>> {
+        if let Some(tracer) = self.tracer.borrow().as_ref() {
+            return Some(tracer.clone());
+        }
+        self.parent.as_ref().and_then(|p| p.tracer())
+    }
+    /// 和 tracer 一样设置在最外层的 Context 上，没有设置时是 DEFAULT_MAX_CALL_DEPTH
+    pub fn set_max_call_depth(&self, depth: usize) {
+        self.max_call_depth.set(Some(depth));
+    }
+    pub fn max_call_depth(&self) -> usize {
+        match (self.max_call_depth.get(), self.parent.as_ref()) {
+            (Some(depth), _) => depth,
+            (None, Some(parent)) => parent.max_call_depth(),
+            (None, None) => DEFAULT_MAX_CALL_DEPTH,
         }
     }
 }
@@ -59,6 +110,8 @@ mod test {
             token: Token {
                 token_type: IDENT,
                 literal: name.to_string(),
+                span: Span::default(),
+                leading_trivia: vec![],
             },
             value: name.to_string(),
         })
@@ -71,9 +124,7 @@ mod test {
         let key = gen_id("foobar");
         context.set(
             key.clone(),
-            Rc::new(ErrorObject {
-                message: "error".into(),
-            }),
+            Rc::new(ErrorObject::new(ErrorKind::Error, "error")),
         );
         assert_eq!(context.scope.borrow().len(), 1);
         let r = context.get(&key);
@@ -87,9 +138,7 @@ mod test {
         let key = gen_id("foobar");
         context.set(
             key.clone(),
-            Rc::new(ErrorObject {
-                message: "error".into(),
-            }),
+            Rc::new(ErrorObject::new(ErrorKind::Error, "error")),
         );
         assert_eq!(context.scope.borrow().len(), 1);
         let r = context.get(&key);
@@ -103,9 +152,7 @@ mod test {
         let c1 = gen_id("c1");
         context1.set(
             c1,
-            Rc::new(ErrorObject {
-                message: "for_context1".into(),
-            }),
+            Rc::new(ErrorObject::new(ErrorKind::Error, "for_context1")),
         );
         assert_eq!(context1.scope.borrow().len(), 1);
         assert_eq!(context.scope.borrow().len(), 1);
@@ -113,12 +160,25 @@ mod test {
         let c = gen_id("c");
         context.set(
             c.clone(),
-            Rc::new(ErrorObject {
-                message: "for_context".into(),
-            }),
+            Rc::new(ErrorObject::new(ErrorKind::Error, "for_context")),
         );
         assert_eq!(context1.scope.borrow().len(), 1);
         assert_eq!(context.scope.borrow().len(), 2);
         assert!(context1.get(&c.clone()).is_some());
     }
+
+    #[test]
+    fn test_assign() {
+        let context = Rc::new(Context::new());
+        let key = gen_id("foobar");
+        context.set(key.clone(), Rc::new(Integer { value: 1 }));
+        let context1 = Context::extend(context.clone());
+
+        assert!(context1.assign(&key, Rc::new(Integer { value: 2 })));
+        assert_eq!(context1.scope.borrow().len(), 0);
+        assert_eq!(context.get(&key).unwrap().inspect(), "2");
+
+        assert!(!context1.assign(&gen_id("c"), Rc::new(Integer { value: 3 })));
+        assert!(context1.get(&gen_id("c")).is_none());
+    }
 }
@@ -0,0 +1,585 @@
+use crate::object::*;
+pub use ast_macro::{FromObject, IntoObject};
+use indexmap::IndexMap;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Object 转 Rust 值失败时的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    pub expected: String,
+    pub found: String,
+}
+
+impl ConversionError {
+    pub fn new(expected: impl Into<String>, found: &Rc<dyn Object>) -> Self {
+        ConversionError {
+            expected: expected.into(),
+            found: found.object_type().into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected {}, got {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// 从解释器里的值转成 Rust 值
+///
+/// 可以用 `#[derive(FromObject)]` 把以字段名为 key 的 HASH 转成结构体，
+/// 只 import 这两个 derive 就够了，泛型参数要能转换
+///
+/// ```
+/// use my_rust_interpreter::object::{FromObject, IntoObject};
+///
+/// #[derive(Debug, PartialEq, FromObject, IntoObject)]
+/// struct Pair<T> {
+///     name: String,
+///     value: T,
+/// }
+///
+/// let pair = Pair { name: "a".to_string(), value: vec![1_i64, 2] };
+/// let obj = Pair { name: "a".to_string(), value: vec![1_i64, 2] }.into_object();
+/// assert_eq!(Pair::<Vec<i64>>::from_object(&obj), Ok(pair));
+/// ```
+pub trait FromObject: Sized {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError>;
+
+    /// 作为原生函数最后几个参数时可以不传，不传的按 NULL 转换
+    fn is_optional() -> bool {
+        false
+    }
+}
+
+/// 把 Rust 值转成解释器里的值
+///
+/// 可以用 `#[derive(IntoObject)]` 把结构体转成以字段名为 key 的 HASH，
+/// 只支持有名字段的结构体，其他类型是编译错误
+///
+/// ```compile_fail
+/// use my_rust_interpreter::object::IntoObject;
+///
+/// #[derive(IntoObject)]
+/// enum Shape {
+///     Circle,
+/// }
+/// ```
+pub trait IntoObject {
+    fn into_object(self) -> Rc<dyn Object>;
+}
+
+impl FromObject for Rc<dyn Object> {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        Ok(obj.clone())
+    }
+}
+
+impl IntoObject for Rc<dyn Object> {
+    fn into_object(self) -> Rc<dyn Object> {
+        self
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        obj.as_any()
+            .downcast_ref::<Integer>()
+            .map(|i| i.value)
+            .ok_or_else(|| ConversionError::new(INTEGER_OBJECT, obj))
+    }
+}
+
+impl IntoObject for i64 {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(Integer { value: self })
+    }
+}
+
+impl IntoObject for BigInt {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(BigInteger { value: self })
+    }
+}
+
+impl IntoObject for BigRational {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(Rational { value: self })
+    }
+}
+
+impl FromObject for f64 {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        let any = obj.as_any();
+        if let Some(f) = any.downcast_ref::<Float>() {
+            return Ok(f.value);
+        }
+        // 和四则运算一样，需要 FLOAT 的地方 INTEGER 也可以用
+        if let Some(i) = any.downcast_ref::<Integer>() {
+            return Ok(i.value as f64);
+        }
+        Err(ConversionError::new(FLOAT_OBJECT, obj))
+    }
+}
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(Float { value: self })
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        obj.as_any()
+            .downcast_ref::<Boolean>()
+            .map(|b| b.value)
+            .ok_or_else(|| ConversionError::new(BOOLEAN_OBJECT, obj))
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(Boolean { value: self })
+    }
+}
+
+impl FromObject for String {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        obj.as_any()
+            .downcast_ref::<StringObject>()
+            .map(|s| s.value.to_string())
+            .ok_or_else(|| ConversionError::new(STRING_OBJECT, obj))
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(StringObject {
+            value: Rc::new(self),
+        })
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Rc<dyn Object> {
+        self.to_string().into_object()
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(Null {})
+    }
+}
+
+/// NULL 对应 None，其他值按 T 转换
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        if obj.as_any().is::<Null>() {
+            return Ok(None);
+        }
+        T::from_object(obj).map(Some).map_err(|e| ConversionError {
+            expected: format!("{} or {}", e.expected, NULL_OBJECT),
+            found: e.found,
+        })
+    }
+
+    fn is_optional() -> bool {
+        true
+    }
+}
+
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Rc<dyn Object> {
+        match self {
+            Some(v) => v.into_object(),
+            None => ().into_object(),
+        }
+    }
+}
+
+/// Err 变成 ErrorObject，宿主函数可以直接用 ? 返回错误，字符串错误的 kind 是 Error
+impl<T: IntoObject> IntoObject for Result<T, String> {
+    fn into_object(self) -> Rc<dyn Object> {
+        self.map_err(|message| ErrorObject::new(ErrorKind::Error, message))
+            .into_object()
+    }
+}
+
+/// 需要指定错误种类时直接返回 ErrorObject
+impl<T: IntoObject> IntoObject for Result<T, ErrorObject> {
+    fn into_object(self) -> Rc<dyn Object> {
+        match self {
+            Ok(v) => v.into_object(),
+            Err(err) => Rc::new(err),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        let arr = obj
+            .as_any()
+            .downcast_ref::<ArrayObject>()
+            .ok_or_else(|| ConversionError::new(ARRAY_OBJECT, obj))?;
+        arr.elements
+            .borrow()
+            .iter()
+            .map(|el| T::from_object(el))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ConversionError {
+                expected: format!("{} of {}", ARRAY_OBJECT, e.expected),
+                found: format!("{} containing {}", ARRAY_OBJECT, e.found),
+            })
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(ArrayObject {
+            elements: RefCell::new(self.into_iter().map(|v| v.into_object()).collect()),
+        })
+    }
+}
+
+/// 只接受 key 全是 STRING 的 HASH
+impl<T: FromObject> FromObject for HashMap<String, T> {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        let hash = obj
+            .as_any()
+            .downcast_ref::<HashObject>()
+            .ok_or_else(|| ConversionError::new(HASH_OBJECT, obj))?;
+        let pairs = hash.pairs.borrow();
+        let mut map = HashMap::with_capacity(pairs.len());
+        for (k, v) in pairs.iter() {
+            let key = match k {
+                HashKey::String(s) => s.to_string(),
+                _ => {
+                    return Err(ConversionError {
+                        expected: format!("{} with {} keys", HASH_OBJECT, STRING_OBJECT),
+                        found: format!("{} with {} key", HASH_OBJECT, k.to_object().object_type()),
+                    })
+                }
+            };
+            let value = T::from_object(v).map_err(|e| ConversionError {
+                expected: format!("{} of {}", HASH_OBJECT, e.expected),
+                found: format!("{} containing {}", HASH_OBJECT, e.found),
+            })?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+impl<T: IntoObject> IntoObject for HashMap<String, T> {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(HashObject {
+            pairs: RefCell::new(
+                self.into_iter()
+                    .map(|(k, v)| (HashKey::String(Rc::new(k)), v.into_object()))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+impl FromObject for HashKey {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        HashKey::try_from(obj).map_err(|_| {
+            ConversionError::new(
+                format!(
+                    "{}, {}, {} or {}",
+                    INTEGER_OBJECT, BIG_INTEGER_OBJECT, BOOLEAN_OBJECT, STRING_OBJECT
+                ),
+                obj,
+            )
+        })
+    }
+}
+
+impl IntoObject for HashKey {
+    fn into_object(self) -> Rc<dyn Object> {
+        self.to_object()
+    }
+}
+
+/// 任意 key 的 HASH，保留插入顺序
+impl<T: FromObject> FromObject for IndexMap<HashKey, T> {
+    fn from_object(obj: &Rc<dyn Object>) -> Result<Self, ConversionError> {
+        let hash = obj
+            .as_any()
+            .downcast_ref::<HashObject>()
+            .ok_or_else(|| ConversionError::new(HASH_OBJECT, obj))?;
+        let pairs = hash.pairs.borrow();
+        let mut map = IndexMap::with_capacity(pairs.len());
+        for (k, v) in pairs.iter() {
+            let value = T::from_object(v).map_err(|e| ConversionError {
+                expected: format!("{} of {}", HASH_OBJECT, e.expected),
+                found: format!("{} containing {}", HASH_OBJECT, e.found),
+            })?;
+            map.insert(k.clone(), value);
+        }
+        Ok(map)
+    }
+}
+
+impl<T: IntoObject> IntoObject for IndexMap<HashKey, T> {
+    fn into_object(self) -> Rc<dyn Object> {
+        Rc::new(HashObject::new(
+            self.into_iter()
+                .map(|(k, v)| (k, v.into_object()))
+                .collect(),
+        ))
+    }
+}
+
+/// derive(FromObject) 生成的代码用来取结构体的字段，字段缺失时按 NULL 处理，所以 Option 字段可以省略
+pub fn object_field<T: FromObject>(obj: &Rc<dyn Object>, name: &str) -> Result<T, ConversionError> {
+    let hash = obj
+        .as_any()
+        .downcast_ref::<HashObject>()
+        .ok_or_else(|| ConversionError::new(HASH_OBJECT, obj))?;
+    let value = hash
+        .pairs
+        .borrow()
+        .get(&HashKey::String(Rc::new(name.to_string())))
+        .cloned()
+        .unwrap_or_else(|| Rc::new(Null {}));
+    T::from_object(&value).map_err(|e| ConversionError {
+        expected: format!("{} in field `{}`", e.expected, name),
+        found: e.found,
+    })
+}
+
+/// derive(IntoObject) 生成的代码用来把字段组装成 HASH
+pub fn fields_into_object(fields: Vec<(&str, Rc<dyn Object>)>) -> Rc<dyn Object> {
+    Rc::new(HashObject {
+        pairs: RefCell::new(
+            fields
+                .into_iter()
+                .map(|(k, v)| (HashKey::String(Rc::new(k.to_string())), v))
+                .collect(),
+        ),
+    })
+}
+
+/// 原生函数的参数列表，按元组的长度检查参数个数，再逐个转换
+/// 结尾的 Option 参数可以省略，个数不对是 ArgumentError，类型不对是 TypeError
+pub trait FromArgs: Sized {
+    const ARITY: usize;
+
+    fn from_args(name: &str, args: &[Rc<dyn Object>]) -> Result<Self, ErrorObject>;
+}
+
+fn check_arity(got: usize, optional: &[bool]) -> Result<(), ErrorObject> {
+    let max = optional.len();
+    let min = max - optional.iter().rev().take_while(|o| **o).count();
+    if (min..=max).contains(&got) {
+        return Ok(());
+    }
+    let want = match max - min {
+        0 => max.to_string(),
+        1 => format!("{} or {}", min, max),
+        _ => format!("{} to {}", min, max),
+    };
+    Err(ErrorObject::new(
+        ErrorKind::ArgumentError,
+        format!("wrong number of arguments. got={}, want={}", got, want),
+    ))
+}
+
+fn convert_arg<T: FromObject>(
+    name: &str,
+    arity: usize,
+    idx: usize,
+    args: &[Rc<dyn Object>],
+) -> Result<T, ErrorObject> {
+    let arg = match args.get(idx) {
+        Some(arg) => arg.clone(),
+        None => Rc::new(Null {}),
+    };
+    T::from_object(&arg).map_err(|e| {
+        let position = if arity == 1 {
+            "argument".to_string()
+        } else {
+            format!("argument[{}]", idx)
+        };
+        ErrorObject::new(
+            ErrorKind::TypeError,
+            format!(
+                "{} to `{}` must be {}, got {}",
+                position, name, e.expected, e.found
+            ),
+        )
+    })
+}
+
+macro_rules! impl_from_args {
+    ($arity:expr; $($t:ident $idx:tt),*) => {
+        impl<$($t: FromObject),*> FromArgs for ($($t,)*) {
+            const ARITY: usize = $arity;
+
+            #[allow(unused_variables)]
+            fn from_args(name: &str, args: &[Rc<dyn Object>]) -> Result<Self, ErrorObject> {
+                check_arity(args.len(), &[$($t::is_optional()),*])?;
+                Ok(($(convert_arg::<$t>(name, Self::ARITY, $idx, args)?,)*))
+            }
+        }
+    };
+}
+
+impl_from_args!(0;);
+impl_from_args!(1; A 0);
+impl_from_args!(2; A 0, B 1);
+impl_from_args!(3; A 0, B 1, C 2);
+impl_from_args!(4; A 0, B 1, C 2, D 3);
+impl_from_args!(5; A 0, B 1, C 2, D 3, E 4);
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, FromObject, IntoObject)]
+    struct Point {
+        x: i64,
+        y: i64,
+        label: Option<String>,
+    }
+
+    fn eval_str(input: &str) -> Rc<dyn Object> {
+        Interpreter::new().eval_str(input).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_from_object() {
+        assert_eq!(i64::from_object(&eval_str("1 + 2")), Ok(3));
+        assert_eq!(bool::from_object(&eval_str("1 < 2")), Ok(true));
+        assert_eq!(f64::from_object(&eval_str("2")), Ok(2.0));
+        assert_eq!(String::from_object(&eval_str("\"ab\"")), Ok("ab".into()));
+        assert_eq!(Vec::<i64>::from_object(&eval_str("[1, 2]")), Ok(vec![1, 2]));
+        assert_eq!(
+            HashMap::<String, bool>::from_object(&eval_str("{\"a\": true}")),
+            Ok([("a".to_string(), true)].into_iter().collect())
+        );
+        assert_eq!(
+            Option::<i64>::from_object(&eval_str("if (false) { 1 }")),
+            Ok(None)
+        );
+        assert_eq!(
+            Point::from_object(&eval_str("{\"x\": 1, \"y\": 2}")),
+            Ok(Point {
+                x: 1,
+                y: 2,
+                label: None
+            })
+        );
+
+        let cases: [(Result<(), ConversionError>, &str); 5] = [
+            (
+                i64::from_object(&eval_str("true")).map(|_| ()),
+                "expected INTEGER, got BOOLEAN",
+            ),
+            (
+                Vec::<i64>::from_object(&eval_str("[1, \"a\"]")).map(|_| ()),
+                "expected ARRAY_OBJECT of INTEGER, got ARRAY_OBJECT containing STRING_OBJECT",
+            ),
+            (
+                HashMap::<String, i64>::from_object(&eval_str("{1: 1}")).map(|_| ()),
+                "expected HASH_OBJECT with STRING_OBJECT keys, got HASH_OBJECT with INTEGER key",
+            ),
+            (
+                Option::<bool>::from_object(&eval_str("1")).map(|_| ()),
+                "expected BOOLEAN or NULL, got INTEGER",
+            ),
+            (
+                Point::from_object(&eval_str("{\"x\": 1}")).map(|_| ()),
+                "expected INTEGER in field `y`, got NULL",
+            ),
+        ];
+        cases.iter().for_each(|(r, msg)| {
+            assert_eq!(r.as_ref().unwrap_err().to_string(), *msg);
+        });
+    }
+
+    #[test]
+    fn test_into_object() {
+        let cases: [(Rc<dyn Object>, &str); 6] = [
+            (42.into_object(), "42"),
+            (false.into_object(), "false"),
+            ("hi".into_object(), "hi"),
+            (Some(vec![1, 2]).into_object(), "[12]"),
+            (None::<i64>.into_object(), "null"),
+            (
+                Err::<i64, _>("boom".to_string()).into_object(),
+                "Error: boom",
+            ),
+        ];
+        cases.iter().for_each(|(obj, out)| {
+            assert_eq!(obj.inspect(), *out);
+        });
+
+        let p = Point {
+            x: 1,
+            y: 2,
+            label: Some("p".into()),
+        };
+        let obj = Point {
+            x: 1,
+            y: 2,
+            label: Some("p".into()),
+        }
+        .into_object();
+        assert_eq!(obj.object_type(), HASH_OBJECT);
+        assert_eq!(Point::from_object(&obj), Ok(p));
+    }
+
+    #[test]
+    fn test_from_args() {
+        fn check<T: FromArgs + PartialEq + std::fmt::Debug>(
+            r: Result<T, ErrorObject>,
+            expected: Result<T, &str>,
+        ) {
+            assert_eq!(r.map_err(|e| e.inspect()), expected.map_err(String::from));
+        }
+        let args = vec![1.into_object(), "a".into_object()];
+        check(
+            <(i64, String)>::from_args("f", &args),
+            Ok((1, "a".to_string())),
+        );
+        check(
+            <(i64,)>::from_args("f", &args),
+            Err("ArgumentError: wrong number of arguments. got=2, want=1"),
+        );
+        check(
+            <(i64, i64)>::from_args("f", &args),
+            Err("TypeError: argument[1] to `f` must be INTEGER, got STRING_OBJECT"),
+        );
+        check(
+            <(bool,)>::from_args("f", &args[..1]),
+            Err("TypeError: argument to `f` must be BOOLEAN, got INTEGER"),
+        );
+        check(
+            <(i64, Option<String>, Option<i64>)>::from_args("f", &args),
+            Ok((1, Some("a".to_string()), None)),
+        );
+        check(
+            <(i64, Option<String>)>::from_args("f", &args[..1]),
+            Ok((1, None)),
+        );
+        check(
+            <(i64, Option<String>)>::from_args("f", &[]),
+            Err("ArgumentError: wrong number of arguments. got=0, want=1 or 2"),
+        );
+        check(
+            <(Option<i64>, Option<i64>, i64)>::from_args("f", &[]),
+            Err("ArgumentError: wrong number of arguments. got=0, want=3"),
+        );
+    }
+}
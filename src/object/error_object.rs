@@ -1,16 +1,177 @@
 // use ast_macro::object;
 
+use crate::diagnostic::render_diagnostic;
 pub use crate::object::*;
+use crate::token::Span;
 use ast_macro::object;
+use std::cell::RefCell;
+
+/// 错误的种类，脚本里 catch 到的错误的 kind 就是这里的名字
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    // 没有指定种类，比如 throw 一个字符串
+    Error,
+    // 运算、调用或者参数的类型不对
+    TypeError,
+    // 变量不存在
+    NameError,
+    // 参数个数不对
+    ArgumentError,
+    // 类型对但是值不合法，比如负数的指数
+    ValueError,
+    // 整数除以 0 或者对 0 取余
+    ZeroDivisionError,
+    // 整数运算的结果超出了 64 位
+    OverflowError,
+    // 脚本 throw 的 hash 里自己写的种类
+    Custom(String),
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &str {
+        match self {
+            ErrorKind::Error => "Error",
+            ErrorKind::TypeError => "TypeError",
+            ErrorKind::NameError => "NameError",
+            ErrorKind::ArgumentError => "ArgumentError",
+            ErrorKind::ValueError => "ValueError",
+            ErrorKind::ZeroDivisionError => "ZeroDivisionError",
+            ErrorKind::OverflowError => "OverflowError",
+            ErrorKind::Custom(name) => name,
+        }
+    }
+}
+
+impl From<&str> for ErrorKind {
+    fn from(name: &str) -> Self {
+        match name {
+            "Error" => ErrorKind::Error,
+            "TypeError" => ErrorKind::TypeError,
+            "NameError" => ErrorKind::NameError,
+            "ArgumentError" => ErrorKind::ArgumentError,
+            "ValueError" => ErrorKind::ValueError,
+            "ZeroDivisionError" => ErrorKind::ZeroDivisionError,
+            "OverflowError" => ErrorKind::OverflowError,
+            _ => ErrorKind::Custom(name.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// 调用栈上的一帧：被调用的函数和调用的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    pub function: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {} ({})", self.function, self.span)
+    }
+}
 
 #[object(ERROR_OBJECT)]
 pub struct ErrorObject {
+    pub kind: ErrorKind,
     pub message: String,
+    // 出错的位置，由 eval 在错误向外传递时补上
+    pub span: Option<Span>,
+    // 错误每穿过一次函数调用就记一帧，最里层的在最前面
+    pub stack: Vec<StackFrame>,
 }
 
 impl ObjectInspect for ErrorObject {
     fn _inspect(&self) -> String {
-        format!("Error: {}", self.message)
+        format!("{}: {}", self.kind, self.message)
+    }
+}
+
+impl ErrorObject {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        ErrorObject {
+            kind,
+            message: message.into(),
+            span: None,
+            stack: vec![],
+        }
+    }
+
+    /// 带上出错位置的源码片段，没有位置时只有错误信息，后面跟着调用栈
+    pub fn render(&self, source: &str) -> String {
+        let mut out = render_diagnostic(source, self.span.unwrap_or_default(), &self.message);
+        self.stack
+            .iter()
+            .for_each(|frame| out.push_str(&format!("  {}\n", frame)));
+        out
+    }
+
+    /// catch 到的错误在脚本里是一个 hash：
+    /// `{"kind": .., "message": .., "line": .., "column": .., "stack": [..]}`，没有位置时 line 和 column 是 null
+    pub fn to_hash(&self) -> Rc<dyn Object> {
+        let string = |s: &str| -> Rc<dyn Object> {
+            Rc::new(StringObject {
+                value: Rc::new(s.to_string()),
+            })
+        };
+        let position = |n: Option<usize>| -> Rc<dyn Object> {
+            match n {
+                Some(n) => Rc::new(Integer { value: n as i64 }),
+                None => Rc::new(Null {}),
+            }
+        };
+        let stack = self
+            .stack
+            .iter()
+            .map(|frame| string(&frame.to_string()))
+            .collect::<Vec<_>>();
+        let pairs = [
+            ("kind", string(self.kind.name())),
+            ("message", string(&self.message)),
+            ("line", position(self.span.map(|s| s.line))),
+            ("column", position(self.span.map(|s| s.column))),
+            (
+                "stack",
+                Rc::new(ArrayObject {
+                    elements: RefCell::new(stack),
+                }),
+            ),
+        ];
+        Rc::new(HashObject::new(
+            pairs
+                .into_iter()
+                .map(|(k, v)| (HashKey::String(Rc::new(k.to_string())), v))
+                .collect(),
+        ))
+    }
+
+    /// throw 的值转成错误：
+    /// ErrorObject 原样抛出；hash 取其中的 kind 和 message，所以 catch 到的错误可以再 throw 出去，
+    /// 调用栈从新的 throw 处重新记录；其他值的 inspect 作为 message
+    pub fn from_thrown(value: &Rc<dyn Object>) -> ErrorObject {
+        if let Some(err) = value.as_any().downcast_ref::<ErrorObject>() {
+            return err.clone();
+        }
+        let Some(hash) = value.as_any().downcast_ref::<HashObject>() else {
+            return ErrorObject::new(ErrorKind::Error, value.inspect());
+        };
+        let field = |name: &str| hash.get(&HashKey::String(Rc::new(name.to_string())));
+        let text = |name: &str| {
+            field(name).and_then(|v| {
+                v.as_any()
+                    .downcast_ref::<StringObject>()
+                    .map(|s| s.value.to_string())
+            })
+        };
+        ErrorObject::new(
+            text("kind").map_or(ErrorKind::Error, |k| ErrorKind::from(k.as_str())),
+            text("message").unwrap_or_else(|| value.inspect()),
+        )
     }
 }
 
@@ -24,6 +185,6 @@ impl TryFrom<Rc<dyn Object>> for ErrorObject {
                 return Ok((*v).clone());
             }
         }
-        Err("Str".into())
+        Err(ConversionError::new(ERROR_OBJECT, &value).to_string())
     }
 }
@@ -0,0 +1,29 @@
+pub use crate::object::*;
+use ast_macro::object;
+pub use std::rc::Rc;
+
+#[object(FLOAT_OBJECT)]
+pub struct Float {
+    pub value: f64,
+}
+
+impl ObjectInspect for Float {
+    fn _inspect(&self) -> String {
+        // {:?} 会保留 .0，可以和整数区分开
+        format!("{:?}", self.value)
+    }
+}
+
+impl TryFrom<Rc<dyn Object>> for Float {
+    type Error = String;
+
+    fn try_from(value: Rc<dyn Object>) -> Result<Self, Self::Error> {
+        let val = value.as_any();
+        if val.is::<Float>() {
+            if let Some(v) = val.downcast_ref::<Float>() {
+                return Ok((*v).clone());
+            }
+        }
+        Err(ConversionError::new(FLOAT_OBJECT, &value).to_string())
+    }
+}
@@ -6,6 +6,8 @@ use std::rc::Rc;
 #[object(FUNCTION_OBJECT)]
 pub struct FunctionObject {
     pub context: Rc<Context>,
+    // fn f() {} 或者 let f = fn() {} 里的 f，匿名函数没有名字
+    pub name: Option<String>,
 
     // TODO change to RC
     pub parameters: Option<Vec<Rc<Identifier>>>,
@@ -16,21 +18,32 @@ pub struct FunctionObject {
 
 impl ObjectInspect for FunctionObject {
     fn _inspect(&self) -> String {
-        format!("fn ({}) {{ {} }}", 1, 2)
+        inspect_function(
+            self.parameters.as_deref().unwrap_or_default(),
+            self.body.as_deref(),
+        )
     }
 }
 
+/// 函数 inspect 出来是参数和函数体，VM 编译函数时也用它，两个后端打印出来一样
+pub fn inspect_function(parameters: &[Rc<Identifier>], body: Option<&dyn Statement>) -> String {
+    let parameters = parameters
+        .iter()
+        .map(|p| p.value.clone())
+        .collect::<Vec<String>>()
+        .join(", ");
+    let body = body.map_or_else(String::new, |b| b.to_string());
+    format!("fn({}) {}", parameters, body)
+}
+
 impl TryFrom<Rc<dyn Object>> for FunctionObject {
     type Error = String;
 
-    fn try_from(_value: Rc<dyn Object>) -> Result<Self, Self::Error> {
-        // let val = value.as_any();
-        // if val.is::<Integer>() {
-        //     if let Some(v) = val.downcast_ref::<Integer>() {
-        //         return Ok((*v).clone());
-        //     }
-        // }
-        Err("Str".into())
+    fn try_from(value: Rc<dyn Object>) -> Result<Self, Self::Error> {
+        match value.as_any().downcast_ref::<FunctionObject>() {
+            Some(v) => Ok(v.clone()),
+            None => Err(ConversionError::new(FUNCTION_OBJECT, &value).to_string()),
+        }
     }
 }
 
@@ -41,7 +54,7 @@ mod test {
     fn test_function() {
         let input = r#"fn() {}"#;
 
-        let tests = vec![
+        let tests = [
             (FUNCTION, "fn"),
             (LPAREN, "("),
             (RPAREN, ")"),
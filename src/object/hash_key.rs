@@ -0,0 +1,113 @@
+use crate::object::*;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::rc::Rc;
+
+/// 能作为 hash key 的值：整数、布尔和字符串
+/// 直接按值比较和哈希，取出来的时候也能还原成原来的对象
+/// 在 i64 范围内的 BigInteger 和相等的 Integer 是同一个 key，超出范围的才是 BigInteger
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HashKey {
+    Integer(i64),
+    BigInteger(Rc<BigInt>),
+    Boolean(bool),
+    String(Rc<String>),
+}
+
+impl HashKey {
+    pub fn to_object(&self) -> Rc<dyn Object> {
+        match self {
+            HashKey::Integer(value) => Rc::new(Integer { value: *value }),
+            HashKey::BigInteger(value) => Rc::new(BigInteger {
+                value: (**value).clone(),
+            }),
+            HashKey::Boolean(value) => Rc::new(Boolean { value: *value }),
+            HashKey::String(value) => Rc::new(StringObject {
+                value: value.clone(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<&Rc<dyn Object>> for HashKey {
+    type Error = String;
+
+    fn try_from(value: &Rc<dyn Object>) -> Result<Self, Self::Error> {
+        let val = value.as_any();
+        if let Some(v) = val.downcast_ref::<Integer>() {
+            return Ok(HashKey::Integer(v.value));
+        }
+        if let Some(v) = val.downcast_ref::<BigInteger>() {
+            return Ok(match v.value.to_i64() {
+                Some(value) => HashKey::Integer(value),
+                None => HashKey::BigInteger(Rc::new(v.value.clone())),
+            });
+        }
+        if let Some(v) = val.downcast_ref::<Boolean>() {
+            return Ok(HashKey::Boolean(v.value));
+        }
+        if let Some(v) = val.downcast_ref::<StringObject>() {
+            return Ok(HashKey::String(v.value.clone()));
+        }
+        Err(format!("unusable as hash key: {}", value.object_type()))
+    }
+}
+
+impl std::fmt::Display for HashKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashKey::Integer(value) => write!(f, "{}", value),
+            HashKey::BigInteger(value) => write!(f, "{}", value),
+            HashKey::Boolean(value) => write!(f, "{}", value),
+            HashKey::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn string(s: &str) -> Rc<dyn Object> {
+        Rc::new(StringObject {
+            value: Rc::new(s.into()),
+        })
+    }
+
+    #[test]
+    fn test_hash_key() {
+        let one: Rc<dyn Object> = Rc::new(Integer { value: 1 });
+        let yes: Rc<dyn Object> = Rc::new(Boolean { value: true });
+        let big = |s: &str| -> Rc<dyn Object> {
+            Rc::new(BigInteger {
+                value: s.parse().unwrap(),
+            })
+        };
+        let cases = [
+            (
+                one.clone(),
+                Rc::new(Integer { value: 1 }) as Rc<dyn Object>,
+                true,
+            ),
+            (yes.clone(), Rc::new(Boolean { value: true }), true),
+            (string("1"), string("1"), true),
+            (one.clone(), string("1"), false),
+            (one.clone(), big("1"), true),
+            (big("1"), one.clone(), true),
+            (big("9223372036854775808"), big("9223372036854775808"), true),
+            (big("9223372036854775808"), one.clone(), false),
+            (one, yes, false),
+        ];
+        cases.iter().for_each(|(a, b, same)| {
+            let (a, b) = (HashKey::try_from(a).unwrap(), HashKey::try_from(b).unwrap());
+            assert_eq!(a == b, *same);
+            assert_eq!(a.to_object().inspect(), a.to_string());
+        });
+
+        let f: Rc<dyn Object> = Rc::new(Float { value: 1.5 });
+        assert_eq!(
+            HashKey::try_from(&f),
+            Err("unusable as hash key: FLOAT".to_string())
+        );
+    }
+}
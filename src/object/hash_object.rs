@@ -1,13 +1,15 @@
 use crate::object::*;
-use crate::*;
 use ast_macro::object;
+use indexmap::IndexMap;
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::rc::Rc;
 
+/// 按插入顺序保存的键值对，遍历和 inspect 的顺序都是确定的
+pub type HashPairs = IndexMap<HashKey, Rc<dyn Object>>;
+
 #[object(HASH_OBJECT)]
 pub struct HashObject {
-    pub pairs: RefCell<HashMap<Rc<String>, Rc<dyn Object>>>,
+    pub pairs: RefCell<HashPairs>,
 }
 
 impl ObjectInspect for HashObject {
@@ -25,9 +27,53 @@ impl ObjectInspect for HashObject {
 }
 
 impl HashObject {
-    pub fn insert() {}
-    pub fn get() {}
+    pub fn new(pairs: HashPairs) -> Self {
+        HashObject {
+            pairs: RefCell::new(pairs),
+        }
+    }
+
+    pub fn get(&self, key: &HashKey) -> Option<Rc<dyn Object>> {
+        self.pairs.borrow().get(key).cloned()
+    }
+
+    /// 已有的 key 保持原来的位置，只替换 value
+    pub fn insert(&self, key: HashKey, value: Rc<dyn Object>) -> Option<Rc<dyn Object>> {
+        self.pairs.borrow_mut().insert(key, value)
+    }
+
+    /// 删除后其他 key 的相对顺序不变
+    pub fn remove(&self, key: &HashKey) -> Option<Rc<dyn Object>> {
+        self.pairs.borrow_mut().shift_remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.borrow().is_empty()
+    }
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_hash_object_order() {
+        let key = |s: &str| HashKey::String(Rc::new(s.to_string()));
+        let h = HashObject::new(HashPairs::new());
+        ["b", "a", "c"].iter().enumerate().for_each(|(i, k)| {
+            h.insert(key(k), Rc::new(Integer { value: i as i64 }));
+        });
+        assert_eq!(h.inspect(), "{ b:0,a:1,c:2 }");
+
+        h.insert(key("b"), Rc::new(Integer { value: 9 }));
+        assert_eq!(h.remove(&key("a")).unwrap().inspect(), "1");
+        assert!(h.remove(&key("a")).is_none());
+        assert_eq!(h.inspect(), "{ b:9,c:2 }");
+        assert_eq!(h.get(&key("c")).unwrap().inspect(), "2");
+        assert_eq!(h.len(), 2);
+    }
+}
@@ -22,6 +22,6 @@ impl TryFrom<Rc<dyn Object>> for Integer {
                 return Ok((*v).clone());
             }
         }
-        Err("Str".into())
+        Err(ConversionError::new(INTEGER_OBJECT, &value).to_string())
     }
 }
@@ -0,0 +1,26 @@
+use crate::object::*;
+use ast_macro::object;
+use std::cell::Cell;
+
+/// vm 执行 for 循环时放在栈上的迭代器，创建时就拿到了所有元素的快照
+#[object(ITERATOR_OBJECT)]
+pub struct IteratorObject {
+    pub items: Vec<Rc<dyn Object>>,
+    pub index: Cell<usize>,
+}
+
+impl IteratorObject {
+    pub fn next(&self) -> Option<Rc<dyn Object>> {
+        let item = self.items.get(self.index.get()).cloned();
+        if item.is_some() {
+            self.index.set(self.index.get() + 1);
+        }
+        item
+    }
+}
+
+impl ObjectInspect for IteratorObject {
+    fn _inspect(&self) -> String {
+        "<iterator>".to_string()
+    }
+}
@@ -0,0 +1,21 @@
+use crate::object::*;
+use ast_macro::object;
+
+/// break 和 continue 执行后产生的信号，和 ReturnValue 一样一路冒泡到最近的循环
+#[object(BREAK_OBJECT)]
+pub struct BreakValue {}
+
+impl ObjectInspect for BreakValue {
+    fn _inspect(&self) -> String {
+        "break".to_string()
+    }
+}
+
+#[object(CONTINUE_OBJECT)]
+pub struct ContinueValue {}
+
+impl ObjectInspect for ContinueValue {
+    fn _inspect(&self) -> String {
+        "continue".to_string()
+    }
+}
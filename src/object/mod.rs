@@ -1,25 +1,43 @@
 use std::{any::Any, fmt::Debug};
 pub mod array_object;
+pub mod big_integer;
 pub mod boolean;
 pub mod builtin;
+pub mod closure_object;
+pub mod compiled_function;
 pub mod context;
+pub mod convert;
 pub mod error_object;
+pub mod float;
 pub mod function_object;
+pub mod hash_key;
 pub mod hash_object;
 pub mod integer;
+pub mod iterator_object;
+pub mod loop_control;
 pub mod null;
+pub mod rational;
 pub mod return_value;
 pub mod string_object;
 
 pub use array_object::*;
+pub use big_integer::*;
 pub use boolean::*;
 pub use builtin::*;
+pub use closure_object::*;
+pub use compiled_function::*;
 pub use context::*;
+pub use convert::*;
 pub use error_object::*;
+pub use float::*;
 pub use function_object::*;
+pub use hash_key::*;
 pub use hash_object::*;
 pub use integer::*;
+pub use iterator_object::*;
+pub use loop_control::*;
 pub use null::*;
+pub use rational::*;
 pub use return_value::*;
 pub use string_object::*;
 pub type ObjectType = &'static str;
@@ -47,6 +65,9 @@ pub trait Object: Debug + std::fmt::Display + ObjectInspect + ObjectWithoutInspe
 
 pub const BOOLEAN_OBJECT: &str = "BOOLEAN";
 pub const INTEGER_OBJECT: &str = "INTEGER";
+pub const BIG_INTEGER_OBJECT: &str = "BIG_INTEGER";
+pub const FLOAT_OBJECT: &str = "FLOAT";
+pub const RATIONAL_OBJECT: &str = "RATIONAL";
 pub const NULL_OBJECT: &str = "NULL";
 pub const RETURN_VALUE_OBJECT: &str = "RETURN_VALUE";
 pub const ERROR_OBJECT: &str = "ERROR_OBJECT";
@@ -55,3 +76,7 @@ pub const STRING_OBJECT: &str = "STRING_OBJECT";
 pub const BUILTIN_OBJECT: &str = "BUILTIN";
 pub const ARRAY_OBJECT: &str = "ARRAY_OBJECT";
 pub const HASH_OBJECT: &str = "HASH_OBJECT";
+pub const COMPILED_FUNCTION_OBJECT: &str = "COMPILED_FUNCTION_OBJECT";
+pub const BREAK_OBJECT: &str = "BREAK";
+pub const CONTINUE_OBJECT: &str = "CONTINUE";
+pub const ITERATOR_OBJECT: &str = "ITERATOR";
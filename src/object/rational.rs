@@ -0,0 +1,33 @@
+pub use crate::object::*;
+use ast_macro::object;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+pub use std::rc::Rc;
+
+/// 精确的分数，总是约分过的，分母为正
+/// 用 rational() 构造，或者开启精确除法时整数除不尽的结果
+#[object(RATIONAL_OBJECT)]
+pub struct Rational {
+    pub value: BigRational,
+}
+
+impl ObjectInspect for Rational {
+    fn _inspect(&self) -> String {
+        // 分母是 1 的时候只显示分子
+        if *self.value.denom() == BigInt::from(1) {
+            return self.value.numer().to_string();
+        }
+        format!("{}/{}", self.value.numer(), self.value.denom())
+    }
+}
+
+impl TryFrom<Rc<dyn Object>> for Rational {
+    type Error = String;
+
+    fn try_from(value: Rc<dyn Object>) -> Result<Self, Self::Error> {
+        match value.as_any().downcast_ref::<Rational>() {
+            Some(v) => Ok(v.clone()),
+            None => Err(ConversionError::new(RATIONAL_OBJECT, &value).to_string()),
+        }
+    }
+}
@@ -23,6 +23,6 @@ impl TryFrom<Rc<dyn Object>> for ReturnValue {
                 return Ok((*v).clone());
             }
         }
-        Err("Str".into())
+        Err(ConversionError::new(RETURN_VALUE_OBJECT, &value).to_string())
     }
 }
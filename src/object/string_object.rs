@@ -23,6 +23,6 @@ impl TryFrom<Rc<dyn Object>> for StringObject {
                 return Ok((*v).clone());
             }
         }
-        Err("Str".into())
+        Err(ConversionError::new(STRING_OBJECT, &value).to_string())
     }
 }
@@ -1,5 +1,9 @@
+#[allow(clippy::module_inception)]
 pub mod parser;
 pub use parser::*;
+pub mod parse_error;
+pub use parse_error::*;
 pub mod parser_fn;
 pub use parser_fn::*;
+#[allow(clippy::module_inception)]
 mod test;
@@ -0,0 +1,56 @@
+use crate::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// 下一个 token 不是语法要求的那个
+    UnexpectedToken,
+    /// 这里应该是一个表达式的开头
+    MissingExpression,
+    /// 字面量写法没问题但是值不合法，比如整数溢出
+    InvalidLiteral,
+    /// 赋值号左边既不是变量也不是下标
+    InvalidAssignmentTarget,
+    /// 字符串一直到结尾都没有引号
+    UnterminatedString,
+    /// 字符串里有不认识的转义序列
+    InvalidEscape,
+    /// 块注释一直到结尾都没有 */
+    UnterminatedComment,
+}
+
+/// 解析错误：期望的是什么，实际遇到的是什么，以及出错的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub expected: String,
+    pub found: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ParseErrorKind::UnexpectedToken => write!(
+                f,
+                "expect next token to be {}, got {} instead",
+                self.expected, self.found
+            ),
+            ParseErrorKind::MissingExpression => {
+                write!(f, "expect expression, got {} instead", self.found)
+            }
+            ParseErrorKind::InvalidLiteral => {
+                write!(f, "invalid {} literal: {}", self.expected, self.found)
+            }
+            ParseErrorKind::InvalidAssignmentTarget => {
+                write!(f, "invalid assignment target: {}", self.found)
+            }
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            ParseErrorKind::InvalidEscape => {
+                write!(f, "invalid escape sequence in string: {}", self.found)
+            }
+            ParseErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -1,7 +1,9 @@
 use crate::ast::*;
+use crate::diagnostic::*;
 use crate::lexer::*;
 use crate::parser::*;
 use crate::token::*;
+use crate::tracer::*;
 
 use crate::ExpressionConst::LOWEST;
 use std::cell::RefCell;
@@ -16,20 +18,26 @@ use std::rc::Rc;
 pub enum ExpressionConst {
     LOWEST = 1,
     // what is this?
+    ASSIGN,
+    // = or += -= *= /=
+    NULLISH,
+    // ??
+    LOGICOR,
+    // ||
+    LOGICAND,
+    // &&
     EQUALS,
     // =
     LESSGREATER,
     // > or <
+    BITOP,
+    // ^ or | or &
     SUM,
     // +
     PRODUCT,
     // "*
     PREFIX,
     // -X or !X
-    BITOP,
-    // ^ or | or &
-    LOGICOP,
-    // && or ||
     POW,
     // ^^
     CALL,  // function
@@ -40,16 +48,19 @@ impl From<isize> for ExpressionConst {
     fn from(value: isize) -> Self {
         match value {
             1 => ExpressionConst::LOWEST,      // what is this?
-            2 => ExpressionConst::EQUALS,      // =
-            3 => ExpressionConst::LESSGREATER, // > or <
-            4 => ExpressionConst::SUM,         // +
-            5 => ExpressionConst::PRODUCT,     // "*
-            6 => ExpressionConst::PREFIX,      // -X or !X
-            7 => ExpressionConst::BITOP,       // ^ or | or &
-            8 => ExpressionConst::LOGICOP,     // && or ||
-            9 => ExpressionConst::POW,         // ^^
-            10 => ExpressionConst::CALL,       // function
-            11 => ExpressionConst::INDEX,      // a[1]
+            2 => ExpressionConst::ASSIGN,      // = or += -= *= /=
+            3 => ExpressionConst::NULLISH,     // ??
+            4 => ExpressionConst::LOGICOR,     // ||
+            5 => ExpressionConst::LOGICAND,    // &&
+            6 => ExpressionConst::EQUALS,      // =
+            7 => ExpressionConst::LESSGREATER, // > or <
+            8 => ExpressionConst::BITOP,       // ^ or | or &
+            9 => ExpressionConst::SUM,         // +
+            10 => ExpressionConst::PRODUCT,    // "*
+            11 => ExpressionConst::PREFIX,     // -X or !X
+            12 => ExpressionConst::POW,        // ^^
+            13 => ExpressionConst::CALL,       // function
+            14 => ExpressionConst::INDEX,      // a[1]
             _ => ExpressionConst::LOWEST,
         }
     }
@@ -63,22 +74,34 @@ thread_local! {
         (NOT_EQ, ExpressionConst::EQUALS),
         (LT, ExpressionConst::LESSGREATER),
         (GT, ExpressionConst::LESSGREATER),
+        (LT_EQ, ExpressionConst::LESSGREATER),
+        (GT_EQ, ExpressionConst::LESSGREATER),
         (PLUS, ExpressionConst::SUM),
         (MINUS, ExpressionConst::SUM),
         (SLASH, ExpressionConst::PRODUCT),
+        (PERCENT, ExpressionConst::PRODUCT),
 
         (BITAND, ExpressionConst::BITOP),
         (BITOR, ExpressionConst::BITOP),
         (BITXOR, ExpressionConst::BITOP),
 
-        (LOGICAND, ExpressionConst::LOGICOP),
-        (LOGICOR, ExpressionConst::LOGICOP),
+        (LOGICAND, ExpressionConst::LOGICAND),
+        (LOGICOR, ExpressionConst::LOGICOR),
+        (NULLISH, ExpressionConst::NULLISH),
 
         (POW, ExpressionConst::POW),
 
+        (ASSIGN, ExpressionConst::ASSIGN),
+        (PLUS_ASSIGN, ExpressionConst::ASSIGN),
+        (MINUS_ASSIGN, ExpressionConst::ASSIGN),
+        (ASTERISK_ASSIGN, ExpressionConst::ASSIGN),
+        (SLASH_ASSIGN, ExpressionConst::ASSIGN),
+
         (ASTERISK, ExpressionConst::PRODUCT),
         (LPAREN, ExpressionConst::CALL),
         (LBRACKET, ExpressionConst::INDEX),
+        (OPTIONAL_LBRACKET, ExpressionConst::INDEX),
+        (OPTIONAL_DOT, ExpressionConst::INDEX),
     ]);
 }
 
@@ -88,6 +111,9 @@ pub struct Parser {
     cur_token: Rc<RefCell<Token>>,
     peek_token: Rc<RefCell<Token>>,
     errors: Rc<RefCell<Vec<String>>>,
+    // 和 errors 一一对应，带上期望的 token 和出错的位置
+    parse_errors: RefCell<Vec<ParseError>>,
+    tracer: RefCell<Option<Rc<dyn Tracer>>>,
 
     prefix_parse_fns: Rc<RefCell<HashMap<TokenType, Rc<PrefixParseFn>>>>,
     infix_parse_fns: Rc<RefCell<HashMap<TokenType, Rc<InfixParseFn>>>>,
@@ -116,6 +142,8 @@ impl Parser {
             cur_token: Rc::new(RefCell::new(Token::default())),
             peek_token: Rc::new(RefCell::new(Token::default())),
             errors: Rc::new(RefCell::new(vec![])),
+            parse_errors: RefCell::new(vec![]),
+            tracer: RefCell::new(None),
             prefix_parse_fns: Rc::new(RefCell::new(HashMap::new())),
             infix_parse_fns: Rc::new(RefCell::new(HashMap::new())),
         };
@@ -124,11 +152,19 @@ impl Parser {
         pc.register_prefix(IDENT, Rc::new(move || pd.parse_identifier()));
         let pd = pc.clone();
         pc.register_prefix(INT, Rc::new(move || pd.parse_integer_literal()));
+        let pd = pc.clone();
+        pc.register_prefix(FLOAT, Rc::new(move || pd.parse_float_literal()));
+        let pd = pc.clone();
+        pc.register_prefix(BIGINT, Rc::new(move || pd.parse_big_integer_literal()));
 
         let pd = pc.clone();
         pc.register_prefix(BANG, Rc::new(move || pd.parse_prefix_expression()));
         let pd = pc.clone();
         pc.register_prefix(MINUS, Rc::new(move || pd.parse_prefix_expression()));
+        let pd = pc.clone();
+        pc.register_prefix(PLUS, Rc::new(move || pd.parse_prefix_expression()));
+        let pd = pc.clone();
+        pc.register_prefix(TILDE, Rc::new(move || pd.parse_prefix_expression()));
 
         let pd = pc.clone();
         pc.register_prefix(BITAND, Rc::new(move || pd.parse_prefix_expression()));
@@ -145,11 +181,26 @@ impl Parser {
         let pd = pc.clone();
         pc.register_prefix(FALSE, Rc::new(move || pd.parse_boolean()));
         let pd = pc.clone();
+        pc.register_prefix(NULL, Rc::new(move || pd.parse_null_literal()));
+        let pd = pc.clone();
         pc.register_prefix(LPAREN, Rc::new(move || pd.parse_grouped_expression()));
         let pd = pc.clone();
         pc.register_prefix(IF, Rc::new(move || pd.parse_if_expression()));
         let pd = pc.clone();
         pc.register_prefix(STRING, Rc::new(move || pd.parse_string_literal()));
+        let pd = pc.clone();
+        pc.register_prefix(TEMPLATE, Rc::new(move || pd.parse_template_literal()));
+        let pd = pc.clone();
+        pc.register_prefix(UNTERMINATED_STRING, Rc::new(move || pd.parse_error_token()));
+        let pd = pc.clone();
+        pc.register_prefix(INVALID_ESCAPE, Rc::new(move || pd.parse_error_token()));
+        let pd = pc.clone();
+        pc.register_prefix(INVALID_NUMBER, Rc::new(move || pd.parse_error_token()));
+        let pd = pc.clone();
+        pc.register_prefix(
+            UNTERMINATED_COMMENT,
+            Rc::new(move || pd.parse_error_token()),
+        );
 
         let pd = pc.clone();
         pc.register_prefix(LBRACKET, Rc::new(move || pd.parse_array_literal()));
@@ -161,21 +212,34 @@ impl Parser {
 
         let pd = pc.clone();
         pc.register_infix(LBRACKET, Rc::new(move |val| pd.parse_index_expression(val)));
+        let pd = pc.clone();
+        pc.register_infix(
+            OPTIONAL_LBRACKET,
+            Rc::new(move |val| pd.parse_index_expression(val)),
+        );
+        let pd = pc.clone();
+        pc.register_infix(
+            OPTIONAL_DOT,
+            Rc::new(move |val| pd.parse_optional_dot_expression(val)),
+        );
 
         // let pd = pc.clone();
         // pc.register_prefix(IF, Rc::new(move || pd.parse_block_statement()));
 
         PRECEDENCES.with(|ps| {
             // println!("before register infix_parse: {:?}", ps);
-            #[allow(unused_variables)]
             ps.iter().for_each(|(&token, ec)| {
                 if token == LPAREN {
                     return;
                 }
-                if token == LBRACKET {
+                if token == LBRACKET || token == OPTIONAL_LBRACKET || token == OPTIONAL_DOT {
                     return;
                 }
                 let pd = pc.clone();
+                if *ec == ExpressionConst::ASSIGN {
+                    pc.register_infix(token, Rc::new(move |left| pd.parse_assign_expression(left)));
+                    return;
+                }
                 // println!("register infix parser for {:?}", token);
                 pc.register_infix(token, Rc::new(move |left| pd.parse_infix_expression(left)));
             });
@@ -185,7 +249,30 @@ impl Parser {
         pc.next_token();
         pc
     }
+    pub fn set_tracer(&self, tracer: Rc<dyn Tracer>) {
+        self.tracer.replace(Some(tracer));
+    }
+    fn trace(&self, event: TraceEvent) {
+        if let Some(t) = self.tracer.borrow().as_ref() {
+            t.on_event(&event);
+        }
+    }
+    /// 没有注册 tracer 时不用复制 token
+    fn pending_node(&self) -> Option<PendingNode> {
+        self.tracer.borrow().as_ref()?;
+        let pending = PendingNode {
+            token: self.cur_token.borrow().clone(),
+        };
+        self.trace(TraceEvent::NodeEntered(&pending));
+        Some(pending)
+    }
+    fn trace_exited(&self, pending: Option<PendingNode>, node: Option<&dyn Node>) {
+        if let Some(pending) = pending {
+            self.trace(TraceEvent::NodeExited(node.unwrap_or(&pending)));
+        }
+    }
     pub fn next_token(&self) {
+        self.trace(TraceEvent::TokenConsumed(&self.cur_token.borrow()));
         self.cur_token.replace(self.peek_token.take());
         self.peek_token.replace(self.l.next_token());
     }
@@ -198,10 +285,9 @@ impl Parser {
         let mut ctk_type = self.cur_token.borrow().token_type;
         while ctk_type != EOF {
             let stmt = self.parse_statement();
-            if stmt.is_some() {
-                let stmt = stmt.unwrap();
-                println!("get push: {:?}", stmt);
-                stm.push(stmt);
+            match stmt {
+                Some(stmt) => stm.push(stmt),
+                None => self.synchronize(),
             }
             self.next_token();
             ctk_type = self.cur_token.borrow().token_type;
@@ -211,13 +297,43 @@ impl Parser {
         Some(program)
     }
     pub fn parse_statement(&self) -> Option<Rc<dyn Statement>> {
+        let pending = self.pending_node();
+        let stmt = self.parse_statement_node();
+        self.trace_exited(pending, stmt.as_ref().map(|s| s.upcast()));
+        stmt
+    }
+    fn parse_statement_node(&self) -> Option<Rc<dyn Statement>> {
         let cur_type = self.cur_token.borrow().token_type;
         match cur_type {
             LET => self.parse_let_statement(),
             RETURN => self.parse_return_statement(),
+            WHILE => self.parse_while_statement(),
+            FOR => self.parse_for_statement(),
+            TRY => self.parse_try_statement(),
+            THROW => self.parse_throw_statement(),
+            BREAK => Some(Rc::new(BreakStatement {
+                token: self.parse_keyword_statement(),
+            }) as Rc<dyn Statement>),
+            CONTINUE => Some(Rc::new(ContinueStatement {
+                token: self.parse_keyword_statement(),
+            }) as Rc<dyn Statement>),
             _ => self.parse_expression_statement(),
         }
     }
+    /// 一条语句解析失败后跳过剩下的 token，停在下一条语句开始之前，
+    /// 这样后面的语句还能继续解析，一次报出所有错误
+    fn synchronize(&self) {
+        while !self.cur_token_is(SEMICOLON) && !self.cur_token_is(EOF) {
+            let peek = self.peek_token.borrow().token_type;
+            if matches!(
+                peek,
+                EOF | RBRACE | LET | RETURN | WHILE | FOR | TRY | THROW | BREAK | CONTINUE
+            ) {
+                return;
+            }
+            self.next_token();
+        }
+    }
     pub fn parse_let_statement(&self) -> Option<Rc<dyn Statement>> {
         let cur_token = (*self.cur_token.borrow()).clone();
 
@@ -235,17 +351,13 @@ impl Parser {
         if !self.expect_peek(ASSIGN) {
             return None;
         }
-        // FIXME: 这里是想给 parse 后面的 expression 的
-        #[allow(unused_assignments)]
-        let mut expression = None;
-
         self.next_token();
-        expression = self.parse_expression(ExpressionConst::LOWEST);
+        let expression = Some(self.parse_expression(ExpressionConst::LOWEST)?);
 
         // while !self.cur_token_is(SEMICOLON) {
         //     self.next_token();
         // }
-        if !self.cur_token_is(SEMICOLON) {
+        if self.peek_token_is(SEMICOLON) {
             self.next_token();
         }
 
@@ -268,13 +380,18 @@ impl Parser {
 
     pub fn parse_return_statement(&self) -> Option<Rc<dyn Statement>> {
         let cur_token = (*self.cur_token.borrow()).clone();
-        #[allow(unused_assignments)]
-        let mut expression = None;
-
-        self.next_token();
-        expression = self.parse_expression(ExpressionConst::LOWEST);
+        // return; 或者 return 直接跟着 } 时没有返回值
+        let expression = if self.peek_token_is(SEMICOLON)
+            || self.peek_token_is(RBRACE)
+            || self.peek_token_is(EOF)
+        {
+            None
+        } else {
+            self.next_token();
+            Some(self.parse_expression(ExpressionConst::LOWEST)?)
+        };
 
-        while !self.cur_token_is(SEMICOLON) {
+        if self.peek_token_is(SEMICOLON) {
             self.next_token();
         }
         Some(Rc::new(ReturnStatement {
@@ -283,14 +400,139 @@ impl Parser {
         }))
     }
 
-    fn parse_expression_statement(&self) -> Option<Rc<dyn Statement>> {
+    pub fn parse_while_statement(&self) -> Option<Rc<dyn Statement>> {
+        let token = (*self.cur_token.borrow()).clone();
+        if !self.expect_peek(LPAREN) {
+            return None;
+        }
+        self.next_token();
+        let condition = self.parse_expression(ExpressionConst::LOWEST)?;
+        if !self.expect_peek(RPAREN) || !self.expect_peek(LBRACE) {
+            return None;
+        }
+        let body = self.parse_block_statement()?;
+        if self.peek_token_is(SEMICOLON) {
+            self.next_token();
+        }
+        Some(Rc::new(WhileStatement {
+            token,
+            condition,
+            body,
+        }))
+    }
+
+    pub fn parse_for_statement(&self) -> Option<Rc<dyn Statement>> {
         let token = (*self.cur_token.borrow()).clone();
-        if token.literal == ";" {
-            println!("parse_expression_statement")
+        if !self.expect_peek(LPAREN) || !self.expect_peek(IDENT) {
+            return None;
         }
+        let ct = (*self.cur_token.borrow()).clone();
+        let variable = Rc::new(Identifier {
+            value: ct.literal.clone(),
+            token: ct,
+        });
+        if !self.expect_peek(IN) {
+            return None;
+        }
+        self.next_token();
+        let iterable = self.parse_expression(ExpressionConst::LOWEST)?;
+        if !self.expect_peek(RPAREN) || !self.expect_peek(LBRACE) {
+            return None;
+        }
+        let body = self.parse_block_statement()?;
+        if self.peek_token_is(SEMICOLON) {
+            self.next_token();
+        }
+        Some(Rc::new(ForStatement {
+            token,
+            variable,
+            iterable,
+            body,
+        }))
+    }
+
+    pub fn parse_try_statement(&self) -> Option<Rc<dyn Statement>> {
+        let token = (*self.cur_token.borrow()).clone();
+        if !self.expect_peek(LBRACE) {
+            return None;
+        }
+        let body = self.parse_block_statement()?;
+        let mut catch_param = None;
+        let mut catch_body = None;
+        if self.peek_token_is(CATCH) {
+            self.next_token();
+            if self.peek_token_is(LPAREN) {
+                self.next_token();
+                if !self.expect_peek(IDENT) {
+                    return None;
+                }
+                let ct = (*self.cur_token.borrow()).clone();
+                catch_param = Some(Rc::new(Identifier {
+                    value: ct.literal.clone(),
+                    token: ct,
+                }));
+                if !self.expect_peek(RPAREN) {
+                    return None;
+                }
+            }
+            if !self.expect_peek(LBRACE) {
+                return None;
+            }
+            catch_body = Some(self.parse_block_statement()?);
+        }
+        let mut finally_body = None;
+        if self.peek_token_is(FINALLY) {
+            self.next_token();
+            if !self.expect_peek(LBRACE) {
+                return None;
+            }
+            finally_body = Some(self.parse_block_statement()?);
+        }
+        if catch_body.is_none() && finally_body.is_none() {
+            self.push_error(ParseError {
+                kind: ParseErrorKind::UnexpectedToken,
+                expected: format!("{} or {}", CATCH, FINALLY),
+                found: self.peek_token.borrow().token_type.into(),
+                span: self.peek_token.borrow().span,
+            });
+            return None;
+        }
+        if self.peek_token_is(SEMICOLON) {
+            self.next_token();
+        }
+        Some(Rc::new(TryStatement {
+            token,
+            body,
+            catch_param,
+            catch_body,
+            finally_body,
+        }))
+    }
+
+    pub fn parse_throw_statement(&self) -> Option<Rc<dyn Statement>> {
+        let token = (*self.cur_token.borrow()).clone();
+        self.next_token();
+        let value = self.parse_expression(ExpressionConst::LOWEST)?;
+        if self.peek_token_is(SEMICOLON) {
+            self.next_token();
+        }
+        Some(Rc::new(ThrowStatement { token, value }))
+    }
+
+    /// break 和 continue 只有一个关键字，后面的分号可有可无
+    fn parse_keyword_statement(&self) -> Token {
+        let token = (*self.cur_token.borrow()).clone();
+        if self.peek_token_is(SEMICOLON) {
+            self.next_token();
+        }
+        token
+    }
+
+    fn parse_expression_statement(&self) -> Option<Rc<dyn Statement>> {
+        let token = (*self.cur_token.borrow()).clone();
         let stm = ExpressionStatement {
             token,
-            expression: self.parse_expression(ExpressionConst::LOWEST),
+            expression: Some(self.parse_expression(ExpressionConst::LOWEST)?),
         };
         if self.peek_token_is(SEMICOLON) {
             self.next_token();
@@ -298,28 +540,36 @@ impl Parser {
         Some(Rc::new(stm))
     }
     fn parse_expression(&self, precedence: ExpressionConst) -> Option<Rc<dyn Expression>> {
+        let pending = self.pending_node();
+        let exp = self.parse_expression_node(precedence);
+        self.trace_exited(pending, exp.as_ref().map(|e| e.upcast()));
+        exp
+    }
+    fn parse_expression_node(&self, precedence: ExpressionConst) -> Option<Rc<dyn Expression>> {
         let tp = self.cur_token.borrow().token_type.to_string();
-        println!("parse_expression {}", &self.cur_token.borrow());
         let pfs = self.prefix_parse_fns.borrow();
-        let pf = pfs.get(&*tp);
-        if pf.is_none() {
-            self.no_prefix_parse_fn_error();
-            return None;
-        }
-        let pf = pf.unwrap();
-        let mut left = pf();
+        let pf = match pfs.get(&*tp) {
+            Some(pf) => pf.clone(),
+            None => {
+                self.no_prefix_parse_fn_error();
+                return None;
+            }
+        };
+        drop(pfs);
+        let mut left = pf()?;
         // println!("before parse_infix: {:?}", left);
         while !self.peek_token_is(SEMICOLON) && precedence < self.peek_precedence() {
             let pktp = self.peek_token.borrow().token_type.to_string();
-            if let Some(infix) = self.infix_parse_fns.borrow().get(&*pktp) {
+            let infix = self.infix_parse_fns.borrow().get(&*pktp).cloned();
+            if let Some(infix) = infix {
                 self.next_token();
-                left = infix(left.unwrap());
+                left = infix(left)?;
             } else {
-                return left;
+                return Some(left);
             }
         }
         // println!("after parse_infix: {:?}", left);
-        return left;
+        Some(left)
         // None
     }
     // fixme: return Option is better?
@@ -329,15 +579,36 @@ impl Parser {
         Some(Rc::new(Identifier { token, value }))
     }
     pub fn parse_integer_literal(&self) -> Option<Rc<dyn Expression>> {
-        let literal = self.cur_token.borrow().literal.clone();
-        if let Ok(v) = IntegerLiteral::try_from(literal) {
-            Some(Rc::new(v))
+        let token = (*self.cur_token.borrow()).clone();
+        if let Ok(v) = IntegerLiteral::try_from(token.literal.clone()) {
+            Some(Rc::new(IntegerLiteral { token, ..v }))
         } else {
+            self.invalid_literal_error("integer", &token);
             None
         }
         // IntegerLiteral::try_from(self.cur_token.borrow().Literal.clone())
         //     .map_or_else(|_| None, move |v| Some(Rc::new(v)))
     }
+    pub fn parse_big_integer_literal(&self) -> Option<Rc<dyn Expression>> {
+        let token = (*self.cur_token.borrow()).clone();
+        match BigIntegerLiteral::try_from(token.literal.clone()) {
+            Ok(v) => Some(Rc::new(BigIntegerLiteral { token, ..v })),
+            Err(_) => {
+                self.invalid_literal_error("integer", &token);
+                None
+            }
+        }
+    }
+    pub fn parse_float_literal(&self) -> Option<Rc<dyn Expression>> {
+        let token = (*self.cur_token.borrow()).clone();
+        match FloatLiteral::try_from(token.literal.clone()) {
+            Ok(v) => Some(Rc::new(FloatLiteral { token, ..v })),
+            Err(_) => {
+                self.invalid_literal_error("float", &token);
+                None
+            }
+        }
+    }
 
     pub fn parse_prefix_expression(&self) -> Option<Rc<dyn Expression>> {
         let token = (*self.cur_token.borrow()).clone();
@@ -349,18 +620,21 @@ impl Parser {
         };
         self.next_token();
         // None
-        ex.right = self.parse_expression(ExpressionConst::PREFIX);
+        ex.right = Some(self.parse_expression(ExpressionConst::PREFIX)?);
         Some(Rc::new(ex))
     }
     pub fn parse_infix_expression(&self, left: Rc<dyn Expression>) -> Option<Rc<dyn Expression>> {
         let token = (*self.cur_token.borrow()).clone();
-        let operator = self.cur_token.borrow().literal.clone().into();
+        let operator = self.cur_token.borrow().literal.clone();
 
-        let precedence = self.cur_precedence();
+        let mut precedence = self.cur_precedence();
+        // ^^ 是右结合的，2 ^^ 3 ^^ 2 == 2 ^^ (3 ^^ 2)
+        if precedence == ExpressionConst::POW {
+            precedence = ExpressionConst::from(precedence as isize - 1);
+        }
         self.next_token();
 
-        #[allow(unused_mut)]
-        let mut right = self.parse_expression(precedence);
+        let right = Some(self.parse_expression(precedence)?);
         let left = Some(left);
 
         let expression = InfixExpression {
@@ -372,54 +646,90 @@ impl Parser {
         // println!("parse_infix_expression result: {:?}", expression);
         Some(Rc::new(expression))
     }
+    /// 赋值是右结合的，a = b = 1 先给 b 赋值
+    pub fn parse_assign_expression(
+        &self,
+        target: Rc<dyn Expression>,
+    ) -> Option<Rc<dyn Expression>> {
+        let token = (*self.cur_token.borrow()).clone();
+        if !target.as_any().is::<Identifier>() && !target.as_any().is::<IndexExpression>() {
+            self.push_error(ParseError {
+                kind: ParseErrorKind::InvalidAssignmentTarget,
+                expected: "identifier or index expression".into(),
+                found: target.to_string(),
+                span: target.span(),
+            });
+            return None;
+        }
+        self.next_token();
+        let value = self.parse_expression(ExpressionConst::LOWEST)?;
+        Some(Rc::new(AssignExpression {
+            operator: token.literal.clone(),
+            token,
+            target,
+            value,
+        }))
+    }
     pub fn parse_boolean(&self) -> Option<Rc<dyn Expression>> {
         Some(Rc::new(BooleanLiteral {
             token: (*self.cur_token.borrow()).clone(),
             value: self.cur_token_is(TRUE),
         }))
     }
+    pub fn parse_null_literal(&self) -> Option<Rc<dyn Expression>> {
+        Some(Rc::new(NullLiteral {
+            token: (*self.cur_token.borrow()).clone(),
+        }))
+    }
     pub fn parse_grouped_expression(&self) -> Option<Rc<dyn Expression>> {
         self.next_token();
 
-        let exp = self.parse_expression(ExpressionConst::LOWEST);
-        self.expect_peek(RPAREN).then(|| exp.unwrap())
+        let exp = self.parse_expression(ExpressionConst::LOWEST)?;
+        self.expect_peek(RPAREN).then_some(exp)
     }
     pub fn parse_if_expression(&self) -> Option<Rc<dyn Expression>> {
         let token = (*self.cur_token.borrow()).clone();
+        let (condition, consequence) = self.parse_if_branch()?;
+        let mut else_ifs = vec![];
+        let mut alternative = None;
+
+        while self.peek_token_is(ELSE) {
+            self.next_token();
+            if self.peek_token_is(IF) {
+                self.next_token();
+                else_ifs.push(self.parse_if_branch()?);
+                continue;
+            }
+            if !self.expect_peek(LBRACE) {
+                return None;
+            }
+            alternative = Some(self.parse_block_statement()?);
+            break;
+        }
 
+        let expression = IfExpression {
+            token,
+            condition,
+            consequence: Some(consequence),
+            else_ifs,
+            alternative,
+        };
+        Some(Rc::new(expression))
+    }
+    /// 当前 token 是 IF，解析后面的 `(condition) { ... }`
+    fn parse_if_branch(&self) -> Option<(Rc<dyn Expression>, Rc<dyn Statement>)> {
         if !self.expect_peek(LPAREN) {
             return None;
         }
         self.next_token();
-        let condition = self.parse_expression(ExpressionConst::LOWEST);
-        if condition.is_none() {
-            return None;
-        }
+        let condition = self.parse_expression(ExpressionConst::LOWEST)?;
         if !self.expect_peek(RPAREN) {
             return None;
         }
-
         if !self.expect_peek(LBRACE) {
             return None;
         }
-
-        let consequence = self.parse_block_statement();
-        let mut alternative = None;
-
-        if self.peek_token_is(ELSE) {
-            self.next_token();
-            self.expect_peek(LBRACE).then(|| {
-                alternative = self.parse_block_statement();
-            });
-        }
-
-        let expression = IfExpression {
-            token,
-            condition: condition.unwrap(),
-            consequence,
-            alternative,
-        };
-        Some(Rc::new(expression))
+        Some((condition, self.parse_block_statement()?))
     }
     pub fn parse_block_statement(&self) -> Option<Rc<dyn Statement>> {
         let mut statement = vec![];
@@ -429,9 +739,21 @@ impl Parser {
 
         while !self.cur_token_is(RBRACE) && !self.cur_token_is(EOF) {
             let stm = self.parse_statement();
-            stm.map(|val| statement.push(val));
+            match stm {
+                Some(val) => statement.push(val),
+                None => self.synchronize(),
+            }
             self.next_token();
         }
+        if self.cur_token_is(EOF) {
+            self.push_error(ParseError {
+                kind: ParseErrorKind::UnexpectedToken,
+                expected: RBRACE.into(),
+                found: EOF.into(),
+                span: self.cur_token.borrow().span,
+            });
+            return None;
+        }
 
         // 这段代码，在书里面没有。我debug之后发现报错没有解析 } 的函数，因此认为这里应该要把这个token消费掉
         // if self.cur_token_is(RBRACE) {
@@ -458,13 +780,13 @@ impl Parser {
             return None;
         }
 
-        let parameters = self.parse_function_parameters();
+        let parameters = Some(self.parse_function_parameters()?);
 
         if !self.expect_peek(LBRACE) {
             return None;
         }
 
-        let body = self.parse_block_statement();
+        let body = Some(self.parse_block_statement()?);
 
         let lit = FunctionLiteral {
             token,
@@ -482,7 +804,9 @@ impl Parser {
             return Some(identifiers);
         }
 
-        self.next_token();
+        if !self.expect_peek(IDENT) {
+            return None;
+        }
 
         let ident = Rc::new(Identifier {
             token: (*self.cur_token.borrow()).clone(),
@@ -498,8 +822,9 @@ impl Parser {
 
         while self.peek_token_is(COMMA) {
             self.next_token();
-            self.next_token();
-            // fixme fn as p
+            if !self.expect_peek(IDENT) {
+                return None;
+            }
             let ident = Rc::new(Identifier {
                 token: (*self.cur_token.borrow()).clone(),
                 value: self.cur_token.borrow().literal.clone(),
@@ -514,10 +839,8 @@ impl Parser {
         Some(identifiers)
     }
     pub fn parse_call_expression(&self, f: Rc<dyn Expression>) -> Option<Rc<dyn Expression>> {
-        println!("\n\nparse_call_expression\n\n{:?}", "args");
-
         let token = (*self.cur_token.borrow()).clone();
-        let args = self.parse_expression_list(RPAREN);
+        let args = self.parse_expression_list(RPAREN)?;
 
         Some(Rc::new(CallExpression {
             token,
@@ -526,48 +849,91 @@ impl Parser {
         }))
     }
     pub fn parse_string_literal(&self) -> Option<Rc<dyn Expression>> {
-        let literal = self.cur_token.borrow().literal.clone();
-        if let Ok(v) = StringLiteral::try_from(literal) {
-            Some(Rc::new(v))
-        } else {
-            None
+        let token = self.cur_token.borrow().clone();
+        Some(Rc::new(StringLiteral {
+            value: Rc::new(token.literal.clone()),
+            token,
+        }))
+    }
+    /// ${} 里的源码交给一个新的 Parser 解析，span 仍然相对整个源码，出的错误合并到这里
+    pub fn parse_template_literal(&self) -> Option<Rc<dyn Expression>> {
+        let token = self.cur_token.borrow().clone();
+        let mut parts = vec![];
+        let mut ok = true;
+        for segment in split_template(&token.literal) {
+            match segment {
+                TemplateSegment::Text(raw) => match unescape(&raw) {
+                    Ok(text) => parts.push(TemplatePart::Text(Rc::new(text))),
+                    Err(seq) => {
+                        self.push_error(ParseError {
+                            kind: ParseErrorKind::InvalidEscape,
+                            expected: "string".into(),
+                            found: seq,
+                            span: token.span,
+                        });
+                        ok = false;
+                    }
+                },
+                TemplateSegment::Code(offset, code) => {
+                    let p = Parser::new(Lexer::with_origin(code, template_origin(&token, offset)));
+                    let exp = p.parse_expression(LOWEST);
+                    if exp.is_some() {
+                        p.expect_peek(EOF);
+                    }
+                    let errors = p.parse_errors();
+                    ok &= errors.is_empty();
+                    errors.into_iter().for_each(|e| self.push_error(e));
+                    if let Some(exp) = exp {
+                        parts.push(TemplatePart::Expression(exp));
+                    }
+                }
+            }
         }
+        if !ok {
+            return None;
+        }
+        Some(Rc::new(TemplateLiteral { token, parts }))
+    }
+    /// lexer 产生的出错的 token：没闭合的字符串、注释和不认识的转义
+    pub fn parse_error_token(&self) -> Option<Rc<dyn Expression>> {
+        let token = self.cur_token.borrow().clone();
+        let (kind, expected) = match token.token_type {
+            UNTERMINATED_STRING => (ParseErrorKind::UnterminatedString, "string"),
+            UNTERMINATED_COMMENT => (ParseErrorKind::UnterminatedComment, "comment"),
+            INVALID_NUMBER => (ParseErrorKind::InvalidLiteral, "number"),
+            _ => (ParseErrorKind::InvalidEscape, "string"),
+        };
+        self.push_error(ParseError {
+            kind,
+            expected: expected.into(),
+            found: token.literal,
+            span: token.span,
+        });
+        None
     }
     pub fn parse_array_literal(&self) -> Option<Rc<dyn Expression>> {
         // let mut list = vec![];
         // Some(Rc::new())
-        let literal = self.cur_token.borrow().literal.clone();
+        let token = (*self.cur_token.borrow()).clone();
         let arr = ArrayLiteral {
-            token: Token {
-                literal,
-                token_type: token::LBRACKET,
-            },
-            elements: self.parse_expression_list(token::RBRACKET),
+            token,
+            elements: self.parse_expression_list(token::RBRACKET)?,
         };
         Some(Rc::new(arr))
     }
     pub fn parse_hash_literal(&self) -> Option<Rc<dyn Expression>> {
-        let literal = self.cur_token.borrow().literal.clone();
-        let mut pairs = HashMap::new();
+        let token = (*self.cur_token.borrow()).clone();
+        let mut pairs = vec![];
 
         while !self.peek_token_is(RBRACE) {
             self.next_token();
-            let k = self.parse_expression(LOWEST);
+            let k = self.parse_expression(LOWEST)?;
             if !self.expect_peek(COLON) {
                 return None;
             };
             self.next_token();
-            let v = self.parse_expression(LOWEST);
-            pairs.insert(
-                k.unwrap()
-                    .as_ref()
-                    .as_any()
-                    .downcast_ref::<StringLiteral>()
-                    .unwrap()
-                    .value
-                    .clone(),
-                v.unwrap(),
-            );
+            let v = self.parse_expression(LOWEST)?;
+            pairs.push((k, v));
 
             if !self.peek_token_is(RBRACE) && !self.expect_peek(COMMA) {
                 return None;
@@ -577,50 +943,65 @@ impl Parser {
             return None;
         }
         Some(Rc::new(HashLiteral {
-            token: Token {
-                literal,
-                token_type: token::LBRACE,
-            },
+            token,
             pairs: RefCell::new(pairs),
         }))
         // None
     }
-    pub fn parse_expression_list(&self, end: TokenType) -> Vec<Rc<dyn Expression>> {
+    pub fn parse_expression_list(&self, end: TokenType) -> Option<Vec<Rc<dyn Expression>>> {
         let mut r = vec![];
         if self.peek_token_is(end) {
             self.next_token();
-            return r;
+            return Some(r);
         }
         self.next_token();
-        r.push(self.parse_expression(LOWEST).unwrap());
+        r.push(self.parse_expression(LOWEST)?);
 
         while self.peek_token_is(token::COMMA) {
             self.next_token();
             self.next_token();
-            if let Some(e) = self.parse_expression(LOWEST) {
-                r.push(e);
-            }
+            r.push(self.parse_expression(LOWEST)?);
         }
         if !self.expect_peek(end) {
-            return vec![];
+            return None;
         }
-        return r;
+        Some(r)
     }
     pub fn parse_index_expression(&self, left: Rc<dyn Expression>) -> Option<Rc<dyn Expression>> {
-        let literal = self.cur_token.borrow().literal.clone();
+        let token = (*self.cur_token.borrow()).clone();
         self.next_token();
-        let index = self.parse_expression(LOWEST);
+        let index = self.parse_expression(LOWEST)?;
         if !self.expect_peek(token::RBRACKET) {
             return None;
         }
-        return Some(Rc::new(IndexExpression {
-            token: Token {
-                literal,
-                token_type: token::LBRACKET,
-            },
+        let optional = token.token_type == OPTIONAL_LBRACKET;
+        Some(Rc::new(IndexExpression {
+            token,
             left: left.clone(),
-            index: index.unwrap(),
-        }));
+            index,
+            optional,
+        }))
+    }
+    /// a?.b 就是 a?["b"]，点后面只能是标识符
+    pub fn parse_optional_dot_expression(
+        &self,
+        left: Rc<dyn Expression>,
+    ) -> Option<Rc<dyn Expression>> {
+        let token = (*self.cur_token.borrow()).clone();
+        if !self.expect_peek(IDENT) {
+            return None;
+        }
+        let name = (*self.cur_token.borrow()).clone();
+        let index = Rc::new(StringLiteral {
+            value: Rc::new(name.literal.clone()),
+            token: name,
+        });
+        Some(Rc::new(IndexExpression {
+            token,
+            left,
+            index,
+            optional: true,
+        }))
     }
     pub fn expect_peek(&self, token: TokenType) -> bool {
         let r = self.peek_token_is(token);
@@ -642,10 +1023,37 @@ impl Parser {
     pub fn errors(&self) -> Rc<RefCell<Vec<String>>> {
         self.errors.clone()
     }
+    pub fn parse_errors(&self) -> Vec<ParseError> {
+        self.parse_errors.borrow().clone()
+    }
+    /// 每条错误都带上出错那一行的源码和 ^ 标记
+    pub fn diagnostics(&self) -> Vec<String> {
+        self.parse_errors
+            .borrow()
+            .iter()
+            .map(|e| render_diagnostic(self.l.input(), e.span, &e.to_string()))
+            .collect()
+    }
+    fn push_error(&self, error: ParseError) {
+        self.errors.borrow_mut().push(error.to_string());
+        self.parse_errors.borrow_mut().push(error);
+    }
     pub fn peek_error(&self, t: TokenType) {
-        let msg = self.peek_token.borrow().token_type;
-        let msg = format!("expect next token to be {}, got {} instead", t, msg);
-        self.errors.borrow_mut().push(msg);
+        let peek = self.peek_token.borrow().clone();
+        self.push_error(ParseError {
+            kind: ParseErrorKind::UnexpectedToken,
+            expected: t.into(),
+            found: peek.token_type.into(),
+            span: peek.span,
+        });
+    }
+    fn invalid_literal_error(&self, expected: &str, token: &Token) {
+        self.push_error(ParseError {
+            kind: ParseErrorKind::InvalidLiteral,
+            expected: expected.into(),
+            found: token.literal.clone(),
+            span: token.span,
+        });
     }
     pub fn register_prefix(&self, token: TokenType, f: Rc<PrefixParseFn>) {
         self.prefix_parse_fns.borrow_mut().insert(token, f);
@@ -654,10 +1062,13 @@ impl Parser {
         self.infix_parse_fns.borrow_mut().insert(token, f);
     }
     pub fn no_prefix_parse_fn_error(&self) {
-        self.errors.borrow_mut().push(format!(
-            "Cannot found prefix_parse_fn for {}",
-            self.cur_token.borrow().token_type.to_string()
-        ));
+        let cur = self.cur_token.borrow().clone();
+        self.push_error(ParseError {
+            kind: ParseErrorKind::MissingExpression,
+            expected: "expression".into(),
+            found: cur.token_type.into(),
+            span: cur.span,
+        });
     }
     pub fn peek_precedence(&self) -> ExpressionConst {
         let mut r = ExpressionConst::LOWEST;
@@ -680,3 +1091,24 @@ impl Parser {
         r
     }
 }
+
+/// 模板字符串内容里第 offset 个字符在整个源码中的位置，内容从开头的引号后面开始
+fn template_origin(token: &Token, offset: usize) -> Span {
+    let mut origin = Span {
+        start: token.span.start + 1,
+        line: token.span.line,
+        column: token.span.column + 1,
+        ..Span::default()
+    };
+    for c in token.literal.chars().take(offset) {
+        origin.start += c.len_utf8();
+        if c == '\n' {
+            origin.line += 1;
+            origin.column = 1;
+        } else {
+            origin.column += 1;
+        }
+    }
+    origin.end = origin.start;
+    origin
+}
@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod test {
-    use {crate::*, std::rc::Rc};
+    use {crate::*, std::cell::RefCell, std::rc::Rc};
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_parser() {
         let input = r#"let x = 5;
@@ -75,7 +76,8 @@ return;
         let lex = Lexer::new(input);
         let p = Parser::new(lex);
         let pr = p.parse_program();
-        test_parser_errors(&p, Some(1));
+        // 不带返回值的 return; 也是合法的
+        test_parser_errors(&p, None);
 
         assert!(pr.is_some());
         let pr = pr.unwrap();
@@ -93,6 +95,251 @@ return;
         }
     }
 
+    #[test]
+    fn test_parser_diagnostics() {
+        let input = "let a = 1;\nlet = 5;";
+        let p = Parser::new(Lexer::new(input));
+        p.parse_program();
+        let diagnostics = p.diagnostics();
+        assert_eq!(
+            diagnostics[0],
+            r#"error: expect next token to be IDENT, got = instead
+ --> 2:5
+  |
+2 | let = 5;
+  |     ^
+"#
+        );
+    }
+
+    #[test]
+    fn test_parser_error_recovery() {
+        let input = "let a = ;\nlet b = 2;\nfoo(1, ;\nlet c = 3;";
+        let p = Parser::new(Lexer::new(input));
+        let pr = p.parse_program().unwrap();
+        // 出错的语句被跳过，后面的语句照常解析
+        assert_eq!(pr.to_string(), "let b = 2;let c = 3;");
+        let errors = p.parse_errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0],
+            ParseError {
+                kind: ParseErrorKind::MissingExpression,
+                expected: "expression".into(),
+                found: ";".into(),
+                span: Span {
+                    start: 8,
+                    end: 9,
+                    line: 1,
+                    column: 9
+                },
+            }
+        );
+        assert_eq!(errors[1].to_string(), "expect expression, got ; instead");
+        assert_eq!(errors[1].span.line, 3);
+
+        let p = Parser::new(Lexer::new("fn() { let x = 1 +; x }; let y = 1;"));
+        let pr = p.parse_program().unwrap();
+        // 块里面也一样，只丢掉出错的那一条
+        assert_eq!(p.errors().borrow().len(), 1);
+        assert_eq!(pr.to_string(), "fn () { x }let y = 1;");
+    }
+
+    #[test]
+    fn test_template_literal() {
+        let cases = [
+            (r#""a ${b} c""#, r#""a ${b} c""#),
+            (r#""${1 + 2 * x}!""#, r#""${(1 + (2 * x))}!""#),
+            (r#""\n${ "x${y}" }""#, "\"\n${\"x${y}\"}\""),
+            (r#""${a}${b}""#, r#""${a}${b}""#),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let p = Parser::new(Lexer::new(*input));
+            let pr = p.parse_program().unwrap();
+            assert_eq!(p.errors().borrow().len(), 0, "{:?}", p.errors());
+            assert_eq!(pr.to_string(), *expected);
+        });
+
+        // ${} 里的错误带着在整个源码中的位置
+        let p = Parser::new(Lexer::new("let a = 1;\nlet s = \"中 ${a +}\";"));
+        p.parse_program();
+        let errors = p.parse_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "expect expression, got EOF instead");
+        assert_eq!((errors[0].span.line, errors[0].span.column), (2, 17));
+        assert_eq!(errors[0].span.start, "let a = 1;\nlet s = \"中 ${a +".len());
+
+        let cases = [
+            (r#""abc"#, "unterminated string literal"),
+            (r#""a\qb""#, r#"invalid escape sequence in string: \q"#),
+            (r#""${a}\q""#, r#"invalid escape sequence in string: \q"#),
+            ("1e", "invalid number literal: 1e"),
+            ("1.5e + 1", "invalid number literal: 1.5e"),
+            ("1e+", "invalid number literal: 1e+"),
+            ("1.5n", "invalid number literal: 1.5n"),
+            (r#""${}""#, "expect expression, got EOF instead"),
+            (
+                r#""${a b}""#,
+                "expect next token to be EOF, got IDENT instead",
+            ),
+        ];
+        cases.iter().for_each(|(input, msg)| {
+            let p = Parser::new(Lexer::new(*input));
+            p.parse_program();
+            assert_eq!(p.errors().borrow().first().map(|e| e.as_str()), Some(*msg));
+        });
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let input = "/// adds\n/// two numbers\nlet add = fn(a, b) { a + b };\n// plain\nlet x = 1;\n/// named\nfn sub(a, b) { a - b }";
+        let p = Parser::new(Lexer::new(input));
+        let pr = p.parse_program().unwrap();
+        assert_eq!(p.errors().borrow().len(), 0);
+        let docs = pr
+            .statement
+            .iter()
+            .map(|st| {
+                let any = st.as_any();
+                if let Some(l) = any.downcast_ref::<LetStatement>() {
+                    return l.doc();
+                }
+                let e = any.downcast_ref::<ExpressionStatement>().unwrap();
+                let e = e.expression.as_ref().unwrap();
+                e.as_any().downcast_ref::<FunctionLiteral>().unwrap().doc()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            docs,
+            [Some("adds\ntwo numbers".into()), None, Some("named".into())]
+        );
+
+        let p = Parser::new(Lexer::new("let a = 1; /* oops"));
+        let pr = p.parse_program().unwrap();
+        assert_eq!(pr.to_string(), "let a = 1;");
+        assert_eq!(
+            p.errors().borrow().as_slice(),
+            ["unterminated block comment"]
+        );
+    }
+
+    #[test]
+    fn test_parser_never_panics() {
+        let cases = [
+            "fn(",
+            "fn(1) {}",
+            "fn(a, ) {}",
+            "[1, 2",
+            "{1: }",
+            "{1 2}",
+            "(1",
+            "a[",
+            "a[1",
+            "if (x) {",
+            "if (x) { 1 } else",
+            "if (x) { 1 } else if",
+            "if (x) { 1 } else if (y) { 2 } else (z)",
+            "let",
+            "let x",
+            "1 +",
+            ")",
+            "foo(,)",
+            "let x = 99999999999999999999;",
+            "while (x",
+            "for (x in",
+            "try { 1 }",
+            "try { 1 } catch (1) { 2 }",
+            "throw",
+        ];
+        cases.iter().for_each(|input| {
+            let p = Parser::new(Lexer::new(*input));
+            assert!(p.parse_program().is_some());
+            assert!(!p.errors().borrow().is_empty(), "{}", input);
+            assert_eq!(p.errors().borrow().len(), p.diagnostics().len());
+        });
+    }
+
+    #[test]
+    fn test_parser_tracer() {
+        #[derive(Default)]
+        struct Recorder(RefCell<Vec<String>>);
+        impl Tracer for Recorder {
+            fn on_event(&self, event: &TraceEvent) {
+                let e = match event {
+                    TraceEvent::TokenConsumed(t) => format!("token {}", t.literal),
+                    TraceEvent::NodeEntered(n) => format!("enter {}", n),
+                    TraceEvent::NodeExited(n) => format!("exit {}", n),
+                    _ => return,
+                };
+                self.0.borrow_mut().push(e);
+            }
+        }
+        let recorder = Rc::new(Recorder::default());
+        let p = Parser::new(Lexer::new("-a * b;"));
+        p.set_tracer(recorder.clone());
+        p.parse_program();
+        assert_eq!(
+            *recorder.0.borrow(),
+            [
+                "enter -",
+                "enter -",
+                "token -",
+                "enter a",
+                "exit a",
+                "token a",
+                "token *",
+                "enter b",
+                "exit b",
+                "exit ((-a) * b)",
+                "token b",
+                "exit ((-a) * b)",
+                "token ;",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_tracer_balanced() {
+        // 还没有退出的节点数，退出时不能比进入的多
+        #[derive(Default)]
+        struct Recorder {
+            depth: RefCell<usize>,
+            pairs: RefCell<usize>,
+        }
+        impl Tracer for Recorder {
+            fn on_event(&self, event: &TraceEvent) {
+                match event {
+                    TraceEvent::NodeEntered(_) => *self.depth.borrow_mut() += 1,
+                    TraceEvent::NodeExited(n) => {
+                        let mut depth = self.depth.borrow_mut();
+                        assert!(*depth > 0, "{}", n);
+                        *depth -= 1;
+                        *self.pairs.borrow_mut() += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let cases = [
+            ("-a * b;", 4),
+            (
+                "let x = fn(a, b) { if (a) { a[0] } else { b(1, 2) } }; x",
+                14,
+            ),
+            ("for (x in [1, 2]) { try { x } catch (e) { throw e; } }", 9),
+            // 解析失败的语句和表达式也要退出
+            ("let = 1; a + ; b", 6),
+        ];
+        cases.iter().for_each(|(input, pairs)| {
+            let recorder = Rc::new(Recorder::default());
+            let p = Parser::new(Lexer::new(*input));
+            p.set_tracer(recorder.clone());
+            p.parse_program();
+            assert_eq!(*recorder.depth.borrow(), 0, "{}", input);
+            assert_eq!(*recorder.pairs.borrow(), *pairs, "{}", input);
+        });
+    }
+
     #[test]
     fn test_register_parse_fns() {
         let input = r#"return 5;
@@ -106,6 +353,8 @@ return 500;
                 token: Token {
                     literal: EOF.into(),
                     token_type: EOF,
+                    span: Span::default(),
+                    leading_trivia: vec![],
                 },
                 expression: None,
             }))
@@ -115,6 +364,8 @@ return 500;
                 token: Token {
                     literal: EOF.into(),
                     token_type: EOF,
+                    span: Span::default(),
+                    leading_trivia: vec![],
                 },
                 expression: None,
             }))
@@ -199,9 +450,15 @@ return 500;
         println!("{:?}", pr);
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_parsing_prefix_expression() {
-        let prefix_tests = vec![("!5", "!", 5i64), ("-15", "-", 15i64)];
+        let prefix_tests = vec![
+            ("!5", "!", 5i64),
+            ("-15", "-", 15i64),
+            ("~15", "~", 15i64),
+            ("+15", "+", 15i64),
+        ];
 
         prefix_tests
             .iter()
@@ -241,6 +498,7 @@ return 500;
             });
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_parsing_infix_expression() {
         let tests = vec![
@@ -250,6 +508,9 @@ return 500;
             ("5 / 5", 5, "/", 5),
             ("5 > 5", 5, ">", 5),
             ("5 < 5", 5, "<", 5),
+            ("5 >= 5", 5, ">=", 5),
+            ("5 <= 5", 5, "<=", 5),
+            ("5 % 5", 5, "%", 5),
             ("5 == 5", 5, "==", 5),
             ("5 != 5", 5, "!=", 5),
             ("5 ^ 5", 5, "^", 5),
@@ -373,6 +634,10 @@ return 500;
             ("3 + 4; -5 * 5", "(3 + 4)((-5) * 5)"),
             ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
             ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4))"),
+            ("a <= b == c >= d", "((a <= b) == (c >= d))"),
+            ("a + b % c * d", "(a + ((b % c) * d))"),
+            ("~a & b", "((~a) & b)"),
+            ("-+a", "(-(+a))"),
             (
                 "3 + 4 * 5 == 3 * 1 + 4 * 5",
                 "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))",
@@ -399,6 +664,22 @@ return 500;
                 "add(a * b[2], b[1], 2 * [1, 2][1])",
                 "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
             ),
+            ("a || b && c", "(a || (b && c))"),
+            ("!a && b", "((!a) && b)"),
+            ("a == b && c < d", "((a == b) && (c < d))"),
+            ("a & b == c", "((a & b) == c)"),
+            ("a | b + c", "(a | (b + c))"),
+            ("-a & b", "((-a) & b)"),
+            ("2 * 3 ^^ 2", "(2 * (3 ^^ 2))"),
+            ("2 ^^ 3 ^^ 2", "(2 ^^ (3 ^^ 2))"),
+            ("-2 ^^ 2", "(-(2 ^^ 2))"),
+            ("1.5 * 2 + 3e2", "((1.5 * 2) + 300.0)"),
+            ("-123n * 0xffn", "((-123n) * 255n)"),
+            ("a ?? b || c", "(a ?? (b || c))"),
+            ("a ?? b ?? null", "((a ?? b) ?? null)"),
+            ("a = b ?? 1", "(a = (b ?? 1))"),
+            ("a?.b?[c + 1][0]", "(((a?[b])?[(c + 1)])[0])"),
+            ("-a?.b", "(-(a?[b]))"),
         ];
 
         #[allow(unused)]
@@ -534,6 +815,20 @@ return 500;
                 .pairs
         );
     }
+
+    #[test]
+    fn test_hash_literal_with_any_key() {
+        let input = r#"{1: "a", true: 2, k: 3, 1 + 1: 4}"#;
+        let p = Parser::new(Lexer::new(input));
+        let pr = p.parse_program();
+        test_parser_errors(&p, None);
+        assert_eq!(format!("{}", pr.unwrap()), "{ 1:a,true:2,k:3,(1 + 1):4 }");
+
+        // 之前这里会 unwrap 失败直接 panic
+        let p = Parser::new(Lexer::new("{1: }"));
+        p.parse_program();
+        assert!(!p.errors().borrow().is_empty());
+    }
     #[test]
     fn test_mixed_to_string() {
         let input = r#"let x = 1;
@@ -617,6 +912,128 @@ return;
         let il = il.unwrap();
     }
 
+    #[test]
+    fn test_else_if_chain() {
+        let cases = [
+            ("if (x < y) { x }", "if ((x < y)) { x }"),
+            ("if (x) { 1 } else { 2 }", "if (x) { 1 } else { 2 }"),
+            (
+                "if (a) { 1 } else if (b) { 2 } else if (c) { 3 } else { 4 }",
+                "if (a) { 1 } else if (b) { 2 } else if (c) { 3 } else { 4 }",
+            ),
+            (
+                "if (a) { 1 } else if (b) { 2 }",
+                "if (a) { 1 } else if (b) { 2 }",
+            ),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let p = Parser::new(Lexer::new(*input));
+            let pr = p.parse_program();
+            test_parser_errors(&p, None);
+            assert_eq!(format!("{}", pr.unwrap()), *expected);
+        });
+
+        // 条件总是带着括号输出，不管是标识符还是中缀表达式，输出都可以再解析回同样的结果
+        let inputs = [
+            "if (a) { 1 } else if (b) { 2 } else { 3 }",
+            "if (a < 1) { 1 } else if (a > 2) { 2 } else { 3 }",
+        ];
+        inputs.iter().for_each(|input| {
+            let p = Parser::new(Lexer::new(*input));
+            let once = format!("{}", p.parse_program().unwrap());
+            test_parser_errors(&p, None);
+            let p = Parser::new(Lexer::new(&once));
+            let twice = format!("{}", p.parse_program().unwrap());
+            test_parser_errors(&p, None);
+            assert_eq!(twice, once);
+        });
+
+        // 分支平铺在 else_ifs 里，不会嵌套
+        let p = Parser::new(Lexer::new(
+            "if (a) { 1 } else if (b) { 2 } else if (c) { 3 }",
+        ));
+        let pr = p.parse_program().unwrap();
+        let stm = ExpressionStatement::try_from(Box::new(&*pr.statement[0].clone())).unwrap();
+        let il = IfExpression::try_from(Box::new(&*stm.expression.unwrap())).unwrap();
+        assert_eq!(il.else_ifs.len(), 2);
+        assert!(il.alternative.is_none());
+    }
+
+    #[test]
+    fn test_assign_expression() {
+        let cases = [
+            ("a = 1", "(a = 1)"),
+            ("a = b = 1 + 2", "(a = (b = (1 + 2)))"),
+            ("a += 1 * 2", "(a += (1 * 2))"),
+            ("a[0] -= 1", "((a[0]) -= 1)"),
+            ("h[\"k\"] /= a || b", "((h[k]) /= (a || b))"),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let p = Parser::new(Lexer::new(*input));
+            let pr = p.parse_program();
+            test_parser_errors(&p, None);
+            assert_eq!(format!("{}", pr.unwrap()), *expected);
+        });
+
+        let p = Parser::new(Lexer::new("a + b = 1"));
+        p.parse_program();
+        assert_eq!(p.errors().borrow()[0], "invalid assignment target: (a + b)");
+    }
+
+    #[test]
+    fn test_loop_statements() {
+        let cases = [
+            ("while (x < 3) { x }", "while (x < 3) { x }"),
+            (
+                "for (x in [1, 2]) { break; }",
+                "for (x in [1, 2]) { break; }",
+            ),
+            (
+                "for (k in h) { continue }; 1",
+                "for (k in h) { continue; }1",
+            ),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let p = Parser::new(Lexer::new(*input));
+            let pr = p.parse_program();
+            test_parser_errors(&p, None);
+            assert_eq!(format!("{}", pr.unwrap()), *expected);
+        });
+
+        let p = Parser::new(Lexer::new("for (1 in a) { a }"));
+        p.parse_program();
+        assert_eq!(
+            p.errors().borrow()[0],
+            "expect next token to be IDENT, got INT instead"
+        );
+    }
+
+    #[test]
+    fn test_try_statement() {
+        let cases = [
+            (
+                "try { a } catch (e) { e } finally { b }",
+                "try { a } catch (e) { e } finally { b }",
+            ),
+            ("try { a } catch { b };", "try { a } catch { b }"),
+            ("try { a } finally { b }", "try { a } finally { b }"),
+            (r#"throw {"kind": k}; 1"#, "throw { kind:k };1"),
+        ];
+        cases.iter().for_each(|(input, expected)| {
+            let p = Parser::new(Lexer::new(*input));
+            let pr = p.parse_program();
+            test_parser_errors(&p, None);
+            assert_eq!(format!("{}", pr.unwrap()), *expected);
+        });
+
+        let p = Parser::new(Lexer::new("try { a } b"));
+        p.parse_program();
+        assert_eq!(
+            p.errors().borrow()[0],
+            "expect next token to be CATCH or FINALLY, got IDENT instead"
+        );
+    }
+
     #[test]
     fn test_function_literal() {
         let input = r#"fn (x, y) { return x + y; };
@@ -715,6 +1132,7 @@ let mf = fn(x, y) { return x + y; };"#;
         println!("test_array_literal: pr: {:?}", pr);
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn test_index_literal() {
         let cases = vec![("[1, 2, 3][0]")];
@@ -9,8 +9,8 @@ thread_local! {
     static HISTORY: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]))
 }
 
-pub const PROMPT: &'static str = ">> ";
-pub const SYMBOL: &'static str = r#"
+pub const PROMPT: &str = ">> ";
+pub const SYMBOL: &str = r#"
  xxxxxxxxxxxx
   xx       xxx
   xx        xxx
@@ -35,10 +35,10 @@ pub fn start() {
     loop {
         print!("{PROMPT}");
         std::io::stdout().flush().unwrap();
-        stdin.read_line(&mut input).unwrap();
-        // println!("read key: {:?}", input);
-        if input == "" {
-            continue;
+        // 读到 EOF（比如 Ctrl-D）时退出
+        if stdin.read_line(&mut input).unwrap_or(0) == 0 {
+            println!();
+            break;
         }
         HISTORY.with(|history| {
             history.borrow_mut().push(input.clone());
@@ -46,17 +46,22 @@ pub fn start() {
         let lex = Lexer::new(input.clone());
         let p = Parser::new(lex.clone());
         let pr = p.parse_program();
-        assert!(pr.is_some());
-        if p.errors().borrow().len() != 0 {
+        if !p.errors().borrow().is_empty() {
             println!("{}\n", SYMBOL);
-            print_parser_errors(p.errors().borrow().as_ref());
+            print_parser_errors(&p.diagnostics());
             input.clear();
             continue;
         }
-        let pr = pr.unwrap();
+        let Some(pr) = pr else {
+            input.clear();
+            continue;
+        };
         println!("{}", &pr);
         if let Some(r) = eval(&pr, context.clone()).as_ref() {
-            println!("{}", r);
+            match r.as_any().downcast_ref::<ErrorObject>() {
+                Some(err) => print!("{}", err.render(&input)),
+                None => println!("{}", r),
+            }
         }
         // eval(&pr);
         // loop {
@@ -93,8 +98,8 @@ pub fn start() {
     // }
 }
 
-pub fn print_parser_errors(errors: &Vec<String>) {
+pub fn print_parser_errors(errors: &[String]) {
     errors.iter().for_each(|err| {
-        println!("\t{}", err);
+        print!("{}", err);
     });
 }
@@ -0,0 +1,29 @@
+use crate::evaluator::*;
+use crate::interpreter::Interpreter;
+use std::rc::Rc;
+
+/// 完整执行一段脚本：lex、parse 然后 eval
+/// 出错时返回的是已经带上源码位置的错误信息，可以直接打印
+pub fn run_source(source: &str) -> Result<Option<Rc<dyn Object>>, String> {
+    Interpreter::new().eval_str(source)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_run_source() {
+        let r = run_source("let a = fn(x) { x * 2 }; a(21)").unwrap();
+        assert_eq!(r.unwrap().inspect(), "42");
+
+        let r = run_source("let a = 1;\nlet = 2;");
+        assert!(r.unwrap_err().contains("2 | let = 2;"));
+
+        let r = run_source("let a = 1;\na + true");
+        assert_eq!(
+            r.unwrap_err(),
+            "error: type mismatch: INTEGER + BOOLEAN\n --> 2:3\n  |\n2 | a + true\n  |   ^\n"
+        );
+    }
+}
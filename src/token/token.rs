@@ -1,10 +1,64 @@
 use std::collections::HashMap;
 pub type TokenType = &'static str;
 
+/// 源码中的位置
+/// start 和 end 是字节偏移（左闭右开），line 和 column 从 1 开始，column 按字符计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriviaKind {
+    Whitespace,
+    // 普通的 // 注释
+    LineComment,
+    // /* */ 注释，可以嵌套
+    BlockComment,
+    // /// 注释，会挂到后面的 let 或者 fn 上
+    DocComment,
+}
+
+/// token 前面的空白和注释，text 是源码中原样的内容
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
+    pub span: Span,
+    // 这个 token 前面的 trivia，文档注释总是会保留，其他的只有 lossless 模式才保留
+    pub leading_trivia: Vec<Trivia>,
+}
+
+impl Token {
+    /// 前面紧挨着的文档注释，去掉 /// 和后面的一个空格，多行用换行连起来
+    pub fn doc_comment(&self) -> Option<String> {
+        let lines = self
+            .leading_trivia
+            .iter()
+            .filter(|t| t.kind == TriviaKind::DocComment)
+            .map(|t| {
+                let text = t.text.trim_start_matches("///");
+                text.strip_prefix(' ').unwrap_or(text).trim_end()
+            })
+            .collect::<Vec<_>>();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
 }
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -16,6 +70,8 @@ impl Default for Token {
         Token {
             token_type: EOF,
             literal: "".into(),
+            span: Span::default(),
+            leading_trivia: vec![],
         }
     }
 }
@@ -25,6 +81,9 @@ pub const EOF: TokenType = "EOF";
 
 pub const IDENT: TokenType = "IDENT";
 pub const INT: TokenType = "INT";
+pub const FLOAT: TokenType = "FLOAT";
+// 123n 这样带 n 后缀的任意精度整数
+pub const BIGINT: TokenType = "BIGINT";
 
 pub const ASSIGN: TokenType = "=";
 pub const PLUS: TokenType = "+";
@@ -32,15 +91,29 @@ pub const MINUS: TokenType = "-";
 pub const BANG: TokenType = "!";
 pub const ASTERISK: TokenType = "*";
 pub const SLASH: TokenType = "/";
+pub const PERCENT: TokenType = "%";
 pub const BITAND: TokenType = "&";
 pub const BITXOR: TokenType = "^";
 pub const BITOR: TokenType = "|";
+pub const TILDE: TokenType = "~";
 pub const POW: TokenType = "^^";
 pub const LOGICOR: TokenType = "||";
 pub const LOGICAND: TokenType = "&&";
+// 左边是 null 时才取右边
+pub const NULLISH: TokenType = "??";
+// a?.b 和 a?[b]：左边是 null 时结果是 null，不再取下标
+pub const OPTIONAL_DOT: TokenType = "?.";
+pub const OPTIONAL_LBRACKET: TokenType = "?[";
+
+pub const PLUS_ASSIGN: TokenType = "+=";
+pub const MINUS_ASSIGN: TokenType = "-=";
+pub const ASTERISK_ASSIGN: TokenType = "*=";
+pub const SLASH_ASSIGN: TokenType = "/=";
 
 pub const LT: TokenType = "<";
 pub const GT: TokenType = ">";
+pub const LT_EQ: TokenType = "<=";
+pub const GT_EQ: TokenType = ">=";
 
 pub const COMMA: TokenType = ",";
 pub const SEMICOLON: TokenType = ";";
@@ -56,13 +129,32 @@ pub const FUNCTION: TokenType = "FUNCTION";
 pub const LET: TokenType = "LET";
 pub const TRUE: TokenType = "TRUE";
 pub const FALSE: TokenType = "FALSE";
+pub const NULL: TokenType = "NULL";
 pub const IF: TokenType = "if";
 pub const ELSE: TokenType = "ELSE";
 pub const RETURN: TokenType = "RETURN";
+pub const WHILE: TokenType = "WHILE";
+pub const FOR: TokenType = "FOR";
+pub const IN: TokenType = "IN";
+pub const BREAK: TokenType = "BREAK";
+pub const CONTINUE: TokenType = "CONTINUE";
+pub const TRY: TokenType = "TRY";
+pub const CATCH: TokenType = "CATCH";
+pub const FINALLY: TokenType = "FINALLY";
+pub const THROW: TokenType = "THROW";
 
 pub const EQ: TokenType = "==";
 pub const NOT_EQ: TokenType = "!=";
 pub const STRING: TokenType = "STRING";
+// 带 ${} 的字符串，literal 是引号之间原样的内容
+pub const TEMPLATE: TokenType = "TEMPLATE";
+// 下面两个是出错的字符串，literal 分别是读到的内容和不认识的转义序列
+pub const UNTERMINATED_STRING: TokenType = "UNTERMINATED_STRING";
+pub const INVALID_ESCAPE: TokenType = "INVALID_ESCAPE";
+// 不合法的数字，比如 1e 和 1.5n，literal 是读到的内容
+pub const INVALID_NUMBER: TokenType = "INVALID_NUMBER";
+// 没有闭合的 /* 注释，literal 是从 /* 到结尾的内容
+pub const UNTERMINATED_COMMENT: TokenType = "UNTERMINATED_COMMENT";
 pub const COLON: TokenType = ":";
 
 // pub const KEYWORDS: HashMap<String, TokenType> = HashMap::new();
@@ -72,9 +164,19 @@ pub fn lookup_ident(ident: &String) -> TokenType {
         ("fn".into(), FUNCTION),
         ("true".into(), TRUE),
         ("false".into(), FALSE),
+        ("null".into(), NULL),
         ("if".into(), IF),
         ("else".into(), ELSE),
         ("return".into(), RETURN),
+        ("while".into(), WHILE),
+        ("for".into(), FOR),
+        ("in".into(), IN),
+        ("break".into(), BREAK),
+        ("continue".into(), CONTINUE),
+        ("try".into(), TRY),
+        ("catch".into(), CATCH),
+        ("finally".into(), FINALLY),
+        ("throw".into(), THROW),
     ]);
 
     if let Some(&t) = keywords.get(ident) {
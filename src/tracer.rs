@@ -0,0 +1,69 @@
+use crate::ast::Node;
+use crate::object::Object;
+use crate::token::Token;
+use std::rc::Rc;
+
+/// 解析和求值过程中产生的事件
+///
+/// Parser 会产生 TokenConsumed，以及每个语句、表达式开始解析时的 NodeEntered 和解析完成后的 NodeExited；
+/// eval 对每个节点依次产生 NodeEntered、ValueProduced（有值的时候）、NodeExited
+#[derive(Debug)]
+pub enum TraceEvent<'a> {
+    TokenConsumed(&'a Token),
+    NodeEntered(&'a dyn Node),
+    NodeExited(&'a dyn Node),
+    ValueProduced(&'a dyn Node, &'a Rc<dyn Object>),
+}
+
+/// 嵌入方通过 Parser::set_tracer 和 Context::set_tracer 注册，默认不输出任何东西
+///
+/// ```
+/// use my_rust_interpreter::*;
+/// use std::cell::RefCell;
+///
+/// #[derive(Default)]
+/// struct CountValues(RefCell<usize>);
+/// impl Tracer for CountValues {
+///     fn on_event(&self, event: &TraceEvent) {
+///         if let TraceEvent::ValueProduced(..) = event {
+///             *self.0.borrow_mut() += 1;
+///         }
+///     }
+/// }
+///
+/// let tracer = Rc::new(CountValues::default());
+/// let context = Rc::new(Context::new());
+/// context.set_tracer(tracer.clone());
+/// let pr = Parser::new(Lexer::new("1 + 2")).parse_program().unwrap();
+/// eval(&pr, context);
+/// // 1, 2, 1 + 2, 表达式语句, 整个程序
+/// assert_eq!(*tracer.0.borrow(), 5);
+/// ```
+pub trait Tracer {
+    fn on_event(&self, event: &TraceEvent);
+}
+
+/// Parser 开始解析一个语句或者表达式时节点还没有建好，NodeEntered 给出的是它开始的 token
+/// 解析失败时 NodeExited 给出的也是它，所以进入和退出总是成对的
+#[derive(Debug, Clone)]
+pub struct PendingNode {
+    pub token: Token,
+}
+
+impl std::fmt::Display for PendingNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.token.literal)
+    }
+}
+
+impl Node for PendingNode {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn span(&self) -> crate::token::Span {
+        self.token.span
+    }
+}
@@ -1,10 +1,10 @@
-// FIXME: 不支持 unicode 的中文等等字码
-pub fn is_letter(ch: char) -> bool {
-    'a' <= ch && ch <= 'z' || 'A' <= ch && ch <= 'Z' || ch == '_'
+/// 标识符按 Unicode XID 的规则，另外允许用 _ 开头
+pub fn is_identifier_start(ch: char) -> bool {
+    ch == '_' || unicode_ident::is_xid_start(ch)
 }
 
-pub fn is_valid_identifier_char(ch: char) -> bool {
-    ch != '\0' && ch != '"'
+pub fn is_identifier_continue(ch: char) -> bool {
+    unicode_ident::is_xid_continue(ch)
 }
 
 #[allow(unused)]
@@ -14,11 +14,11 @@ pub fn is_valid_variable_prefix(ch: char) -> bool {
 }
 
 pub fn is_digits(ch: char) -> bool {
-    '0' <= ch && ch <= '9'
+    ch.is_ascii_digit()
 }
 
 pub fn is_hex(ch: char) -> bool {
-    '0' <= ch && ch <= '9' || 'a' <= ch && ch <= 'f' || 'A' <= ch && ch <= 'F'
+    ch.is_ascii_digit() || ('a'..='f').contains(&ch) || ('A'..='F').contains(&ch)
 }
 
 pub fn is_not_decimal_symbol(ch: char) -> bool {
@@ -0,0 +1,36 @@
+use crate::object::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// 一次函数调用的现场
+/// 局部变量放在格子里而不是栈上，闭包捕获的就是这些格子
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub closure: Rc<ClosureObject>,
+    pub ip: usize,
+    // 调用前栈的高度，返回时把栈恢复到这里
+    pub base_pointer: usize,
+    pub locals: Vec<FreeVariable>,
+}
+
+impl Frame {
+    pub fn new(closure: Rc<ClosureObject>, base_pointer: usize, args: Vec<Rc<dyn Object>>) -> Self {
+        let num_locals = closure.function.num_locals.max(args.len());
+        let mut locals = Vec::with_capacity(num_locals);
+        args.into_iter()
+            .for_each(|arg| locals.push(Rc::new(RefCell::new(Some(arg)))));
+        // 其余的局部变量在 let 之前都没有值
+        while locals.len() < num_locals {
+            locals.push(Rc::new(RefCell::new(None)));
+        }
+        Frame {
+            closure,
+            ip: 0,
+            base_pointer,
+            locals,
+        }
+    }
+    pub fn instructions(&self) -> &[u8] {
+        &self.closure.function.instructions
+    }
+}
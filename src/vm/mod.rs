@@ -0,0 +1,7 @@
+pub mod frame;
+pub use frame::*;
+#[allow(clippy::module_inception)]
+pub mod vm;
+pub use vm::*;
+#[allow(clippy::module_inception)]
+mod test;
@@ -0,0 +1,330 @@
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_integer_arithmetic() {
+        let cases = [
+            ("1", "1"),
+            ("1 + 2", "3"),
+            ("1 - 2", "-1"),
+            ("4 / 2", "2"),
+            ("50 / 2 * 2 + 10 - 5", "55"),
+            ("5 * (2 + 10)", "60"),
+            ("-5", "-5"),
+            ("-50 + 100 + -50", "0"),
+            ("!(1 < 2)", "false"),
+            ("(1 > 2) == false", "true"),
+        ];
+        cases.iter().for_each(|(input, out)| {
+            assert_eq!(run_vm(input).unwrap().inspect(), *out);
+        });
+    }
+
+    #[test]
+    fn test_closures() {
+        let cases = [
+            (
+                "let newAdder = fn(a) { fn(b) { a + b } }; let addTwo = newAdder(2); addTwo(3);",
+                "5",
+            ),
+            (
+                r#"
+            let newAdderOuter = fn(a, b) {
+                let c = a + b;
+                fn(d) {
+                    let e = d + c;
+                    fn(f) { e + f; };
+                };
+            };
+            let newAdderInner = newAdderOuter(1, 2);
+            let adder = newAdderInner(3);
+            adder(8);
+            "#,
+                "14",
+            ),
+            (
+                r#"
+            let wrapper = fn() {
+                let countDown = fn(x) {
+                    if (x == 0) { return 0; } else { countDown(x - 1); }
+                };
+                countDown(1);
+            };
+            wrapper();
+            "#,
+                "0",
+            ),
+            (
+                "let fib = fn(x) { if (x < 2) { x } else { fib(x - 1) + fib(x - 2) } }; fib(15);",
+                "610",
+            ),
+            // 闭包和外层函数共享变量，重新 let 之后闭包能看到新值
+            (
+                "let f = fn() { let a = 1; let g = fn() { a }; let a = 2; g() }; f();",
+                "2",
+            ),
+        ];
+        cases.iter().for_each(|(input, out)| {
+            assert_eq!(run_vm(input).unwrap().inspect(), *out);
+        });
+    }
+
+    #[test]
+    fn test_vm_errors() {
+        let cases = [
+            (
+                "fn(a) { a }()",
+                "wrong number of arguments to anonymous function: want=1, got=0",
+            ),
+            (
+                "let f = fn(a, b) { a }; f(1)",
+                "wrong number of arguments to `f`: want=2, got=1",
+            ),
+            (
+                "fn g() { 1 }; g(1)",
+                "wrong number of arguments to `g`: want=0, got=1",
+            ),
+            ("1()", "calling non-function: INTEGER"),
+            ("let f = fn() { f() }; f();", "stack overflow"),
+            ("let f = fn(x) { map([x], f) }; f(1)", "stack overflow"),
+            ("let f = fn() { g }; f()", "identifier not found: g"),
+            ("for (x in true) { x }", "not iterable: BOOLEAN"),
+        ];
+        cases.iter().for_each(|(input, out)| {
+            let r = ErrorObject::try_from(run_vm(input).unwrap());
+            assert!(r.is_ok());
+            assert_eq!(r.unwrap().message, *out);
+        });
+    }
+
+    /// 错误的位置和调用栈和 evaluator 一致，catch 到的 hash 也一样
+    #[test]
+    fn test_vm_error_location() {
+        let cases = [
+            "5 + true;",
+            "let a = 1;\nlet b = a - true;",
+            "let f = fn() {\n  foobar\n};\nf();",
+            "if (true) { -true }",
+            "let inner = fn() {\n  throw \"bad\";\n};\nlet outer = fn() { inner() };\nouter();",
+            "let f = fn(a) { a }; let g = fn() { f() }; g()",
+            "let f = fn(n) { if (n == 0) { [][\"x\"] } else { f(n - 1) } }; f(3)",
+            "let f = fn() { f() }; f()",
+            "fn(x) { x / 0 }(1)",
+            "let f = fn(x) { x + \"a\" }; map([1], f)",
+            "let f = fn(x) { g(x) }; let g = fn(x) { -x }; map([\"s\"], f)",
+            "for (x in true) { x }",
+            "let h = {}; h[[1]] = 1",
+            "try { 1 / 0 } finally { 2 }",
+            "let f = fn() { try { 1 / 0 } catch { throw \"x\" } finally { 3 } }; f()",
+            "let f = fn() { 1 + true }; let g = fn() { try { f() } catch (e) { throw e } }; g()",
+            "len(1, 2)",
+            "{1: 2, [1]: 3}",
+            "let f = fn() { map([1], fn(x) { g(x) }) }; let g = fn(x) { x + true }; f()",
+            "break;",
+            "if (true) {\n  continue\n}",
+            "let f = fn() {\n  break\n};\nlet g = fn() { f() };\ng()",
+            "x += 1",
+            "let f = fn() {\n  y = 1\n};\nf()",
+        ];
+        cases.iter().for_each(|input| {
+            let expected = ErrorObject::try_from(run_eval(input).unwrap()).unwrap();
+            let got = ErrorObject::try_from(run_vm(input).unwrap()).unwrap();
+            assert_eq!(got.inspect(), expected.inspect(), "{}", input);
+            assert_eq!(got.span, expected.span, "{}", input);
+            assert_eq!(got.stack, expected.stack, "{}", input);
+
+            let caught = format!(
+                "let e = null; try {{ {} }} catch (err) {{ e = err; }} e",
+                input
+            );
+            let expected = run_eval(&caught).unwrap();
+            let got = run_vm(&caught).unwrap();
+            assert_eq!(got.inspect(), expected.inspect(), "{}", caught);
+        });
+    }
+
+    #[test]
+    fn test_vm_integer_overflow_promotion() {
+        let cases = [
+            "9223372036854775807 + 1",
+            "[2 ^^ 64, 2 ^^ 64 % 10, ~(2 ^^ 64), 2 ^^ 64 > 1]",
+            "let m = -9223372036854775807 - 1; -m",
+            "let x = 2 ^^ 62; x *= 4",
+            "2 ^^ 64 / 0",
+            "map([62, 63, 64], fn(n) { 2 ^^ n })",
+        ];
+        cases.iter().for_each(|input| {
+            let expected =
+                with_integer_overflow(IntegerOverflow::Promote, || run_eval(input)).unwrap();
+            let pr = Parser::new(Lexer::new(*input)).parse_program().unwrap();
+            let mut c = Compiler::new();
+            assert!(c.compile(&pr).is_ok());
+            let mut vm = Vm::new(c.bytecode());
+            vm.set_integer_overflow(IntegerOverflow::Promote);
+            let got = vm.run().unwrap();
+            assert_eq!(got.object_type(), expected.object_type(), "{}", input);
+            assert_eq!(got.inspect(), expected.inspect(), "{}", input);
+        });
+    }
+
+    #[test]
+    fn test_vm_integer_division_exact() {
+        let cases = [
+            "7 / 2",
+            "[6 / 3, 10n / 4, 1 / 3 + 2 / 3]",
+            "map([2, 3], fn(n) { 1 / n })",
+        ];
+        cases.iter().for_each(|input| {
+            let expected =
+                with_integer_division(IntegerDivision::Exact, || run_eval(input)).unwrap();
+            let pr = Parser::new(Lexer::new(*input)).parse_program().unwrap();
+            let mut c = Compiler::new();
+            assert!(c.compile(&pr).is_ok());
+            let mut vm = Vm::new(c.bytecode());
+            vm.set_integer_division(IntegerDivision::Exact);
+            let got = vm.run().unwrap();
+            assert_eq!(got.object_type(), expected.object_type(), "{}", input);
+            assert_eq!(got.inspect(), expected.inspect(), "{}", input);
+        });
+    }
+
+    #[test]
+    fn test_vm_max_call_depth() {
+        let run = |n: i64| {
+            let input = format!(
+                "let f = fn(n) {{ if (n == 0) {{ 0 }} else {{ f(n - 1) }} }}; f({})",
+                n
+            );
+            let pr = Parser::new(Lexer::new(&input)).parse_program().unwrap();
+            let mut c = Compiler::new();
+            assert!(c.compile(&pr).is_ok());
+            let mut vm = Vm::new(c.bytecode());
+            vm.set_max_call_depth(10);
+            vm.run().unwrap().inspect()
+        };
+        // 和 evaluator 一样只数函数调用，最外层的程序不算
+        assert_eq!(run(9), "0");
+        assert_eq!(run(10), "Error: stack overflow");
+    }
+
+    /// evaluator 的测试用例在 test_eval 里都会再用 VM 执行一遍
+    /// 这里是 evaluator 的测试里没有的组合，主要覆盖编译器的闭包捕获、跳转和 try，同样要和 eval 的结果一样
+    #[test]
+    fn test_same_result_as_evaluator() {
+        let cases = [
+            "let f = fn(x) { if (x < 0) { \"neg\" } else if (x == 0) { \"zero\" } else { \"pos\" } }; [f(-1), f(0), f(1)]",
+            "if (10 > 1) { if (10 > 1) { return 10; } return 1; }",
+            "let add = fn(a, b) { a + b; }; let applyFunc = fn(a, b, func) { func(a, b) }; applyFunc(2, 2, add);",
+            r#"
+        let map = fn(arr, f) {
+            let iter = fn(arr, acc) {
+                if (len(arr) == 0) {
+                    acc
+                } else {
+                    iter(rest(arr), push(acc, f(first(arr))));
+                }
+            };
+            iter(arr, []);
+        };
+        let a = [1,2,3];
+        map(a, fn(x) { x * 2 })"#,
+            r#"
+        let reduce = fn(arr, initial, f) {
+            let iter = fn(arr, result) {
+                if (len(arr) == 0) {
+                    result
+                } else {
+                    iter(rest(arr), f(result, first(arr)));
+                }
+            };
+            iter(arr, initial);
+        };
+        let sum = fn(arr) {
+            reduce(arr, 0, fn(x, y) { x + y })
+        };
+        sum([1,2,3])"#,
+            "6 & 3 | 8 ^ 1",
+            "1 + 0.5 * 3",
+            "-1.5 < 1",
+            "let f = fn(x) { x > 0 && x < 10 }; [f(5), f(20)]",
+            "let f = fn(n) { let i = 0; while (true) { if (i == n) { break; } let i = i + 1; } i }; f(3)",
+            "let f = fn() { let n = 1; let g = fn() { fn() { n *= 2 } }; let h = g(); h(); h(); n }; f()",
+            "let s = 0; let i = 0; while (i < 4) { i += 1; s += i; } s",
+            "let s = 0; for (k in {3: 1, 1: 2}) { s = s * 10 + k; } s",
+            r#"{"a": 1}[[1]]"#,
+            "let f = fn() { return; 1 }; f()",
+            "let f = fn(x) { if (x) { return } 2 }; [f(true), f(false)]",
+            r#""${1 + 2} ${[1, 2]} ${true} ${"b${1}"}""#,
+            r#"let f = fn(x) { "<${x}>" }; f(1) + f("\n")"#,
+            r#"let 名字 = "héllo"; [名字[1], len(名字), byte_len(名字), slice(名字, -4, 3)]"#,
+            r#""ab"[5]"#,
+            r#"let words = split("b a c"); format("{}: {}", len(words), join(words, ","))"#,
+            r#"[upper("x"), index_of("xyz", "z"), substr("hello", 1, 3), repeat("-", 2)]"#,
+            r#"format("{}")"#,
+            "let k = 10; map([1, 2], fn(x) { x + k })",
+            "let double = fn(x) { x * 2 }; reduce(map(range(4), double), fn(a, b) { a + b }, 1)",
+            "let s = 0; each([1, 2, 3], fn(x) { s = s + x; }); s",
+            "filter([1, 2, 3], fn(x) { x > 1 })",
+            "map([[1, 2], [3]], len)",
+            "let inc = fn(x) { x + 1 }; map([1], fn(x) { map([x], inc) })",
+            "map([1, 2], fn(x) { x + true })",
+            "1; let a = 2;",
+            "[null ?? 1, 0 ?? 1, false ?? 1, null ?? null ?? 2, 1 ?? missing]",
+            r#"let h = {"a": {"b": [1, 2]}}; [h?.a?.b?[1], h?.x?.y, null?[missing], null?.a ?? "none"]"#,
+            "[null == null, null != 0, {}[1] == null]",
+            r#"let h = {"b": 1, "a": 2}; h["c"] = 3; [keys(h), values(h), len(h), has(h, "c")]"#,
+            r#"let s = ""; for (k in {"z": 1, "a": 2}) { s += k; } [s, "${merge({1: 1}, {0: 0})}"]"#,
+            "[reverse(\"ab\"), slice([1, 2, 3], 1), concat([1], [2]), zip([1], [2]), contains([1, 2], 2)]",
+            r#"let r = []; try { 1 + true; } catch (e) { r = [e["kind"], e["message"]]; } r"#,
+            "try { 1 } catch { 2 }",
+            "let r = 0; for (x in [1, 2, 3]) { try { if (x == 2) { throw x; } r = r + x; } catch (e) { r = r + 10; } } r",
+            r#"let f = fn(n) { if (n == 0) { throw "x" } f(n - 1) }; let r = 0; try { f(3); } catch (e) { r = 1; } r"#,
+            "[7 % 3, -7 % 3, 7.5 % 2, 1 <= 1, 2 >= 3, 1.5 <= 2, ~5, +5, -+5, ~~7]",
+            r#"["a" < "b", "ab" < "abc", "b" > "abc", "x" <= "x", "x" >= "y"]"#,
+            "let m = -9223372036854775807 - 1; [m / -1]",
+            r#"let k = null; try { 1 / 0 } catch (e) { k = e["kind"]; } k"#,
+            "let x = 4611686018427387904; x *= 2",
+            "let i = 0; let s = 0; while (i <= 10) { if (i % 2 == 0) { s += i; } i += 1; } s",
+            r#"let r = 0; each([1, 2], fn(x) { try { throw x; } catch (e) { r = r + len(e["message"]); } }); r"#,
+            "[123n, 0xffffffffffffffffn + 1, 2n ^^ 100, -5n / 2, 7 == 7n, 1n + 0.5]",
+            "[rational(6, 4), rational(1, 3) + 1, rational(2, 3) ^^ -2, -rational(1, 2), rational(1, 2) < 1]",
+            r#"[int("ff", 16), big(1e20), to_string(255, 2), to_string(rational(1, 3)), int(2n ^^ 64)]"#,
+            "let f = fn() { if (false) { let y = 1 }; y }; f()",
+            "let y = 5; let f = fn() { if (false) { let y = 1 }; y }; f()",
+            "let y = 0; let f = fn(c) { if (c) { let y = 1 }; y += 10; y }; [f(true), f(false), y]",
+            "let f = fn() { if (false) { let y = 1 }; y = 2 }; f()",
+            "let f = fn() { for (x in []) { 1 }; x }; f()",
+            "let f = fn() { try { throw 1; let y = 2 } catch { y } }; f()",
+            "let f = fn() { let x = 1; let g = fn() { let r = x; let x = 2; r }; g() }; f()",
+            "let f = fn() { let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } }; let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; even(4) }; f()",
+            "let f = fn() { let g = fn() { x }; let x = 1; g() }; f()",
+            "let x = 5; let f = fn() { let g = fn() { x }; let a = g(); let x = 1; [a, g()] }; f()",
+            "let x = 5; let f = fn() { let a = x; let x = 1; [a, x] }; f()",
+            "let f = fn() { let r = []; let i = 0; while (i < 3) { if (i > 0) { r = push(r, y) }; let y = i; i += 1 } r }; f()",
+            "let f = fn() { let g = fn() { h = 2 }; g(); let h = 1; h }; f()",
+            "let f = fn() { let g = fn() { let k = fn() { y }; k }; let k = g(); let y = 3; k() }; f()",
+            "let f = fn() { let r = len(\"ab\"); let len = 1; [r, len] }; f()",
+        ];
+        cases.iter().for_each(|input| {
+            let expected = run_eval(input).unwrap();
+            let got = run_vm(input).unwrap();
+            assert_eq!(got.object_type(), expected.object_type(), "{}", input);
+            assert_eq!(got.inspect(), expected.inspect(), "{}", input);
+        });
+    }
+
+    fn run_eval(input: &str) -> Option<Rc<dyn Object>> {
+        let p = Parser::new(Lexer::new(input));
+        let pr = p.parse_program().unwrap();
+        eval(&pr, Rc::new(Context::new()))
+    }
+
+    fn run_vm(input: &str) -> Option<Rc<dyn Object>> {
+        let p = Parser::new(Lexer::new(input));
+        let pr = p.parse_program().unwrap();
+        let mut c = Compiler::new();
+        assert!(c.compile(&pr).is_ok());
+        Vm::new(c.bytecode()).run()
+    }
+}
@@ -0,0 +1,654 @@
+use crate::compiler::*;
+use crate::evaluator::*;
+use crate::vm::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+pub const STACK_SIZE: usize = 2048;
+pub const MAX_FRAMES: usize = 1024;
+
+type Globals = Rc<RefCell<Vec<Option<Rc<dyn Object>>>>>;
+
+/// OpTry 登记的错误处理：出错时回到这时的调用帧和栈高度，再跳到 catch_ip
+struct Handler {
+    catch_ip: usize,
+    frames: usize,
+    stack_len: usize,
+}
+
+/// 基于栈的虚拟机，执行 Compiler 产出的 Bytecode
+/// 运算的语义直接复用 evaluator 里的实现，保证两个后端的结果一致
+pub struct Vm {
+    // 内置函数回调闭包时会起一个新的 Vm，常量和全局变量和它共享
+    constants: Rc<Vec<Rc<dyn Object>>>,
+    global_names: Rc<Vec<String>>,
+    globals: Globals,
+
+    stack: Vec<Rc<dyn Object>>,
+    // OpClosure 之前由 OpCapture* 压入的变量格子
+    captures: Vec<FreeVariable>,
+    frames: Vec<Frame>,
+    // 函数调用超过这么多层时报 stack overflow
+    max_frames: usize,
+    integer_overflow: IntegerOverflow,
+    integer_division: IntegerDivision,
+    handlers: Vec<Handler>,
+
+    last_popped: Option<Rc<dyn Object>>,
+}
+
+impl Vm {
+    pub fn new(bytecode: Bytecode) -> Self {
+        let main = Rc::new(ClosureObject {
+            function: Rc::new(CompiledFunctionObject {
+                instructions: Rc::new(bytecode.instructions),
+                spans: Rc::new(bytecode.spans),
+                num_locals: 0,
+                num_parameters: 0,
+                name: None,
+                source: Rc::new(String::new()),
+            }),
+            free: vec![],
+        });
+        Vm {
+            constants: Rc::new(bytecode.constants),
+            global_names: Rc::new(bytecode.global_names),
+            globals: Rc::new(RefCell::new(vec![])),
+            stack: Vec::with_capacity(STACK_SIZE),
+            captures: vec![],
+            frames: vec![Frame::new(main, 0, vec![])],
+            max_frames: MAX_FRAMES,
+            integer_overflow: IntegerOverflow::default(),
+            integer_division: IntegerDivision::default(),
+            handlers: vec![],
+            last_popped: None,
+        }
+    }
+
+    /// 默认是 MAX_FRAMES
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_frames = depth;
+    }
+
+    /// 整数运算溢出时报错还是提升成 BigInteger，默认报错
+    pub fn set_integer_overflow(&mut self, mode: IntegerOverflow) {
+        self.integer_overflow = mode;
+    }
+
+    /// 整数除不尽时向 0 取整还是得到精确的 Rational，默认取整
+    pub fn set_integer_division(&mut self, mode: IntegerDivision) {
+        self.integer_division = mode;
+    }
+
+    /// 返回值和 evaluator::eval 保持一致：
+    /// 程序最后一个表达式的值，或者中途产生的 ErrorObject
+    pub fn run(&mut self) -> Option<Rc<dyn Object>> {
+        let caller = self.closure_caller();
+        let (overflow, division) = (self.integer_overflow, self.integer_division);
+        let result = with_integer_overflow(overflow, || {
+            with_integer_division(division, || with_closure_caller(caller, || self.execute()))
+        });
+        match result {
+            Ok(()) => Some(
+                self.last_popped
+                    .clone()
+                    .unwrap_or_else(|| NULLOBJ.with(|n| n.clone())),
+            ),
+            Err(err) => Some(err),
+        }
+    }
+
+    /// 内置函数回调 VM 的闭包时，在一个共享全局变量的新 Vm 里执行这次调用
+    fn closure_caller(&self) -> Rc<ClosureCaller> {
+        let constants = self.constants.clone();
+        let global_names = self.global_names.clone();
+        let globals = self.globals.clone();
+        let max_frames = self.max_frames;
+        let integer_overflow = self.integer_overflow;
+        let integer_division = self.integer_division;
+        // 回调会在宿主的栈上再起一个 Vm，和 evaluator 一样限制嵌套的层数
+        Rc::new(move |func, args| {
+            with_call_depth(max_frames, || {
+                let mut vm = Vm {
+                    constants: constants.clone(),
+                    global_names: global_names.clone(),
+                    globals: globals.clone(),
+                    stack: Vec::with_capacity(STACK_SIZE),
+                    captures: vec![],
+                    frames: vec![],
+                    max_frames,
+                    integer_overflow,
+                    integer_division,
+                    handlers: vec![],
+                    last_popped: None,
+                };
+                let num_args = args.len();
+                let called = vm
+                    .push(func.clone())
+                    .and_then(|_| args.into_iter().try_for_each(|a| vm.push(a)))
+                    .and_then(|_| vm.call_function(num_args));
+                let result = match called {
+                    // 内置函数直接得到结果，闭包要执行到最外层的 return
+                    Ok(()) if vm.frames.is_empty() => Ok(vm.pop()),
+                    Ok(()) => vm.execute().map(|_| {
+                        vm.last_popped
+                            .clone()
+                            .unwrap_or_else(|| NULLOBJ.with(|n| n.clone()))
+                    }),
+                    Err(err) => Err(err),
+                };
+                Some(result.unwrap_or_else(|err| err))
+            })
+        })
+    }
+
+    fn execute(&mut self) -> Result<(), Rc<dyn Object>> {
+        loop {
+            match self.step() {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(err) => self.handle_error(err)?,
+            }
+        }
+    }
+
+    /// 有 try 接住时回退到登记时的现场，把错误压栈，从 catch 继续执行
+    /// 错误穿过的每个调用帧都记到它的调用栈上，和 evaluator 一样最底下的那一帧不算
+    fn handle_error(&mut self, err: Rc<dyn Object>) -> Result<(), Rc<dyn Object>> {
+        let handler = self.handlers.pop();
+        let keep = handler.as_ref().map_or(1, |h| h.frames);
+        let err = match err.as_any().downcast_ref::<ErrorObject>() {
+            Some(e) if self.frames.len() > keep => {
+                let mut e = e.clone();
+                for i in (keep..self.frames.len()).rev() {
+                    e.stack.push(self.stack_frame(i));
+                }
+                Rc::new(e)
+            }
+            _ => err,
+        };
+        let Some(handler) = handler else {
+            return Err(err);
+        };
+        self.frames.truncate(handler.frames);
+        self.stack.truncate(handler.stack_len);
+        self.current_frame_mut().ip = handler.catch_ip;
+        self.push(err)
+    }
+
+    /// 第 i 个调用帧对应的调用
+    fn stack_frame(&self, i: usize) -> StackFrame {
+        call_site(&self.frames[i].closure.function, &self.frames[i - 1])
+    }
+
+    /// 执行一条指令，整个程序结束时返回 true
+    fn step(&mut self) -> Result<bool, Rc<dyn Object>> {
+        let function = self.current_frame().closure.function.clone();
+        let ins = function.instructions.as_slice();
+        let ip = self.current_frame().ip;
+        if ip >= ins.len() {
+            return Ok(true);
+        }
+        let op = ins[ip];
+        let def = lookup_definition(op)
+            .ok_or_else(|| new_error(ErrorKind::Error, format!("opcode {} undefined", op)))?;
+        let (operands, read) = read_operands(&def, &ins[ip + 1..]);
+        self.current_frame_mut().ip = ip + 1 + read;
+        // 和 evaluator 一样，错误第一次出现时记下产生它的指令的位置
+        self.execute_instruction(op, &def, &operands)
+            .map_err(|err| with_error_span(err, function.spans[ip]))
+    }
+
+    fn execute_instruction(
+        &mut self,
+        op: Opcode,
+        def: &Definition,
+        operands: &[usize],
+    ) -> Result<bool, Rc<dyn Object>> {
+        match op {
+            OP_CONSTANT => {
+                let c = self.constants[operands[0]].clone();
+                self.push(c)?;
+            }
+            OP_POP => {
+                self.last_popped = Some(self.pop());
+            }
+            OP_TRUE => self.push(TRUEOBJ.with(|v| v.clone()))?,
+            OP_FALSE => self.push(FALSEOBJ.with(|v| v.clone()))?,
+            OP_NULL => self.push(NULLOBJ.with(|v| v.clone()))?,
+            OP_ADD | OP_SUB | OP_MUL | OP_DIV | OP_EQUAL | OP_NOT_EQUAL | OP_GREATER_THAN
+            | OP_LESS_THAN | OP_BIT_AND | OP_BIT_OR | OP_BIT_XOR | OP_POW | OP_MOD
+            | OP_LESS_EQUAL | OP_GREATER_EQUAL => {
+                let right = self.pop();
+                let left = self.pop();
+                let operator = infix_operator(op);
+                let result =
+                    eval_infix_expression(operator, Some(left.clone()), Some(right.clone()))
+                        .ok_or_else(|| {
+                            new_error(
+                                ErrorKind::TypeError,
+                                format!(
+                                    "unknown operator: {} {} {}",
+                                    left.object_type(),
+                                    operator,
+                                    right.object_type()
+                                ),
+                            )
+                        })?;
+                self.push_checked(result)?;
+            }
+            OP_MINUS | OP_BANG | OP_PLUS | OP_BIT_NOT => {
+                let right = self.pop();
+                let operator = prefix_operator(op);
+                let result = eval_prefix_expression(operator, Some(right)).ok_or_else(|| {
+                    new_error(
+                        ErrorKind::TypeError,
+                        format!("unknown operator: {}", operator),
+                    )
+                })?;
+                self.push_checked(result)?;
+            }
+            OP_JUMP => {
+                self.current_frame_mut().ip = operands[0];
+            }
+            OP_JUMP_NULL => {
+                if self.stack.last().is_some_and(|v| v.as_any().is::<Null>()) {
+                    self.current_frame_mut().ip = operands[0];
+                }
+            }
+            OP_JUMP_NOT_NULL => {
+                if self.stack.last().is_some_and(|v| !v.as_any().is::<Null>()) {
+                    self.current_frame_mut().ip = operands[0];
+                } else {
+                    self.pop();
+                }
+            }
+            OP_JUMP_NOT_TRUTHY => {
+                let condition = self.pop();
+                if !is_truthy(Some(condition)) {
+                    self.current_frame_mut().ip = operands[0];
+                }
+            }
+            OP_SET_GLOBAL => {
+                let value = self.pop();
+                let index = operands[0];
+                let mut globals = self.globals.borrow_mut();
+                if globals.len() <= index {
+                    globals.resize(index + 1, None);
+                }
+                globals[index] = Some(value);
+                // 和 evaluator 一样，程序最后是 let 语句时结果是 null
+                if self.frames.len() == 1 {
+                    self.last_popped = Some(NULLOBJ.with(|n| n.clone()));
+                }
+            }
+            OP_GET_GLOBAL => {
+                let index = operands[0];
+                let value = self
+                    .globals
+                    .borrow()
+                    .get(index)
+                    .cloned()
+                    .flatten()
+                    .ok_or_else(|| {
+                        new_error(
+                            ErrorKind::NameError,
+                            format!(
+                                "identifier not found: {}",
+                                self.global_names.get(index).cloned().unwrap_or_default()
+                            ),
+                        )
+                    })?;
+                self.push(value)?;
+            }
+            OP_CHECK_GLOBAL => {
+                let index = operands[0];
+                if self
+                    .globals
+                    .borrow()
+                    .get(index)
+                    .cloned()
+                    .flatten()
+                    .is_none()
+                {
+                    return Err(new_error(
+                        ErrorKind::NameError,
+                        format!(
+                            "assignment to undeclared variable: {}",
+                            self.global_names.get(index).cloned().unwrap_or_default()
+                        ),
+                    ));
+                }
+            }
+            OP_SET_LOCAL => {
+                let value = self.pop();
+                *self.current_frame().locals[operands[0]].borrow_mut() = Some(value);
+            }
+            OP_GET_LOCAL => {
+                let value = read_cell(&self.current_frame().locals[operands[0]])?;
+                self.push(value)?;
+            }
+            OP_GET_FREE => {
+                let value = read_cell(&self.current_frame().closure.free[operands[0]])?;
+                self.push(value)?;
+            }
+            OP_SET_FREE => {
+                let value = self.pop();
+                *self.current_frame().closure.free[operands[0]].borrow_mut() = Some(value);
+            }
+            OP_JUMP_UNSET_LOCAL => {
+                if self.current_frame().locals[operands[1]].borrow().is_none() {
+                    self.current_frame_mut().ip = operands[0];
+                }
+            }
+            OP_JUMP_UNSET_FREE => {
+                if self.current_frame().closure.free[operands[1]]
+                    .borrow()
+                    .is_none()
+                {
+                    self.current_frame_mut().ip = operands[0];
+                }
+            }
+            OP_SET_INDEX => {
+                let value = self.pop();
+                let index = self.pop();
+                let left = self.pop();
+                let result = eval_index_assignment(
+                    left,
+                    index,
+                    infix_operator(operands[0] as Opcode),
+                    value,
+                );
+                self.push_checked(result)?;
+            }
+            OP_GET_BUILTIN => {
+                let name = self.constants[operands[0]].inspect();
+                let builtin = BUILTINS
+                    .with(|b| b.get(name.as_str()).cloned())
+                    .ok_or_else(|| {
+                        new_error(
+                            ErrorKind::NameError,
+                            format!("identifier not found: {}", name),
+                        )
+                    })?;
+                self.push(builtin)?;
+            }
+            OP_ARRAY => {
+                let elements = self.stack.split_off(self.stack.len() - operands[0]);
+                self.push(Rc::new(ArrayObject {
+                    elements: RefCell::new(elements),
+                }))?;
+            }
+            OP_TEMPLATE => {
+                let parts = self.stack.split_off(self.stack.len() - operands[0]);
+                self.push(concat_template(&parts))?;
+            }
+            OP_HASH_KEY => {
+                if let Some(key) = self.stack.last() {
+                    HashKey::try_from(key)
+                        .map_err(|message| new_error(ErrorKind::TypeError, message))?;
+                }
+            }
+            OP_HASH => {
+                let elements = self.stack.split_off(self.stack.len() - operands[0]);
+                let mut pairs = HashPairs::new();
+                for kv in elements.chunks(2) {
+                    let key = HashKey::try_from(&kv[0])
+                        .map_err(|message| new_error(ErrorKind::TypeError, message))?;
+                    pairs.insert(key, kv[1].clone());
+                }
+                self.push(Rc::new(HashObject::new(pairs)))?;
+            }
+            OP_INDEX => {
+                let index = self.pop();
+                let left = self.pop();
+                let result = eval_index_expression(left.clone(), index).ok_or_else(|| {
+                    new_error(
+                        ErrorKind::TypeError,
+                        format!("index operator not supported: {}", left.object_type()),
+                    )
+                })?;
+                self.push_checked(result)?;
+            }
+            OP_ITER => {
+                let iterable = self.pop();
+                let items = iterable_items(&iterable)?;
+                self.push(Rc::new(IteratorObject {
+                    items,
+                    index: Cell::new(0),
+                }))?;
+            }
+            OP_ITER_NEXT => {
+                let next = self
+                    .stack
+                    .last()
+                    .and_then(|it| it.as_any().downcast_ref::<IteratorObject>())
+                    .map(|it| it.next());
+                match next {
+                    Some(Some(item)) => self.push(item)?,
+                    Some(None) => self.current_frame_mut().ip = operands[0],
+                    None => return Err(new_error(ErrorKind::Error, "iterator missing on stack")),
+                }
+            }
+            OP_CALL => self.call_function(operands[0])?,
+            OP_RETURN_VALUE => {
+                let value = self.pop();
+                if self.return_from_frame(value)? {
+                    return Ok(true);
+                }
+            }
+            OP_RETURN => {
+                if self.return_from_frame(NULLOBJ.with(|n| n.clone()))? {
+                    return Ok(true);
+                }
+            }
+            OP_TRY => self.handlers.push(Handler {
+                catch_ip: operands[0],
+                frames: self.frames.len(),
+                stack_len: self.stack.len(),
+            }),
+            OP_END_TRY => {
+                self.handlers.pop();
+            }
+            OP_THROW => {
+                let value = self.pop();
+                return Err(Rc::new(ErrorObject::from_thrown(&value)));
+            }
+            OP_OUTSIDE_LOOP => {
+                let statement = if operands[0] == 0 {
+                    "break"
+                } else {
+                    "continue"
+                };
+                let mut err =
+                    ErrorObject::new(ErrorKind::Error, format!("{} outside loop", statement));
+                // evaluator 在函数返回时才把它变成错误，位置是调用的地方
+                if self.frames.len() > 1 {
+                    err.span = Some(self.stack_frame(self.frames.len() - 1).span);
+                }
+                return Err(Rc::new(err));
+            }
+            OP_CATCH => {
+                let value = self.pop();
+                let value = match value.as_any().downcast_ref::<ErrorObject>() {
+                    Some(err) => err.to_hash(),
+                    None => value,
+                };
+                self.push(value)?;
+            }
+            OP_CAPTURE_LOCAL => {
+                let cell = self.current_frame().locals[operands[0]].clone();
+                self.captures.push(cell);
+            }
+            OP_CAPTURE_FREE => {
+                let cell = self.current_frame().closure.free[operands[0]].clone();
+                self.captures.push(cell);
+            }
+            OP_CLOSURE => {
+                let function = self.constants[operands[0]]
+                    .as_any()
+                    .downcast_ref::<CompiledFunctionObject>()
+                    .cloned()
+                    .ok_or_else(|| {
+                        new_error(ErrorKind::Error, format!("not a function: {}", operands[0]))
+                    })?;
+                let free = self.captures.split_off(self.captures.len() - operands[1]);
+                self.push(Rc::new(ClosureObject {
+                    function: Rc::new(function),
+                    free,
+                }))?;
+            }
+            _ => {
+                return Err(new_error(
+                    ErrorKind::Error,
+                    format!("opcode {} undefined", def.name),
+                ))
+            }
+        }
+        Ok(false)
+    }
+
+    fn call_function(&mut self, num_args: usize) -> Result<(), Rc<dyn Object>> {
+        let args = self.stack.split_off(self.stack.len() - num_args);
+        let callee = self.pop();
+        if let Some(closure) = callee.as_any().downcast_ref::<ClosureObject>() {
+            let err = if closure.function.num_parameters != num_args {
+                Some(arity_error(
+                    closure.function.name.as_deref(),
+                    closure.function.num_parameters,
+                    num_args,
+                ))
+            } else if !self.frames.is_empty() && call_depth() + self.frames.len() > self.max_frames
+            {
+                // 和 evaluator 一样只数函数调用：最底下那一帧是主程序，或者是 with_call_depth 已经计过数的回调
+                Some(ErrorObject::new(ErrorKind::Error, "stack overflow"))
+            } else {
+                None
+            };
+            // 和 evaluator 一样，没能开始执行的调用也记到调用栈上
+            if let Some(mut err) = err {
+                if let Some(caller) = self.frames.last() {
+                    err.stack.push(call_site(&closure.function, caller));
+                }
+                return Err(Rc::new(err));
+            }
+            let frame = Frame::new(Rc::new(closure.clone()), self.stack.len(), args);
+            self.frames.push(frame);
+            return Ok(());
+        }
+        if let Some(builtin) = callee.as_any().downcast_ref::<BuiltinObject>() {
+            let result = (builtin.func)(args).unwrap_or_else(|| NULLOBJ.with(|n| n.clone()));
+            return self.push_checked(result);
+        }
+        Err(new_error(
+            ErrorKind::TypeError,
+            format!("calling non-function: {}", callee.object_type()),
+        ))
+    }
+
+    /// 从当前函数返回，如果是最外层的 return 则整个程序结束，返回 true
+    fn return_from_frame(&mut self, value: Rc<dyn Object>) -> Result<bool, Rc<dyn Object>> {
+        if self.frames.len() == 1 {
+            self.last_popped = Some(value);
+            return Ok(true);
+        }
+        let frame = self.frames.pop().unwrap();
+        self.stack.truncate(frame.base_pointer);
+        self.push(value)?;
+        Ok(false)
+    }
+
+    fn current_frame(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    fn current_frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn push(&mut self, obj: Rc<dyn Object>) -> Result<(), Rc<dyn Object>> {
+        if self.stack.len() >= STACK_SIZE {
+            return Err(new_error(ErrorKind::Error, "stack overflow"));
+        }
+        self.stack.push(obj);
+        Ok(())
+    }
+
+    /// 和 evaluator 一样，运算得到 ErrorObject 时整个程序就此结束
+    fn push_checked(&mut self, obj: Rc<dyn Object>) -> Result<(), Rc<dyn Object>> {
+        if is_error(&obj) {
+            return Err(obj);
+        }
+        self.push(obj)
+    }
+
+    fn pop(&mut self) -> Rc<dyn Object> {
+        self.stack
+            .pop()
+            .unwrap_or_else(|| NULLOBJ.with(|n| n.clone()))
+    }
+}
+
+fn infix_operator(op: Opcode) -> &'static str {
+    match op {
+        OP_ADD => "+",
+        OP_SUB => "-",
+        OP_MUL => "*",
+        OP_DIV => "/",
+        OP_EQUAL => "==",
+        OP_NOT_EQUAL => "!=",
+        OP_GREATER_THAN => ">",
+        OP_LESS_THAN => "<",
+        OP_BIT_AND => "&",
+        OP_BIT_OR => "|",
+        OP_BIT_XOR => "^",
+        OP_POW => "^^",
+        OP_MOD => "%",
+        OP_LESS_EQUAL => "<=",
+        OP_GREATER_EQUAL => ">=",
+        _ => "",
+    }
+}
+
+fn prefix_operator(op: Opcode) -> &'static str {
+    match op {
+        OP_MINUS => "-",
+        OP_BANG => "!",
+        OP_PLUS => "+",
+        OP_BIT_NOT => "~",
+        _ => "",
+    }
+}
+
+/// caller 调用 function 的位置：caller 的 ip 已经越过了 OpCall，OpCall 的最后一个字节记着调用的位置
+fn call_site(function: &CompiledFunctionObject, caller: &Frame) -> StackFrame {
+    StackFrame {
+        function: function
+            .name
+            .clone()
+            .unwrap_or_else(|| "<anonymous>".to_string()),
+        span: caller.closure.function.spans[caller.ip - 1],
+    }
+}
+
+/// 已经有位置的错误不再覆盖，比如从被调用的函数里传出来的
+fn with_error_span(err: Rc<dyn Object>, span: Span) -> Rc<dyn Object> {
+    match err.as_any().downcast_ref::<ErrorObject>() {
+        Some(e) if e.span.is_none() && span.line > 0 => Rc::new(ErrorObject {
+            span: Some(span),
+            ..e.clone()
+        }),
+        _ => err,
+    }
+}
+
+fn new_error(kind: ErrorKind, message: impl Into<String>) -> Rc<dyn Object> {
+    Rc::new(ErrorObject::new(kind, message))
+}
+
+/// 编译器只在变量一定有值时才直接读格子，没有值的格子先用 OpJumpUnset* 跳过
+fn read_cell(cell: &FreeVariable) -> Result<Rc<dyn Object>, Rc<dyn Object>> {
+    cell.borrow()
+        .clone()
+        .ok_or_else(|| new_error(ErrorKind::Error, "read of an unset local variable"))
+}